@@ -0,0 +1,21 @@
+extern crate calc;
+
+use std::io;
+use std::process;
+use calc::lsp::Server;
+
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    let code = match Server::new().run(stdin.lock(), stdout.lock()) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("calc-lsp: {}", err);
+            1
+        }
+    };
+
+    process::exit(code);
+}
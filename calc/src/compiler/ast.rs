@@ -1,12 +1,20 @@
-use std::rc::Rc;
 use super::location::*;
+use super::shared::Shared;
 
 
-#[derive(Debug, PartialEq)]
+/// Nodes nested deeper are rejected by the parser and by the JSON loader
+/// alike, so that the recursive passes over the tree can't overflow the
+/// stack. The root of a tree is at depth 0.
+pub const MAX_AST_DEPTH: usize = 128;
+
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum AST {
     Block {
         loc: Loc,
         body: Vec<AST>,
+        end: Loc,   // closing `}` or the end of the text
     },
 
     // statements
@@ -29,7 +37,43 @@ pub enum AST {
 
     Func {
         loc: Loc,
-        decl: Rc<FuncDecl>,
+        decl: Shared<FuncDecl>,
+    },
+
+    Yield {
+        loc: Loc,
+        val: Box<AST>,
+    },
+
+    For {
+        loc: Loc,
+        var: String,
+        iter: Box<AST>,
+        body: Box<AST>,
+    },
+
+    Throw {
+        loc: Loc,
+        val: Box<AST>,
+    },
+
+    Import {
+        loc: Loc,
+        path: String,
+        alias: String,
+    },
+
+    FromImport {
+        loc: Loc,
+        path: String,
+        names: Vec<String>,
+    },
+
+    Try {
+        loc: Loc,
+        body: Box<AST>,
+        catch: Option<(String, Box<AST>)>,  // error variable and handler
+        finally: Option<Box<AST>>,
     },
 
     // expression
@@ -38,6 +82,11 @@ pub enum AST {
         val: i32,
     },
 
+    Float {
+        loc: Loc,
+        val: f64,
+    },
+
     Str {
         loc: Loc,
         val: String,
@@ -60,14 +109,59 @@ pub enum AST {
         left: Box<AST>,
         right: Box<AST>,
     },
+
+    List {
+        loc: Loc,
+        items: Vec<AST>,
+    },
+
+    Map {
+        loc: Loc,
+        items: Vec<(String, AST)>,
+    },
+
+    Index {
+        loc: Loc,
+        obj: Box<AST>,
+        index: Box<AST>,
+    },
+
+    SetIndex {
+        loc: Loc,
+        obj: Box<AST>,
+        index: Box<AST>,
+        val: Box<AST>,
+    },
+
+    GetProp {
+        loc: Loc,
+        obj: Box<AST>,
+        name: String,
+    },
+
+    SetProp {
+        loc: Loc,
+        obj: Box<AST>,
+        name: String,
+        val: Box<AST>,
+    },
+
+    MethodCall {
+        loc: Loc,
+        obj: Box<AST>,
+        name: String,
+        args: Vec<AST>,
+    },
 }
 
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FuncDecl {
     pub name: String,
     pub args: Vec<String>,
+    pub arg_locs: Vec<Loc>, // of the names in `args`
     pub body: AST,
+    pub generator: bool,    // body contains `yield` statement
 }
 
 
@@ -81,11 +175,25 @@ impl Location for AST {
             Assign  { ref loc, .. } => loc,
             Return  { ref loc, .. } => loc,
             Func    { ref loc, .. } => loc,
+            Yield   { ref loc, .. } => loc,
+            For     { ref loc, .. } => loc,
+            Throw   { ref loc, .. } => loc,
+            Try     { ref loc, .. } => loc,
+            Import  { ref loc, .. } => loc,
+            FromImport { ref loc, .. } => loc,
             Num     { ref loc, .. } => loc,
+            Float   { ref loc, .. } => loc,
             Str     { ref loc, .. } => loc,
             Var     { ref loc, .. } => loc,
             Call    { ref loc, .. } => loc,
             BinOp   { ref loc, .. } => loc,
+            List    { ref loc, .. } => loc,
+            Map     { ref loc, .. } => loc,
+            Index   { ref loc, .. } => loc,
+            SetIndex { ref loc, .. } => loc,
+            GetProp { ref loc, .. } => loc,
+            SetProp { ref loc, .. } => loc,
+            MethodCall { ref loc, .. } => loc,
         }
     }
 }
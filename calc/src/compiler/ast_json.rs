@@ -0,0 +1,409 @@
+//! JSON form of the `AST` for external tools.
+//!
+//! The document is `{"version": 1, "ast": <node>}`. Every node is an object
+//! with `"type"` (the `AST` variant name), `"loc"` and the fields of the
+//! variant under the same names:
+//!
+//! | type         | fields                                                   |
+//! |--------------|----------------------------------------------------------|
+//! | `Block`      | `body`: [node], `end`: loc                               |
+//! | `DeclVar`    | `name`: str, `init`: node                                |
+//! | `Assign`     | `name`: str, `init`: node                                |
+//! | `Return`     | `ret`: node                                              |
+//! | `Func`       | `decl`: func                                             |
+//! | `Yield`      | `val`: node                                              |
+//! | `For`        | `var`: str, `iter`: node, `body`: node                   |
+//! | `Throw`      | `val`: node                                              |
+//! | `Import`     | `path`: str, `alias`: str                                |
+//! | `FromImport` | `path`: str, `names`: [str]                              |
+//! | `Try`        | `body`: node, `catch`: {`var`: str, `body`: node} or null, `finally`: node or null |
+//! | `Num`        | `val`: integer                                           |
+//! | `Float`      | `val`: number                                            |
+//! | `Str`        | `val`: str                                               |
+//! | `Var`        | `name`: str                                              |
+//! | `Call`       | `name`: str, `args`: [node]                              |
+//! | `BinOp`      | `op`: str of one char, `left`: node, `right`: node       |
+//! | `List`       | `items`: [node]                                          |
+//! | `Map`        | `items`: [{`key`: str, `val`: node}]                     |
+//! | `Index`      | `obj`: node, `index`: node                               |
+//! | `SetIndex`   | `obj`: node, `index`: node, `val`: node                  |
+//! | `GetProp`    | `obj`: node, `name`: str                                 |
+//! | `SetProp`    | `obj`: node, `name`: str, `val`: node                    |
+//! | `MethodCall` | `obj`: node, `name`: str, `args`: [node]                 |
+//!
+//! A loc is `{"file": str, "line": int, "col": int, "start": int, "end": int}`
+//! as in `Loc`, a func is `{"name": str, "args": [str], "arg_locs": [loc],
+//! "body": node, "generator": bool}` as in `FuncDecl`. Without `arg_locs`
+//! the arguments get the loc of the function. Unknown members are ignored, the
+//! version changes only when existing members change their meaning. Nodes
+//! nested deeper than `MAX_AST_DEPTH` are not loaded, as the parser doesn't
+//! produce them.
+
+use std::convert::TryFrom;
+use compiler::*;
+use compiler::json::Json;
+
+
+pub const AST_JSON_VERSION: i64 = 1;
+
+pub fn ast_to_json(ast: &AST) -> Json {
+    Json::object(vec![
+        ("version", Json::Int(AST_JSON_VERSION)),
+        ("ast", node_to_json(ast)),
+    ])
+}
+
+
+pub fn ast_from_json(doc: &Json) -> Result<AST, String> {
+    let version = field(doc, "", "version")?.as_i64();
+
+    if version != Some(AST_JSON_VERSION) {
+        return Err(format!("'version': {} expected", AST_JSON_VERSION));
+    }
+
+    node_from_json(field(doc, "", "ast")?, "ast", 0)
+}
+
+
+fn node_to_json(ast: &AST) -> Json {
+    use self::AST::*;
+
+    let (kind, fields): (&str, Vec<(&str, Json)>) = match *ast {
+        Block{ ref body, ref end, .. } => ("Block", vec![
+            ("body", nodes_to_json(body)),
+            ("end", loc_to_json(end)),
+        ]),
+        DeclVar{ ref name, ref init, .. } => ("DeclVar", vec![
+            ("name", str_to_json(name)),
+            ("init", node_to_json(init)),
+        ]),
+        Assign{ ref name, ref init, .. } => ("Assign", vec![
+            ("name", str_to_json(name)),
+            ("init", node_to_json(init)),
+        ]),
+        Return{ ref ret, .. } => ("Return", vec![("ret", node_to_json(ret))]),
+        Func{ ref decl, .. } => ("Func", vec![("decl", Json::object(vec![
+            ("name", str_to_json(&decl.name)),
+            ("args", Json::Array(decl.args.iter().map(|arg| str_to_json(arg)).collect())),
+            ("arg_locs", Json::Array(decl.arg_locs.iter().map(loc_to_json).collect())),
+            ("body", node_to_json(&decl.body)),
+            ("generator", Json::Bool(decl.generator)),
+        ]))]),
+        Yield{ ref val, .. } => ("Yield", vec![("val", node_to_json(val))]),
+        For{ ref var, ref iter, ref body, .. } => ("For", vec![
+            ("var", str_to_json(var)),
+            ("iter", node_to_json(iter)),
+            ("body", node_to_json(body)),
+        ]),
+        Throw{ ref val, .. } => ("Throw", vec![("val", node_to_json(val))]),
+        Import{ ref path, ref alias, .. } => ("Import", vec![
+            ("path", str_to_json(path)),
+            ("alias", str_to_json(alias)),
+        ]),
+        FromImport{ ref path, ref names, .. } => ("FromImport", vec![
+            ("path", str_to_json(path)),
+            ("names", Json::Array(names.iter().map(|name| str_to_json(name)).collect())),
+        ]),
+        Try{ ref body, ref catch, ref finally, .. } => ("Try", vec![
+            ("body", node_to_json(body)),
+            ("catch", match *catch {
+                Some((ref var, ref handler)) => Json::object(vec![
+                    ("var", str_to_json(var)),
+                    ("body", node_to_json(handler)),
+                ]),
+                None => Json::Null,
+            }),
+            ("finally", finally.as_ref().map_or(Json::Null, |body| node_to_json(body))),
+        ]),
+        Num{ val, .. } => ("Num", vec![("val", Json::Int(val as i64))]),
+        Float{ val, .. } => ("Float", vec![("val", Json::Float(val))]),
+        Str{ ref val, .. } => ("Str", vec![("val", str_to_json(val))]),
+        Var{ ref name, .. } => ("Var", vec![("name", str_to_json(name))]),
+        Call{ ref name, ref args, .. } => ("Call", vec![
+            ("name", str_to_json(name)),
+            ("args", nodes_to_json(args)),
+        ]),
+        BinOp{ op, ref left, ref right, .. } => ("BinOp", vec![
+            ("op", Json::Str(op.to_string())),
+            ("left", node_to_json(left)),
+            ("right", node_to_json(right)),
+        ]),
+        List{ ref items, .. } => ("List", vec![("items", nodes_to_json(items))]),
+        Map{ ref items, .. } => ("Map", vec![("items", Json::Array(items.iter()
+            .map(|(key, val)| Json::object(vec![
+                ("key", str_to_json(key)),
+                ("val", node_to_json(val)),
+            ]))
+            .collect()))]),
+        Index{ ref obj, ref index, .. } => ("Index", vec![
+            ("obj", node_to_json(obj)),
+            ("index", node_to_json(index)),
+        ]),
+        SetIndex{ ref obj, ref index, ref val, .. } => ("SetIndex", vec![
+            ("obj", node_to_json(obj)),
+            ("index", node_to_json(index)),
+            ("val", node_to_json(val)),
+        ]),
+        GetProp{ ref obj, ref name, .. } => ("GetProp", vec![
+            ("obj", node_to_json(obj)),
+            ("name", str_to_json(name)),
+        ]),
+        SetProp{ ref obj, ref name, ref val, .. } => ("SetProp", vec![
+            ("obj", node_to_json(obj)),
+            ("name", str_to_json(name)),
+            ("val", node_to_json(val)),
+        ]),
+        MethodCall{ ref obj, ref name, ref args, .. } => ("MethodCall", vec![
+            ("obj", node_to_json(obj)),
+            ("name", str_to_json(name)),
+            ("args", nodes_to_json(args)),
+        ]),
+    };
+
+    let mut members = vec![
+        ("type", str_to_json(kind)),
+        ("loc", loc_to_json(ast.loc())),
+    ];
+    members.extend(fields);
+
+    Json::object(members)
+}
+
+
+fn nodes_to_json(nodes: &[AST]) -> Json {
+    Json::Array(nodes.iter().map(node_to_json).collect())
+}
+
+
+fn str_to_json(text: &str) -> Json {
+    Json::Str(String::from(text))
+}
+
+
+fn loc_to_json(loc: &Loc) -> Json {
+    Json::object(vec![
+        ("file", str_to_json(&loc.filename)),
+        ("line", Json::Int(loc.line as i64)),
+        ("col", Json::Int(loc.col as i64)),
+        ("start", Json::Int(loc.start as i64)),
+        ("end", Json::Int(loc.end as i64)),
+    ])
+}
+
+
+/// Member of the object, `path` of the object is used in errors.
+fn field<'a>(obj: &'a Json, path: &str, name: &str) -> Result<&'a Json, String> {
+    match obj.get(name) {
+        Some(val) => Ok(val),
+        None if path.is_empty() => Err(format!("'{}': member expected", name)),
+        None => Err(format!("{}: '{}': member expected", path, name)),
+    }
+}
+
+
+fn invalid(path: &str, name: &str, expected: &str) -> String {
+    format!("{}.{}: {} expected", path, name, expected)
+}
+
+
+fn get_str(obj: &Json, path: &str, name: &str) -> Result<String, String> {
+    field(obj, path, name)?.as_str()
+        .map(String::from)
+        .ok_or_else(|| invalid(path, name, "string"))
+}
+
+
+fn get_int(obj: &Json, path: &str, name: &str) -> Result<i64, String> {
+    field(obj, path, name)?.as_i64()
+        .ok_or_else(|| invalid(path, name, "integer"))
+}
+
+
+fn get_array<'a>(obj: &'a Json, path: &str, name: &str) -> Result<&'a Vec<Json>, String> {
+    field(obj, path, name)?.as_array()
+        .ok_or_else(|| invalid(path, name, "array"))
+}
+
+
+fn get_strs(obj: &Json, path: &str, name: &str) -> Result<Vec<String>, String> {
+    get_array(obj, path, name)?.iter().enumerate()
+        .map(|(idx, item)| {
+            item.as_str()
+                .map(String::from)
+                .ok_or_else(|| format!("{}.{}[{}]: string expected", path, name, idx))
+        })
+        .collect()
+}
+
+
+/// Child node of the node at `depth`.
+fn get_node(obj: &Json, path: &str, name: &str, depth: usize) -> Result<Box<AST>, String> {
+    let json = field(obj, path, name)?;
+    node_from_json(json, &format!("{}.{}", path, name), depth + 1).map(Box::new)
+}
+
+
+fn get_nodes(obj: &Json, path: &str, name: &str, depth: usize) -> Result<Vec<AST>, String> {
+    get_array(obj, path, name)?.iter().enumerate()
+        .map(|(idx, item)| node_from_json(item, &format!("{}.{}[{}]", path, name, idx), depth + 1))
+        .collect()
+}
+
+
+fn get_loc(obj: &Json, path: &str, name: &str) -> Result<Loc, String> {
+    loc_from_json(field(obj, path, name)?, &format!("{}.{}", path, name))
+}
+
+
+fn loc_from_json(json: &Json, path: &str) -> Result<Loc, String> {
+    let filename = get_str(json, path, "file")?;
+    let number = |name| -> Result<i64, String> {
+        let val = get_int(json, path, name)?;
+        if val < 0 {
+            return Err(format!("{}.{}: non-negative integer expected", path, name));
+        }
+        Ok(val)
+    };
+
+    Ok(Loc::new(&filename, number("line")? as i32)
+        .at(number("col")? as i32, number("start")? as usize, number("end")? as usize))
+}
+
+
+fn node_from_json(json: &Json, path: &str, depth: usize) -> Result<AST, String> {
+    use self::AST::*;
+
+    if depth == MAX_AST_DEPTH {
+        return Err(format!("{}: nesting is too deep", path));
+    }
+
+    let kind = get_str(json, path, "type")?;
+    let loc = get_loc(json, path, "loc")?;
+
+    let r = match kind.as_str() {
+        "Block" => Block{
+            loc,
+            body: get_nodes(json, path, "body", depth)?,
+            end: get_loc(json, path, "end")?,
+        },
+        "DeclVar" => DeclVar{ loc, name: get_str(json, path, "name")?, init: get_node(json, path, "init", depth)? },
+        "Assign" => Assign{ loc, name: get_str(json, path, "name")?, init: get_node(json, path, "init", depth)? },
+        "Return" => Return{ loc, ret: get_node(json, path, "ret", depth)? },
+        "Func" => {
+            let decl = field(json, path, "decl")?;
+            let path = format!("{}.decl", path);
+
+            let generator = field(decl, &path, "generator")?.as_bool()
+                .ok_or_else(|| invalid(&path, "generator", "boolean"))?;
+
+            let args = get_strs(decl, &path, "args")?;
+            let arg_locs = match decl.get("arg_locs") {
+                Some(_) => get_array(decl, &path, "arg_locs")?.iter().enumerate()
+                    .map(|(idx, item)| loc_from_json(item, &format!("{}.arg_locs[{}]", path, idx)))
+                    .collect::<Result<Vec<Loc>, String>>()?,
+                None => vec![loc.clone(); args.len()],
+            };
+
+            if arg_locs.len() != args.len() {
+                return Err(format!("{}.arg_locs: {} locs expected", path, args.len()));
+            }
+
+            Func{
+                loc,
+                decl: Shared::new(FuncDecl {
+                    name: get_str(decl, &path, "name")?,
+                    args, arg_locs,
+                    body: *get_node(decl, &path, "body", depth)?,
+                    generator,
+                }),
+            }
+        }
+        "Yield" => Yield{ loc, val: get_node(json, path, "val", depth)? },
+        "For" => For{
+            loc,
+            var: get_str(json, path, "var")?,
+            iter: get_node(json, path, "iter", depth)?,
+            body: get_node(json, path, "body", depth)?,
+        },
+        "Throw" => Throw{ loc, val: get_node(json, path, "val", depth)? },
+        "Import" => Import{ loc, path: get_str(json, path, "path")?, alias: get_str(json, path, "alias")? },
+        "FromImport" => FromImport{ loc, path: get_str(json, path, "path")?, names: get_strs(json, path, "names")? },
+        "Try" => {
+            let catch = match *field(json, path, "catch")? {
+                Json::Null => None,
+                ref catch => {
+                    let path = format!("{}.catch", path);
+                    Some((get_str(catch, &path, "var")?, get_node(catch, &path, "body", depth)?))
+                }
+            };
+
+            let finally = match *field(json, path, "finally")? {
+                Json::Null => None,
+                _ => Some(get_node(json, path, "finally", depth)?),
+            };
+
+            if catch.is_none() && finally.is_none() {
+                return Err(format!("{}: 'catch' or 'finally' expected", path));
+            }
+
+            Try{ loc, body: get_node(json, path, "body", depth)?, catch, finally }
+        }
+        "Num" => {
+            let val = get_int(json, path, "val")?;
+            let val = i32::try_from(val).map_err(|_| format!("{}.val: '{}': integer overflow", path, val))?;
+            Num{ loc, val }
+        }
+        "Float" => Float{
+            loc,
+            val: field(json, path, "val")?.as_f64().ok_or_else(|| invalid(path, "val", "number"))?,
+        },
+        "Str" => Str{ loc, val: get_str(json, path, "val")? },
+        "Var" => Var{ loc, name: get_str(json, path, "name")? },
+        "Call" => Call{ loc, name: get_str(json, path, "name")?, args: get_nodes(json, path, "args", depth)? },
+        "BinOp" => {
+            let op = get_str(json, path, "op")?;
+            let mut chars = op.chars();
+
+            let op = match (chars.next(), chars.next()) {
+                (Some(ch), None) if "+-*/%".contains(ch) => ch,
+                _ => return Err(format!("{}.op: '{}': unknown operator", path, op)),
+            };
+
+            BinOp{ loc, op, left: get_node(json, path, "left", depth)?, right: get_node(json, path, "right", depth)? }
+        }
+        "List" => List{ loc, items: get_nodes(json, path, "items", depth)? },
+        "Map" => {
+            let items = get_array(json, path, "items")?.iter().enumerate()
+                .map(|(idx, item)| {
+                    let path = format!("{}.items[{}]", path, idx);
+                    Ok((get_str(item, &path, "key")?, *get_node(item, &path, "val", depth)?))
+                })
+                .collect::<Result<_, String>>()?;
+
+            Map{ loc, items }
+        }
+        "Index" => Index{ loc, obj: get_node(json, path, "obj", depth)?, index: get_node(json, path, "index", depth)? },
+        "SetIndex" => SetIndex{
+            loc,
+            obj: get_node(json, path, "obj", depth)?,
+            index: get_node(json, path, "index", depth)?,
+            val: get_node(json, path, "val", depth)?,
+        },
+        "GetProp" => GetProp{ loc, obj: get_node(json, path, "obj", depth)?, name: get_str(json, path, "name")? },
+        "SetProp" => SetProp{
+            loc,
+            obj: get_node(json, path, "obj", depth)?,
+            name: get_str(json, path, "name")?,
+            val: get_node(json, path, "val", depth)?,
+        },
+        "MethodCall" => MethodCall{
+            loc,
+            obj: get_node(json, path, "obj", depth)?,
+            name: get_str(json, path, "name")?,
+            args: get_nodes(json, path, "args", depth)?,
+        },
+        _ => return Err(format!("{}.type: '{}': unknown node type", path, kind)),
+    };
+
+    Ok(r)
+}
@@ -0,0 +1,338 @@
+//! Conversions between `Val` and Rust types, used to bind ordinary Rust
+//! closures as native functions (see `ExecContext::decl_typed_func`).
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use compiler::*;
+
+
+/// Rust types that could be extracted from script values.
+pub trait FromVal: Sized {
+    fn from_val(val: &Val) -> Result<Self, String>;
+
+    /// Whether the argument could be omitted (if it's trailing).
+    fn is_optional() -> bool {
+        false
+    }
+}
+
+
+/// Rust types that could be converted to script values.
+pub trait IntoVal {
+    fn into_val(self) -> Val;
+}
+
+
+/// Return types of typed native functions: `T` or `Result<T, E>`.
+pub trait IntoValResult {
+    fn into_val_result(self) -> Result<Val, String>;
+}
+
+
+/// Argument lists extracted from script values, implemented for tuples of
+/// `FromVal` types with up to 6 items, e.g. `let (a, b) = <(i32, String)>::from_args(&args)?`.
+pub trait FromArgs: Sized {
+    fn arity() -> Arity;
+    fn from_args(args: &[Val]) -> Result<Self, String>;
+}
+
+
+/// Rust closures that could be registered as native functions, implemented
+/// for `Fn(A, B, ...) -> R` with up to 6 arguments.
+pub trait TypedFunc<Args, R>: Threadsafe + 'static {
+    fn arity() -> Arity;
+    fn call(&self, args: Vec<Val>) -> Result<Val, String>;
+}
+
+
+pub fn mismatch(expected: &str, val: &Val) -> String {
+    format!("type mismatch, {} expected, found '{}'", expected, val)
+}
+
+
+impl FromVal for Val {
+    fn from_val(val: &Val) -> Result<Self, String> {
+        Ok(val.clone())
+    }
+}
+
+
+impl FromVal for i32 {
+    fn from_val(val: &Val) -> Result<Self, String> {
+        val.as_num().ok_or_else(|| mismatch("number", val))
+    }
+}
+
+
+impl FromVal for i64 {
+    fn from_val(val: &Val) -> Result<Self, String> {
+        i32::from_val(val).map(i64::from)
+    }
+}
+
+
+impl FromVal for usize {
+    fn from_val(val: &Val) -> Result<Self, String> {
+        let num = i32::from_val(val)?;
+
+        if num < 0 {
+            return Err(format!("non-negative number expected, found '{}'", num));
+        }
+
+        Ok(num as usize)
+    }
+}
+
+
+/// Integers are accepted as well.
+impl FromVal for f64 {
+    fn from_val(val: &Val) -> Result<Self, String> {
+        val.as_float().ok_or_else(|| mismatch("number", val))
+    }
+}
+
+
+impl FromVal for String {
+    fn from_val(val: &Val) -> Result<Self, String> {
+        val.as_str().cloned().ok_or_else(|| mismatch("string", val))
+    }
+}
+
+
+impl FromVal for Shared<String> {
+    fn from_val(val: &Val) -> Result<Self, String> {
+        match *val {
+            Val::Str(ref val) => Ok(Shared::clone(val)),
+            _ => Err(mismatch("string", val)),
+        }
+    }
+}
+
+
+impl<T: FromVal> FromVal for Option<T> {
+    fn from_val(val: &Val) -> Result<Self, String> {
+        match *val {
+            Val::None => Ok(None),
+            _ => T::from_val(val).map(Some),
+        }
+    }
+
+    fn is_optional() -> bool {
+        true
+    }
+}
+
+
+impl<T: FromVal> FromVal for Vec<T> {
+    fn from_val(val: &Val) -> Result<Self, String> {
+        match *val {
+            Val::List(ref items) => items.borrow().iter().map(T::from_val).collect(),
+            _ => Err(mismatch("list", val)),
+        }
+    }
+}
+
+
+/// List shared with the script, changes are visible on both sides.
+impl FromVal for Shared<Lock<Vec<Val>>> {
+    fn from_val(val: &Val) -> Result<Self, String> {
+        match *val {
+            Val::List(ref items) => Ok(Shared::clone(items)),
+            _ => Err(mismatch("list", val)),
+        }
+    }
+}
+
+
+/// Map shared with the script, changes are visible on both sides.
+impl FromVal for Shared<Lock<BTreeMap<String, Val>>> {
+    fn from_val(val: &Val) -> Result<Self, String> {
+        match *val {
+            Val::Map(ref items) => Ok(Shared::clone(items)),
+            _ => Err(mismatch("map", val)),
+        }
+    }
+}
+
+
+impl<T: FromVal> FromVal for BTreeMap<String, T> {
+    fn from_val(val: &Val) -> Result<Self, String> {
+        match *val {
+            Val::Map(ref items) => items.borrow().iter()
+                .map(|(key, item)| Ok((key.clone(), T::from_val(item)?)))
+                .collect(),
+            _ => Err(mismatch("map", val)),
+        }
+    }
+}
+
+
+impl IntoVal for Val {
+    fn into_val(self) -> Val {
+        self
+    }
+}
+
+
+impl IntoVal for () {
+    fn into_val(self) -> Val {
+        Val::None
+    }
+}
+
+
+impl IntoVal for i32 {
+    fn into_val(self) -> Val {
+        Val::Num(self)
+    }
+}
+
+
+impl IntoVal for f64 {
+    fn into_val(self) -> Val {
+        Val::Float(self)
+    }
+}
+
+
+impl IntoVal for String {
+    fn into_val(self) -> Val {
+        Val::Str(Shared::new(self))
+    }
+}
+
+
+impl IntoVal for &str {
+    fn into_val(self) -> Val {
+        Val::Str(Shared::new(String::from(self)))
+    }
+}
+
+
+impl IntoVal for Shared<String> {
+    fn into_val(self) -> Val {
+        Val::Str(self)
+    }
+}
+
+
+impl<T: IntoVal> IntoVal for Option<T> {
+    fn into_val(self) -> Val {
+        match self {
+            Some(val) => val.into_val(),
+            None => Val::None,
+        }
+    }
+}
+
+
+impl<T: IntoVal> IntoVal for Vec<T> {
+    fn into_val(self) -> Val {
+        Val::new_list(self.into_iter().map(IntoVal::into_val).collect())
+    }
+}
+
+
+impl<T: IntoVal> IntoVal for BTreeMap<String, T> {
+    fn into_val(self) -> Val {
+        Val::new_map(self.into_iter().map(|(key, item)| (key, item.into_val())).collect())
+    }
+}
+
+
+impl<T: IntoVal> IntoVal for HashMap<String, T> {
+    fn into_val(self) -> Val {
+        Val::new_map(self.into_iter().map(|(key, item)| (key, item.into_val())).collect())
+    }
+}
+
+
+impl<T: IntoVal> IntoValResult for T {
+    fn into_val_result(self) -> Result<Val, String> {
+        Ok(self.into_val())
+    }
+}
+
+
+impl<T: IntoVal, E: fmt::Display> IntoValResult for Result<T, E> {
+    fn into_val_result(self) -> Result<Val, String> {
+        self.map(IntoVal::into_val).map_err(|err| err.to_string())
+    }
+}
+
+
+macro_rules! tuple_into_val {
+    ($($name:ident),+) => {
+        impl<$($name: IntoVal),+> IntoVal for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn into_val(self) -> Val {
+                let ($($name,)+) = self;
+                Val::new_list(vec![$($name.into_val()),+])
+            }
+        }
+    }
+}
+
+tuple_into_val!(A, B);
+tuple_into_val!(A, B, C);
+tuple_into_val!(A, B, C, D);
+
+
+macro_rules! from_args {
+    ($count:expr; $($name:ident),*) => {
+        impl<$($name: FromVal),*> FromArgs for ($($name,)*) {
+            fn arity() -> Arity {
+                // trailing optional arguments could be omitted
+                let optional: [bool; $count] = [$($name::is_optional()),*];
+                let required = optional.iter().rposition(|&opt| !opt).map_or(0, |idx| idx + 1);
+
+                if required == $count {
+                    Arity::Fixed($count)
+                } else {
+                    Arity::Range(required, $count)
+                }
+            }
+
+            #[allow(unused_variables, unused_mut)]
+            fn from_args(args: &[Val]) -> Result<Self, String> {
+                if !Self::arity().accepts(args.len()) {
+                    return Err(format!("wrong arguments count, expected {} args", Self::arity()));
+                }
+
+                let mut args = args.iter().enumerate();
+
+                Ok(($(
+                    match args.next() {
+                        Some((idx, val)) => $name::from_val(val)
+                            .map_err(|err| format!("argument {}: {}", idx + 1, err))?,
+                        None => $name::from_val(&Val::None)?,
+                    },
+                )*))
+            }
+        }
+
+        impl<Func, Ret, $($name),*> TypedFunc<($($name,)*), Ret> for Func
+            where Func: Fn($($name),*) -> Ret + Threadsafe + 'static,
+                  Ret: IntoValResult,
+                  $($name: FromVal),*
+        {
+            fn arity() -> Arity {
+                <($($name,)*)>::arity()
+            }
+
+            #[allow(non_snake_case)]
+            fn call(&self, args: Vec<Val>) -> Result<Val, String> {
+                let ($($name,)*) = <($($name,)*)>::from_args(&args)?;
+                self($($name),*).into_val_result()
+            }
+        }
+    }
+}
+
+from_args!(0;);
+from_args!(1; A);
+from_args!(2; A, B);
+from_args!(3; A, B, C);
+from_args!(4; A, B, C, D);
+from_args!(5; A, B, C, D, E);
+from_args!(6; A, B, C, D, E, F);
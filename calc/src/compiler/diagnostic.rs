@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use compiler::*;
+
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+
+/// Renders errors and warnings the way rustc does: the message, the offending source
+/// line with the token underlined, secondary labels and notes.
+///
+/// ```text
+/// error: 'x': redefinition
+///  --> main.calc:3:5
+///   |
+/// 1 | let x = 1;
+///   |     - first declared here
+/// 2 | x = x + 1;
+/// 3 | let x = 2;
+///   |     ^
+/// ```
+///
+/// Source text is looked up by the file name of a location, lines of
+/// unknown files (and locations without a span) are not shown.
+#[derive(Default)]
+pub struct Renderer<'a> {
+    sources: HashMap<String, &'a str>,
+    color: bool,
+}
+
+
+/// Common parts of errors and warnings.
+struct Message<'a> {
+    title: String,  // e.g. "error" or "warning[W001]"
+    color: &'a str,
+    description: &'a str,
+    loc: &'a Loc,
+    labels: &'a [Label],
+    notes: &'a [Note],
+}
+
+
+/// Underlined span within a rendered line.
+struct Mark<'a> {
+    loc: &'a Loc,
+    text: &'a str,
+    primary: bool,
+}
+
+
+impl<'a> Renderer<'a> {
+    pub fn new() -> Self {
+        Renderer::default()
+    }
+
+    /// Use ANSI escape codes to highlight the output.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn source(mut self, filename: &str, text: &'a str) -> Self {
+        self.sources.insert(String::from(filename), text);
+        self
+    }
+
+    pub fn render(&self, err: &Error) -> String {
+        self.render_message(&Message {
+            title: String::from("error"),
+            color: RED,
+            description: &err.description,
+            loc: &err.loc,
+            labels: &err.labels,
+            notes: &err.notes,
+        })
+    }
+
+    pub fn render_warning(&self, warn: &Warning) -> String {
+        self.render_message(&Message {
+            title: format!("warning[{}]", warn.lint.code()),
+            color: YELLOW,
+            description: &warn.description,
+            loc: &warn.loc,
+            labels: &warn.labels,
+            notes: &warn.notes,
+        })
+    }
+
+    fn render_message(&self, msg: &Message) -> String {
+        let mut out = String::new();
+
+        // marks grouped by file, the file of the message goes first
+        let mut files: Vec<(&Loc, Vec<Mark>)> = vec![
+            (msg.loc, vec![Mark { loc: msg.loc, text: "", primary: true }])
+        ];
+
+        for label in msg.labels {
+            let mark = Mark { loc: &label.loc, text: &label.text, primary: false };
+
+            match files.iter_mut().find(|file| file.0.filename == label.loc.filename) {
+                Some(file) => file.1.push(mark),
+                None => files.push((&label.loc, vec![mark])),
+            }
+        }
+
+        let width = files.iter()
+            .flat_map(|file| file.1.iter())
+            .map(|mark| mark.loc.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+
+        let _ = writeln!(out, "{}{}", self.paint(msg.color, &msg.title), self.paint(BOLD, &format!(": {}", msg.description)));
+
+        for (idx, &(loc, ref marks)) in files.iter().enumerate() {
+            // labels of unknown files are listed with their positions
+            if idx == 0 || self.sources.contains_key(&**loc.filename) {
+                let arrow = if idx == 0 { "-->" } else { ":::" };
+                let _ = writeln!(out, "{:w$}{} {}", "", self.paint(BLUE, arrow), position(loc), w = width);
+            }
+
+            self.render_marks(&mut out, width, msg.color, loc, marks);
+        }
+
+        for note in msg.notes {
+            match *note {
+                Note::Info(ref text) => self.render_note(&mut out, width, "note", text),
+                Note::Help(ref text) => self.render_note(&mut out, width, "help", text),
+            }
+        }
+
+        out
+    }
+
+    fn render_marks(&self, out: &mut String, width: usize, color: &str, file: &Loc, marks: &[Mark]) {
+        let text = match self.sources.get(&**file.filename) {
+            Some(text) => *text,
+            None => {
+                // no source, at least show the labels
+                for mark in marks.iter().filter(|mark| !mark.primary) {
+                    let _ = writeln!(out, "{:w$} {} {} ({})", "", self.paint(BLUE, "="), mark.text, position(mark.loc), w = width);
+                }
+                return;
+            }
+        };
+
+        let mut marks: Vec<&Mark> = marks.iter()
+            .filter(|mark| mark.loc.has_span() && mark.loc.end <= text.len()
+                && text.is_char_boundary(mark.loc.start) && text.is_char_boundary(mark.loc.end))
+            .collect();
+
+        if marks.is_empty() {
+            return;
+        }
+
+        marks.sort_by_key(|mark| (mark.loc.line, mark.loc.col));
+
+        let gutter = self.paint(BLUE, &format!("{:w$} |", "", w = width));
+        let _ = writeln!(out, "{}", gutter);
+
+        let mut prev_line = None;
+
+        for mark in &marks {
+            let start = text[..mark.loc.start].rfind('\n').map_or(0, |pos| pos + 1);
+            let end = text[start..].find('\n').map_or(text.len(), |pos| start + pos);
+            let line = mark.loc.line;
+
+            if prev_line != Some(line) {
+                match prev_line {
+                    Some(prev) if line - prev == 2 => {
+                        // a single skipped line is shown as is
+                        let prev_end = text[..start - 1].rfind('\n').map_or(0, |pos| pos + 1);
+                        self.render_line(out, width, line - 1, &text[prev_end..start - 1]);
+                    }
+                    Some(prev) if line - prev > 2 => {
+                        let _ = writeln!(out, "{}", self.paint(BLUE, "..."));
+                    }
+                    _ => {}
+                }
+
+                self.render_line(out, width, line, &text[start..end]);
+                prev_line = Some(line);
+            }
+
+            // underline up to the end of the line
+            let line_text = &text[start..end];
+            let offset = mark.loc.start - start;
+            let span = &text[mark.loc.start..mark.loc.end.min(end).max(mark.loc.start)];
+
+            let indent: String = line_text[..offset].chars()
+                .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            let (ch, color) = if mark.primary { ('^', color) } else { ('-', BLUE) };
+            let underline: String = (0..span.chars().count().max(1)).map(|_| ch).collect();
+            let label = if mark.text.is_empty() { underline } else { format!("{} {}", underline, mark.text) };
+
+            let _ = writeln!(out, "{} {}{}", gutter, indent, self.paint(color, &label));
+        }
+    }
+
+    fn render_line(&self, out: &mut String, width: usize, line: i32, text: &str) {
+        let _ = writeln!(out, "{} {}", self.paint(BLUE, &format!("{:>w$} |", line, w = width)), text);
+    }
+
+    fn render_note(&self, out: &mut String, width: usize, kind: &str, text: &str) {
+        let _ = writeln!(out, "{:w$} {} {}: {}", "", self.paint(BLUE, "="), self.paint(BOLD, kind), text, w = width);
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            String::from(text)
+        }
+    }
+}
+
+
+fn position(loc: &Loc) -> String {
+    if loc.has_span() {
+        format!("{}:{}:{}", loc.filename, loc.line, loc.col)
+    } else {
+        format!("{}:{}", loc.filename, loc.line)
+    }
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::Path;
+use compiler;
+use compiler::*;
+
+
+/// Embedding facade: keeps one execution context across calls, so globals
+/// and functions declared by one script are visible to the next ones.
+///
+/// ```
+/// let mut engine = calc::Engine::new();
+/// engine.set_global("limit", 10);
+///
+/// let r = engine.eval("limit * 2").unwrap();
+/// assert_eq!(calc::compiler::Val::Num(20), r);
+/// ```
+pub struct Engine {
+    ctx: ExecContext,
+}
+
+
+impl Engine {
+    pub fn new() -> Self {
+        Engine {
+            ctx: ExecContext::new(),
+        }
+    }
+
+    /// Underlying context, e.g. to declare native functions or set limits.
+    pub fn context(&self) -> &ExecContext {
+        &self.ctx
+    }
+
+    pub fn context_mut(&mut self) -> &mut ExecContext {
+        &mut self.ctx
+    }
+
+    /// Run the script and return the value of its last statement. A single
+    /// expression without trailing `;` is accepted too.
+    pub fn eval(&mut self, text: &str) -> Result<Val, Error> {
+        self.run(text, "<eval>")
+    }
+
+    pub fn run_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Val, Error> {
+        let path = path.as_ref();
+        let filename = path.display().to_string();
+
+        let text = fs::read_to_string(path)
+            .map_err(|err| Loc::new(&filename, 0).error(format!("can't read file: {}", err)))?;
+
+        self.run(&text, &filename)
+    }
+
+    /// Loader of modules requested by `import` statements.
+    pub fn set_module_loader<T: ModuleLoader + 'static>(&mut self, loader: T) {
+        self.ctx.set_module_loader(loader);
+    }
+
+    pub fn get_global<T: FromVal>(&self, name: &str) -> Result<T, Error> {
+        let loc = Loc::new(&format!(r#"<get_global("{}")>"#, name), 0);
+
+        let val = match self.ctx.lookup_name(name) {
+            Some(val) => val,
+            None => return loc.error(format!("'{}': undeclared variable", name)).into(),
+        };
+
+        T::from_val(&val).map_err(|msg| loc.error(format!("'{}': {}", name, msg)))
+    }
+
+    pub fn set_global<T: IntoVal>(&mut self, name: &str, val: T) {
+        self.ctx.set_var(name, val.into_val());
+    }
+
+    /// Call a script or native function declared in the global scope.
+    pub fn call(&mut self, name: &str, args: Vec<Val>) -> Result<Val, Error> {
+        let loc = Loc::new(&format!(r#"<call("{}")>"#, name), 0);
+        let r = self.ctx.exec_func(name, args);
+
+        self.finish(r, &loc)
+    }
+
+    /// Parse the script without running it, a single expression without
+    /// trailing `;` is accepted too. Lets the caller tell syntax errors from
+    /// runtime ones.
+    ///
+    /// If the text is neither, the error of the grammar that got further into
+    /// the text is returned, the expression one if they fail at the same token.
+    pub fn parse(&self, text: &str, filename: &str) -> Result<AST, Error> {
+        let err = match compiler::parse(text, filename) {
+            Ok(prog) => return Ok(prog),
+            Err(err) => err,
+        };
+
+        compiler::parse_expr(text, filename).map_err(|expr_err| {
+            if err.loc.start > expr_err.loc.start { err } else { expr_err }
+        })
+    }
+
+    /// Run the parsed script and return the value of its last statement.
+    pub fn execute(&mut self, prog: &AST) -> Result<Val, Error> {
+        let r = compiler::execute(&mut self.ctx, prog);
+        self.finish(r, prog.loc())
+    }
+
+    fn run(&mut self, text: &str, filename: &str) -> Result<Val, Error> {
+        let prog = self.parse(text, filename)?;
+        self.execute(&prog)
+    }
+
+    fn finish(&mut self, r: ExecResult, loc: &Loc) -> Result<Val, Error> {
+        // the engine has no way to resume, so don't keep the context locked
+        if self.ctx.is_suspended() {
+            self.ctx.cancel_suspended();
+        }
+
+        r.map_err(|exc| exc.into_error(loc))
+    }
+}
+
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
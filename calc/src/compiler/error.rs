@@ -3,10 +3,70 @@ use std::fmt::{self, Display, Formatter};
 use super::location::*;
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    /// Syntax or runtime error reported by the script.
+    General,
+    /// Script allocated more memory than `ExecContext` allows.
+    MemoryLimit,
+    /// Execution was stopped by an `InterruptHandle`.
+    Interrupted,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Error {
     pub description: String,
     pub loc: Loc,
+    pub kind: ErrorKind,
+    pub labels: Vec<Label>,
+    pub notes: Vec<Note>,
+}
+
+
+/// Secondary location mentioned by an error, e.g. the first declaration of
+/// a redefined variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub loc: Loc,
+    pub text: String,
+}
+
+
+/// Text shown after the source lines of an error.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Note {
+    Info(String),
+    Help(String),  // how to fix the error
+}
+
+
+impl Error {
+    /// Whether a script may handle the error with `try`/`catch`, limits
+    /// imposed by the host can't be bypassed this way.
+    pub fn is_catchable(&self) -> bool {
+        self.kind == ErrorKind::General
+    }
+
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_label(mut self, loc: Loc, text: &str) -> Self {
+        self.labels.push(Label { loc, text: String::from(text) });
+        self
+    }
+
+    pub fn with_note(mut self, text: &str) -> Self {
+        self.notes.push(Note::Info(String::from(text)));
+        self
+    }
+
+    pub fn with_help(mut self, text: &str) -> Self {
+        self.notes.push(Note::Help(String::from(text)));
+        self
+    }
 }
 
 
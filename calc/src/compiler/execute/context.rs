@@ -1,27 +1,218 @@
-use std::rc::Rc;
+use std::mem;
 use compiler::*;
-use super::exec_func_call;
+use super::{exec_func_call, exec_entry, exec_next};
+use super::runtime::{Runtime, InterruptHandle};
+use super::frame::{Frame, Entry};
 
 
 pub struct ExecContext {
-    pub scope: Rc<Scope>,
+    pub scope: Shared<Scope>,
+    pub prelude: Shared<Scope>,     // native functions, parent of the global scopes
     pub allow_return: bool,
+    pub allow_yield: bool,
+    runtime: Shared<Runtime>,
 }
 
 
 impl ExecContext {
     pub fn new() -> Self {
+        let runtime = Shared::new(Runtime::new());
+        let prelude = Scope::new();
+        runtime.register_scope(&prelude);
+        let scope = Scope::new_nested(&prelude);
+        runtime.register_scope(&scope);
+
         ExecContext {
-            scope: Scope::new(),
+            scope,
+            prelude,
             allow_return: false,
+            allow_yield: false,
+            runtime,
         }
     }
 
-    pub fn new_with(&self, scope: &Rc<Scope>) -> Self {
+    pub fn new_with(&self, scope: &Shared<Scope>) -> Self {
         ExecContext {
-            scope: Scope::new_nested(scope),
+            scope: self.new_scope(scope),
+            prelude: Shared::clone(&self.prelude),
             allow_return: false,
+            allow_yield: false,
+            runtime: Shared::clone(&self.runtime),
+        }
+    }
+
+    /// Nested context that continues execution within existing scope.
+    pub fn resume_with_scope(&self, scope: &Shared<Scope>) -> Self {
+        ExecContext {
+            scope: Shared::clone(scope),
+            prelude: Shared::clone(&self.prelude),
+            allow_return: false,
+            allow_yield: false,
+            runtime: Shared::clone(&self.runtime),
+        }
+    }
+
+    pub fn new_scope(&self, parent: &Shared<Scope>) -> Shared<Scope> {
+        let scope = Scope::new_nested(parent);
+        self.runtime.register_scope(&scope);
+        scope
+    }
+
+    /// Scope of a loop iteration or a `try` clause, it sees and assigns
+    /// variables of the enclosing scope.
+    pub fn new_block_scope(&self) -> Shared<Scope> {
+        let scope = Scope::new_block(&self.scope);
+        self.runtime.register_scope(&scope);
+        scope
+    }
+
+    /// Run the callback with the nested scope as the current one.
+    pub fn in_scope<F, T>(&mut self, scope: &Shared<Scope>, run: F) -> T
+        where F: FnOnce(&mut Self) -> T
+    {
+        let outer = mem::replace(&mut self.scope, Shared::clone(scope));
+        let r = run(self);
+        self.scope = outer;
+        r
+    }
+
+    /// Top-level scope of a module, it sees the native functions but not
+    /// the variables of the importing script.
+    pub fn new_module_scope(&self) -> Shared<Scope> {
+        let scope = Scope::new_nested(&self.prelude);
+        self.runtime.register_scope(&scope);
+        scope
+    }
+
+    /// Loader of modules requested by `import` statements.
+    pub fn set_module_loader<T: ModuleLoader + 'static>(&mut self, loader: T) {
+        self.runtime.set_module_loader(Shared::new(loader));
+    }
+
+    pub fn module_loader(&self) -> Option<Shared<dyn ModuleLoader>> {
+        self.runtime.module_loader()
+    }
+
+    pub fn cached_module(&self, name: &str) -> Option<Shared<Module>> {
+        self.runtime.cached_module(name)
+    }
+
+    pub fn cache_module(&self, module: &Shared<Module>) {
+        self.runtime.cache_module(module);
+    }
+
+    pub fn start_import(&self, name: &str) -> Result<(), Vec<String>> {
+        self.runtime.start_import(name)
+    }
+
+    pub fn finish_import(&self, name: &str) {
+        self.runtime.finish_import(name);
+    }
+
+    /// Handle to stop running scripts from another thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.runtime.interrupt_handle().clone()
+    }
+
+    pub fn check_interrupt(&self, loc: &Loc) -> Result<(), Error> {
+        self.runtime.check_interrupt(loc)
+    }
+
+    /// Whether the last execution is waiting for a native function result.
+    pub fn is_suspended(&self) -> bool {
+        self.runtime.is_suspended()
+    }
+
+    /// Provide result of the pending native function. The suspended
+    /// execution continues when the host calls the same entry point
+    /// (`compiler::execute` with the same program or `exec_func`) again.
+    pub fn resume_with(&mut self, val: Val) {
+        self.runtime.resume_with(val);
+    }
+
+    /// Drop the suspended execution, so the context could run other code.
+    pub fn cancel_suspended(&mut self) {
+        self.runtime.cancel_suspended();
+    }
+
+    pub fn take_resume_val(&self) -> Option<Val> {
+        self.runtime.take_resume_val()
+    }
+
+    pub fn is_resuming(&self) -> bool {
+        self.runtime.is_resuming()
+    }
+
+    pub fn pop_frame(&self) -> Frame {
+        self.runtime.pop_frame()
+    }
+
+    pub fn peek_frame<F, R>(&self, callback: F) -> Option<R>
+        where F: FnOnce(&Frame) -> R
+    {
+        self.runtime.peek_frame(callback)
+    }
+
+    /// Save progress of the current node if execution is being suspended
+    /// (or generator yields a value).
+    pub fn save_frame(&self, exc: FlowExc, frame: Frame) -> FlowExc {
+        if let FlowExc::Suspend | FlowExc::Yield(..) = exc {
+            self.runtime.push_frame(frame);
         }
+
+        exc
+    }
+
+    pub fn take_frames(&self) -> Vec<Frame> {
+        self.runtime.take_frames()
+    }
+
+    pub fn restore_frames(&self, frames: Vec<Frame>) {
+        self.runtime.restore_frames(frames);
+    }
+
+    pub fn enter(&self, entry: &Entry, loc: &Loc) -> Result<(), Error> {
+        self.runtime.enter(entry, loc)
+    }
+
+    pub fn leave(&self, entry: Entry, suspended: bool) {
+        self.runtime.leave(entry, suspended);
+    }
+
+    /// Limit approximate amount of memory held by script values, `None`
+    /// disables the limit.
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.runtime.set_memory_limit(limit);
+    }
+
+    pub fn memory_limit(&self) -> Option<usize> {
+        self.runtime.memory_limit()
+    }
+
+    /// Approximate number of bytes held by values of all live scopes.
+    pub fn memory_used(&self) -> usize {
+        self.runtime.measure()
+    }
+
+    /// Free scopes, lists and maps kept alive only by reference cycles
+    /// (e.g. closures), returns the number of freed values.
+    ///
+    /// It runs automatically from time to time, all remaining scopes are
+    /// cleared once the context and all its nested contexts are dropped.
+    pub fn collect_garbage(&mut self) -> usize {
+        self.runtime.collect_cycles()
+    }
+
+    pub fn charge(&self, loc: &Loc, bytes: usize) -> Result<(), Error> {
+        self.runtime.charge(loc, bytes)
+    }
+
+    pub fn charge_scope(&self, loc: &Loc) -> Result<(), Error> {
+        self.charge(loc, mem::size_of::<Scope>())
+    }
+
+    pub fn charge_var(&self, loc: &Loc, name: &str) -> Result<(), Error> {
+        self.charge(loc, name.len() + mem::size_of::<(String, Val)>())
     }
 
     pub fn lookup_name(&self, name: &str) -> Option<Val> {
@@ -45,20 +236,124 @@ impl ExecContext {
     }
 
     pub fn set_var(&mut self, name: &str, val: Val) {
+        self.runtime.register_container(&val);
         self.scope.vals.borrow_mut().insert(String::from(name), val);
     }
 
-    pub fn decl_func<T>(&mut self, name: &str, func: T) 
-        where T: Fn(Vec<Val>) -> Val + 'static
+    /// Assign the variable declared in the current scope or, from a block,
+    /// in the enclosing ones. Returns `false` if there is no such variable.
+    pub fn assign_var(&mut self, name: &str, val: Val) -> bool {
+        let mut scope = Shared::clone(&self.scope);
+
+        loop {
+            if scope.vals.borrow().contains_key(name) {
+                break;
+            }
+
+            scope = match scope.parent {
+                Some(ref parent) if scope.is_block => Shared::clone(parent),
+                _ => return false,
+            };
+        }
+
+        self.runtime.register_container(&val);
+        scope.vals.borrow_mut().insert(String::from(name), val);
+        true
+    }
+
+    /// Declare a script variable in the current scope, its location is
+    /// kept to point at it if the name is redefined later.
+    pub fn decl_var(&mut self, loc: &Loc, name: &str, val: Val) -> Result<(), Error> {
+        if self.has_var(name) {
+            return Err(self.redefinition(loc, name));
+        }
+
+        self.charge_var(loc, name)?;
+        self.set_var(name, val);
+        self.scope.decls.borrow_mut().insert(String::from(name), loc.clone());
+        Ok(())
+    }
+
+    pub fn redefinition(&self, loc: &Loc, name: &str) -> Error {
+        let err = loc.error(format!("'{}': redefinition", name));
+
+        match self.scope.decl_loc(name) {
+            Some(first) => err.with_label(first, "first declared here"),
+            None => err,
+        }
+    }
+
+    /// Declare native function, it may return `Poll::Pending` to suspend
+    /// the script until the host calls `resume_with`.
+    pub fn decl_func<T, R>(&mut self, name: &str, func: T)
+        where T: Fn(Vec<Val>) -> R + Threadsafe + 'static,
+              R: IntoNativeResult
     {
-        self.set_var(name, Val::new_func(name, func));
+        self.set_prelude(name, Val::new_func(name, func));
+    }
+
+    /// Declare native function that may call back into the script, e.g.
+    /// to apply a script function to list items.
+    pub fn decl_context_func<T>(&mut self, name: &str, arity: Arity, func: T)
+        where T: Fn(&mut CallContext, Vec<Val>) -> ExecResult + Threadsafe + 'static
+    {
+        self.set_prelude(name, Val::new_context_func(name, arity, func));
+    }
+
+    /// Declare native function from a typed Rust closure, e.g.
+    /// `|a: i32, b: String| -> Result<i32, E>`. Arguments are checked and
+    /// converted with `FromVal`, the result with `IntoVal`.
+    pub fn decl_typed_func<T, Args, R>(&mut self, name: &str, func: T)
+        where T: TypedFunc<Args, R>
+    {
+        self.decl_func_arity(name, T::arity(), move |args| func.call(args));
+    }
+
+    /// Declare native function that checks arguments count, wrong calls are
+    /// reported the same way as for script functions.
+    pub fn decl_func_arity<T, R>(&mut self, name: &str, arity: Arity, func: T)
+        where T: Fn(Vec<Val>) -> R + Threadsafe + 'static,
+              R: IntoNativeResult
+    {
+        self.set_prelude(name, Val::new_func_arity(name, arity, func));
+    }
+
+    /// Declare a read-only value, e.g. a library constant. Like native
+    /// functions it is visible to imported modules.
+    pub fn decl_const(&mut self, name: &str, val: Val) {
+        self.set_prelude(name, val);
+    }
+
+    /// Library names are kept in the prelude, so scripts and modules can
+    /// declare their own variables with the same names but can't assign them.
+    fn set_prelude(&mut self, name: &str, val: Val) {
+        self.prelude.vals.borrow_mut().insert(String::from(name), val);
     }
 
     pub fn exec_func(&mut self, name: &str, args: Vec<Val>) -> ExecResult {
-        exec_func_call(
-            self,
-            &Loc::new(&format!(r#"<exec_func("{}")>"#, name), 0),
-            name, args)
+        let loc = Loc::new(&format!(r#"<exec_func("{}")>"#, name), 0);
+
+        exec_entry(self, Entry::Func(String::from(name)), &loc, |ctx| {
+            exec_func_call(ctx, &loc, name, args)
+        })
+    }
+
+    /// Pull the next value from a generator, `None` once it's exhausted.
+    pub fn next_value(&mut self, iter: &Val) -> Result<Option<Val>, FlowExc> {
+        let loc = Loc::new("<next_value>", 0);
+        let entry = match *iter {
+            Val::Generator(ref gen) => Entry::Iter(Shared::as_ptr(gen) as usize),
+            _ => return Err(loc.error(format!(
+                "type mismatch, generator expected, found '{}'", iter)).into()),
+        };
+
+        exec_entry(self, entry, &loc, |ctx| exec_next(ctx, &loc, iter))
     }
 }
 
+
+impl Default for ExecContext {
+    fn default() -> Self {
+        ExecContext::new()
+    }
+}
@@ -0,0 +1,65 @@
+use compiler::*;
+
+
+/// Progress of an AST node, saved when execution is suspended.
+///
+/// Suspension unwinds the interpreter like any other `FlowExc`, each node
+/// on the way pushes a frame describing how far it got. On resume the same
+/// nodes are executed again, pop their frames (outermost first) and
+/// continue from the saved point instead of starting over.
+#[derive(Debug)]
+pub enum Frame {
+    /// Index of the statement in progress.
+    Block(usize),
+
+    /// Left operand, if it was evaluated already.
+    BinOp(Option<Val>),
+
+    /// Call arguments evaluated so far.
+    Args(Vec<Val>),
+
+    /// Body of a script function in progress.
+    Body {
+        decl: Shared<FuncDecl>,
+        scope: Shared<Scope>,
+    },
+
+    /// Native function waiting for its result.
+    Native,
+
+    /// Statement `yield` waiting for the next value request.
+    Yield,
+
+    /// Statement `try` in progress, outcome of the body (or handler) is
+    /// kept until `finally` block is done.
+    Try {
+        stage: TryStage,
+        pending: Option<FlowExc>,
+        scope: Shared<Scope>,   // of the clause in progress
+    },
+
+    /// Loop in progress, the iterator is known once it is evaluated, the
+    /// scope once the body of the current iteration is entered.
+    For {
+        iter: Option<Val>,
+        pos: usize,     // index of the next item of a list
+        scope: Option<Shared<Scope>>,
+    },
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TryStage {
+    Body,
+    Catch,
+    Finally,
+}
+
+
+/// Host entry point of a suspended execution.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    Program(usize),  // address of the executed AST
+    Func(String),
+    Iter(usize),     // address of the generator
+}
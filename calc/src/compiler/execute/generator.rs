@@ -0,0 +1,50 @@
+use std::fmt;
+use compiler::*;
+use super::frame::Frame;
+
+
+/// Suspended call of a generator function.
+pub struct Generator {
+    pub decl: Shared<FuncDecl>,
+    pub scope: Shared<Scope>,
+    pub state: Lock<GenState>,
+}
+
+
+pub enum GenState {
+    /// Waiting for the next value request, frames of the last `yield`
+    /// (empty if the body was not started yet).
+    Ready(Vec<Frame>),
+
+    /// Body is executed (or suspended by a native function).
+    Running,
+
+    /// Body has finished, there are no more values.
+    Done,
+}
+
+
+impl Generator {
+    pub fn new(decl: &Shared<FuncDecl>, scope: Shared<Scope>) -> Self {
+        Generator {
+            decl: Shared::clone(decl),
+            scope,
+            state: Lock::new(GenState::Ready(Vec::new())),
+        }
+    }
+}
+
+
+impl fmt::Debug for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, r#"Val::Generator("{}", ..)"#, self.decl.name)
+    }
+}
+
+
+impl PartialEq for Generator {
+    fn eq(&self, other: &Self) -> bool {
+        // every call creates a new generator
+        std::ptr::eq(self, other)
+    }
+}
@@ -0,0 +1,139 @@
+//! Properties, methods and items of values: host objects, errors, modules
+//! and collections.
+
+use compiler::*;
+use super::module;
+
+
+pub fn get_prop(loc: &Loc, obj: &Val, name: &str) -> ExecResult {
+    match *obj {
+        Val::Object(ref obj) => {
+            obj.get(name).or_else(|msg| member_error(loc, obj.type_name(), name, msg))
+        }
+
+        Val::Module(ref module) => module::module_var(loc, module, name),
+
+        Val::Map(ref items) => match items.borrow().get(name) {
+            Some(val) => Ok(val.clone()),
+            None => loc.error(format!("'{}': no such key", name)).into(),
+        },
+
+        Val::Error(ref err) => match name {
+            "message" => Ok(Val::Str(Shared::new(err.description.clone()))),
+            "line" => Ok(Val::Num(err.loc.line)),
+            "file" => Ok(Val::Str(Shared::clone(&err.loc.filename))),
+            _ => member_error(loc, "error", name, String::from("no such property")),
+        },
+
+        _ => type_mismatch(loc, obj, name),
+    }
+}
+
+
+pub fn set_prop(loc: &Loc, obj: &Val, name: &str, val: Val) -> ExecResult {
+    match *obj {
+        Val::Object(ref obj) => {
+            obj.set(name, val).or_else(|msg| member_error(loc, obj.type_name(), name, msg))?;
+            Ok(Val::None)
+        }
+
+        Val::Error(_) => {
+            member_error(loc, "error", name, String::from("property is read-only or missing"))
+        }
+
+        Val::Module(ref module) => {
+            Err(loc.error(format!("'{}': variables of module '{}' are read-only", name, module.name)).into())
+        }
+
+        Val::Map(ref items) => {
+            items.borrow_mut().insert(String::from(name), val);
+            Ok(Val::None)
+        }
+
+        _ => type_mismatch(loc, obj, name),
+    }
+}
+
+
+pub fn call_method(loc: &Loc, obj: &Val, name: &str, args: Vec<Val>) -> ExecResult {
+    match *obj {
+        Val::Object(ref obj) => {
+            obj.call(name, args).or_else(|msg| member_error(loc, obj.type_name(), name, msg))
+        }
+
+        Val::Error(_) => member_error(loc, "error", name, String::from("no such method")),
+
+        _ => type_mismatch(loc, obj, name),
+    }
+}
+
+
+pub fn get_index(loc: &Loc, obj: &Val, index: &Val) -> ExecResult {
+    match (obj, index) {
+        (Val::List(items), &Val::Num(idx)) => {
+            let items = items.borrow();
+            let idx = list_index(loc, idx, items.len())?;
+
+            Ok(items[idx].clone())
+        }
+
+        (Val::Str(val), &Val::Num(idx)) => {
+            let len = val.chars().count();
+            let idx = list_index(loc, idx, len)?;
+
+            Ok(Val::Str(Shared::new(val.chars().nth(idx).unwrap().to_string())))
+        }
+
+        (Val::Map(items), Val::Str(key)) => match items.borrow().get(key.as_str()) {
+            Some(val) => Ok(val.clone()),
+            None => loc.error(format!("'{}': no such key", key)).into(),
+        },
+
+        _ => index_mismatch(loc, obj, index),
+    }
+}
+
+
+pub fn set_index(loc: &Loc, obj: &Val, index: &Val, val: Val) -> ExecResult {
+    match (obj, index) {
+        (Val::List(items), &Val::Num(idx)) => {
+            let mut items = items.borrow_mut();
+            let idx = list_index(loc, idx, items.len())?;
+
+            items[idx] = val;
+        }
+
+        (Val::Map(items), Val::Str(key)) => {
+            items.borrow_mut().insert((**key).clone(), val);
+        }
+
+        _ => return index_mismatch(loc, obj, index),
+    }
+
+    Ok(Val::None)
+}
+
+
+fn list_index(loc: &Loc, idx: i32, len: usize) -> Result<usize, FlowExc> {
+    if idx < 0 || idx as usize >= len {
+        return Err(loc.error(format!("index {} is out of range (length {})", idx, len)).into());
+    }
+
+    Ok(idx as usize)
+}
+
+
+fn index_mismatch(loc: &Loc, obj: &Val, index: &Val) -> ExecResult {
+    loc.error(format!(
+        "type mismatch, can't index '{}' by '{}'", obj, index)).into()
+}
+
+
+fn member_error<T>(loc: &Loc, type_name: &str, name: &str, msg: String) -> Result<T, FlowExc> {
+    Err(loc.error(format!("'{}.{}': {}", type_name, name, msg)).into())
+}
+
+
+fn type_mismatch<T>(loc: &Loc, obj: &Val, name: &str) -> Result<T, FlowExc> {
+    Err(loc.error(format!("'{}': type mismatch, object expected, found '{}'", name, obj)).into())
+}
@@ -1,59 +1,241 @@
-
 pub mod context;
 pub mod result;
-
-use std::rc::Rc;
+pub mod runtime;
+pub mod frame;
+pub mod generator;
+pub mod member;
+pub mod module;
+pub mod native;
+
+use std::mem;
+use std::task::Poll;
 use compiler::*;
 use self::AST::*;
+use self::frame::{Frame, Entry, TryStage};
+use self::generator::{Generator, GenState};
+use self::native::CallContext;
+use compiler::value::NativeCallback;
 
 
+/// Execute the program, or continue it if it was suspended by a native
+/// function (see `ExecContext::resume_with`).
 pub fn execute(ctx: &mut ExecContext, expr: &AST) -> ExecResult {
+    let entry = Entry::Program(expr as *const AST as usize);
+
+    exec_entry(ctx, entry, expr.loc(), |ctx| exec(ctx, expr))
+}
+
+
+pub fn exec_entry<F, T>(ctx: &mut ExecContext, entry: Entry, loc: &Loc, run: F) -> Result<T, FlowExc>
+    where F: FnOnce(&mut ExecContext) -> Result<T, FlowExc>
+{
+    ctx.enter(&entry, loc)?;
+
+    let r = run(ctx);
+    ctx.leave(entry, matches!(r, Err(FlowExc::Suspend)));
+
+    r
+}
+
+
+fn exec(ctx: &mut ExecContext, expr: &AST) -> ExecResult {
     match *expr {
         Block{ ref body, .. } => {
-            for expr in body {
-                execute(ctx, expr)?;
+            let start = if ctx.is_resuming() {
+                match ctx.pop_frame() {
+                    Frame::Block(idx) => idx,
+                    frame => unexpected_frame(frame),
+                }
+            } else {
+                0
+            };
+
+            // value of the last statement is the result of the block
+            let mut last = Val::None;
+
+            for (idx, expr) in body.iter().enumerate().skip(start) {
+                ctx.check_interrupt(expr.loc())?;
+                last = exec(ctx, expr)
+                    .map_err(|exc| ctx.save_frame(exc, Frame::Block(idx)))?;
             }
+
+            return Ok(last);
         }
 
-        Func{ ref decl, .. } => {
+        Func{ ref decl, ref loc } => {
             if ctx.has_var(&decl.name) {
-                return expr.error(format!("'{}': redefinition", &decl.name)).into();
+                return ctx.redefinition(loc, &decl.name).into();
             }
 
-            let scope = Scope::new_nested(&ctx.scope);
+            ctx.charge_scope(loc)?;
+            let scope = ctx.new_scope(&ctx.scope);
 
-            ctx.set_var(&decl.name, Val::Func{
-                decl: Rc::clone(decl),
+            ctx.decl_var(loc, &decl.name, Val::Func{
+                decl: Shared::clone(decl),
                 scope,
-            });
+            })?;
+        }
+
+        Yield{ ref val, .. } => {
+            // otherwise it was suspended while evaluating the value
+            if let Some(true) = ctx.peek_frame(|frame| matches!(*frame, Frame::Yield)) {
+                ctx.pop_frame();
+                return Ok(Val::None);
+            }
+
+            let val = exec(ctx, val)?;
+
+            if !ctx.allow_yield {
+                return expr.error_str("unexpected 'yield' statement").into();
+            }
+
+            return Err(ctx.save_frame(FlowExc::Yield(val), Frame::Yield));
+        }
+
+        For{ ref var, ref iter, ref body, ref loc } => {
+            let (saved, mut pos, mut body_scope) = if ctx.is_resuming() {
+                match ctx.pop_frame() {
+                    Frame::For{ iter, pos, scope } => (iter, pos, scope),
+                    frame => unexpected_frame(frame),
+                }
+            } else {
+                (None, 0, None)
+            };
+
+            let iter = match saved {
+                Some(iter) => iter,
+                None => {
+                    let iter = exec(ctx, iter)
+                        .map_err(|exc| ctx.save_frame(exc, Frame::For{ iter: None, pos: 0, scope: None }))?;
+
+                    // maps are iterated over a snapshot of their keys
+                    match iter {
+                        Val::Map(ref items) => Val::new_list(items.borrow().keys()
+                            .map(|key| Val::Str(Shared::new(key.clone())))
+                            .collect()),
+                        _ => iter,
+                    }
+                }
+            };
+
+            if !iter.is_generator() && iter.as_list().is_none() {
+                return expr.error(format!(
+                    "type mismatch, iterable expected, found '{}'", iter)).into();
+            }
+
+            loop {
+                let scope = match body_scope.take() {
+                    Some(scope) => scope,   // resumed within the body
+                    None => {
+                        let item = match iter.as_list() {
+                            Some(items) => items.borrow().get(pos).cloned(),
+                            None => exec_next(ctx, loc, &iter)
+                                .map_err(|exc| ctx.save_frame(exc, Frame::For{
+                                    iter: Some(iter.clone()),
+                                    pos,
+                                    scope: None,
+                                }))?,
+                        };
+
+                        let item = match item {
+                            Some(item) => item,
+                            None => break,
+                        };
+                        pos += 1;
+
+                        // each iteration declares the variable anew
+                        ctx.charge_scope(loc)?;
+                        let scope = ctx.new_block_scope();
+                        ctx.in_scope(&scope, |ctx| ctx.decl_var(loc, var, item))?;
+                        scope
+                    }
+                };
+
+                ctx.in_scope(&scope, |ctx| exec(ctx, body))
+                    .map_err(|exc| ctx.save_frame(exc, Frame::For{
+                        iter: Some(iter.clone()),
+                        pos,
+                        scope: Some(scope),
+                    }))?;
+            }
         }
 
-        DeclVar{ ref name, ref init, .. } => {
-            let init = execute(ctx, init)?;
+        Throw{ ref val, ref loc } => {
+            let val = exec(ctx, val)?;
+
+            // error values are rethrown as is, so they keep their location
+            let err = match val {
+                Val::Error(ref err) => (**err).clone(),
+                Val::Str(ref msg) => loc.error_str(msg),
+                _ => loc.error(format!("{}", val)),
+            };
+
+            return Err(FlowExc::Error(err));
+        }
 
-            if ctx.has_var(name) {
-                return expr.error(format!("'{}': redefinition", name)).into();
+        Import{ ref path, ref alias, ref loc } => {
+            let module = module::exec_import(ctx, loc, path)?;
+
+            ctx.decl_var(loc, alias, Val::Module(module))?;
+        }
+
+        FromImport{ ref path, ref names, ref loc } => {
+            let module = module::exec_import(ctx, loc, path)?;
+
+            for name in names {
+                let val = module::module_var(loc, &module, name)?;
+
+                ctx.decl_var(loc, name, val)?;
             }
+        }
 
-            ctx.set_var(name, init);
+        Try{ ref body, ref catch, ref finally, ref loc } => {
+            return exec_try(ctx, loc, body, catch, finally);
+        }
+
+        DeclVar{ ref name, ref init, ref loc } => {
+            let init = exec(ctx, init)?;
+
+            ctx.decl_var(loc, name, init)?;
         }
 
         Assign{ ref name, ref init, .. } => {
-            let init = execute(ctx, init)?;
+            let init = exec(ctx, init)?;
 
-            if !ctx.has_var(name) {
+            if !ctx.assign_var(name, init) {
+                if ctx.prelude.get(name).is_some() {
+                    return expr.error(format!("'{}': library names are read-only", name)).into();
+                }
                 return expr.error(format!("'{}': undeclared variable", name)).into();
             }
-
-            ctx.set_var(name, init);
         }
 
         BinOp{ op, ref left, ref right, .. } => {
-            let left = execute(ctx, left)?;
-            let right = execute(ctx, right)?;
+            let saved = if ctx.is_resuming() {
+                match ctx.pop_frame() {
+                    Frame::BinOp(left) => left,
+                    frame => unexpected_frame(frame),
+                }
+            } else {
+                None
+            };
 
-            if left.is_num() && right.is_num() {
-                return exec_numeric_op(op, left, right);
+            let left = match saved {
+                Some(left) => left,
+                None => exec(ctx, left)
+                    .map_err(|exc| ctx.save_frame(exc, Frame::BinOp(None)))?,
+            };
+
+            let right = exec(ctx, right)
+                .map_err(|exc| ctx.save_frame(exc, Frame::BinOp(Some(left.clone()))))?;
+
+            if let (Some(l), Some(r)) = (left.as_num(), right.as_num()) {
+                return exec_numeric_op(expr, op, l, r);
+            }
+
+            // integers are promoted if the other operand is a float
+            if let (Some(l), Some(r)) = (left.as_float(), right.as_float()) {
+                return Ok(Val::Float(exec_float_op(op, l, r)));
             }
 
             return expr.error(format!(
@@ -61,12 +243,91 @@ pub fn execute(ctx: &mut ExecContext, expr: &AST) -> ExecResult {
                 op, left, right)).into();
         }
 
+        List{ ref items, ref loc } => {
+            let operands: Vec<&AST> = items.iter().collect();
+            let val = Val::new_list(exec_operands(ctx, &operands)?);
+            ctx.charge(loc, val.heap_size())?;
+
+            return Ok(val);
+        }
+
+        Map{ ref items, ref loc } => {
+            let operands: Vec<&AST> = items.iter().map(|item| &item.1).collect();
+            let values = exec_operands(ctx, &operands)?;
+
+            let items = items.iter().map(|item| item.0.clone()).zip(values).collect();
+            let val = Val::new_map(items);
+            ctx.charge(loc, val.heap_size())?;
+
+            return Ok(val);
+        }
+
+        Index{ ref obj, ref index, ref loc } => {
+            let values = exec_operands(ctx, &[obj, index])?;
+
+            return member::get_index(loc, &values[0], &values[1]);
+        }
+
+        SetIndex{ ref obj, ref index, ref val, ref loc } => {
+            let mut values = exec_operands(ctx, &[obj, index, val])?;
+            let val = values.pop().unwrap();
+
+            ctx.charge(loc, val.heap_size())?;
+            return member::set_index(loc, &values[0], &values[1], val);
+        }
+
+        GetProp{ ref obj, ref name, ref loc } => {
+            let obj = exec(ctx, obj)?;
+
+            let r = member::get_prop(loc, &obj, name)?;
+            ctx.charge(loc, r.heap_size())?;
+
+            return Ok(r);
+        }
+
+        SetProp{ ref obj, ref name, ref val, ref loc } => {
+            let mut values = exec_operands(ctx, &[obj, val])?;
+            let val = values.pop().unwrap();
+
+            return member::set_prop(loc, &values[0], name, val);
+        }
+
+        MethodCall{ ref obj, ref name, ref args, ref loc } => {
+            // module functions save their frames, so operands are done then
+            if let Some(false) = ctx.peek_frame(|frame| matches!(*frame, Frame::Args(..))) {
+                return resume_func_call(ctx, loc);
+            }
+
+            let operands: Vec<&AST> = Some(&**obj).into_iter().chain(args).collect();
+            let mut values = exec_operands(ctx, &operands)?;
+            let obj = values.remove(0);
+
+            ctx.check_interrupt(loc)?;
+
+            if let Val::Module(ref module) = obj {
+                let func = module::module_var(loc, module, name)?;
+                return exec_call_val(ctx, loc, name, &func, values);
+            }
+
+            let r = member::call_method(loc, &obj, name, values)?;
+            ctx.charge(loc, r.heap_size())?;
+
+            return Ok(r);
+        }
+
         Num{ val, .. } => {
             return Ok(Val::Num(val));
         }
 
-        Str{ ref val, .. } => {
-            return Ok(Val::Str(Rc::new(val.clone())));
+        Float{ val, .. } => {
+            return Ok(Val::Float(val));
+        }
+
+        Str{ ref val, ref loc } => {
+            let val = Val::Str(Shared::new(val.clone()));
+            ctx.charge(loc, val.heap_size())?;
+
+            return Ok(val);
         }
 
         Var{ ref name, .. } => {
@@ -78,19 +339,34 @@ pub fn execute(ctx: &mut ExecContext, expr: &AST) -> ExecResult {
         }
 
         Call{ ref name, ref args, ref loc, .. } => {
-            let mut values: Vec<Val> = Vec::new();
-            for p in args {
-                values.push(execute(ctx, p)?);
+            // the call itself saves its frame, so arguments are done then
+            let in_args = ctx.peek_frame(|frame| matches!(*frame, Frame::Args(..)));
+
+            let mut values: Vec<Val> = match in_args {
+                Some(true) => match ctx.pop_frame() {
+                    Frame::Args(values) => values,
+                    frame => unexpected_frame(frame),
+                },
+                Some(false) => {
+                    return exec_func_call(ctx, loc, name, Vec::new());
+                }
+                None => Vec::new(),
+            };
+
+            while values.len() < args.len() {
+                let val = exec(ctx, &args[values.len()])
+                    .map_err(|exc| ctx.save_frame(exc, Frame::Args(values.clone())))?;
+                values.push(val);
             }
 
             return exec_func_call(ctx, loc, name, values);
         }
 
         Return{ ref ret, .. } => {
-            let ret = execute(ctx, ret)?;
+            let ret = exec(ctx, ret)?;
 
             if !ctx.allow_return {
-                return expr.error(format!("unexpected 'return' statement")).into();
+                return expr.error_str("unexpected 'return' statement").into();
             }
 
             return Err(FlowExc::Return(ret));
@@ -101,11 +377,136 @@ pub fn execute(ctx: &mut ExecContext, expr: &AST) -> ExecResult {
 }
 
 
-fn exec_numeric_op(op: char, left: Val, right: Val) -> ExecResult {
-    let left = left.as_num().unwrap();
-    let right = right.as_num().unwrap();
+/// Evaluate operands in order, the ones done are saved on suspension.
+fn exec_operands(ctx: &mut ExecContext, operands: &[&AST]) -> Result<Vec<Val>, FlowExc> {
+    let mut values = if ctx.is_resuming() {
+        match ctx.pop_frame() {
+            Frame::Args(values) => values,
+            frame => unexpected_frame(frame),
+        }
+    } else {
+        Vec::new()
+    };
+
+    while values.len() < operands.len() {
+        let val = exec(ctx, operands[values.len()])
+            .map_err(|exc| ctx.save_frame(exc, Frame::Args(values.clone())))?;
+        values.push(val);
+    }
+
+    Ok(values)
+}
+
+
+type CatchClause = Option<(String, Box<AST>)>;
+
+
+fn exec_try(ctx: &mut ExecContext, loc: &Loc, body: &AST, catch: &CatchClause, finally: &Option<Box<AST>>) -> ExecResult {
+    let (mut stage, mut pending, mut clause_scope) = if ctx.is_resuming() {
+        match ctx.pop_frame() {
+            Frame::Try{ stage, pending, scope } => (stage, pending, Some(scope)),
+            frame => unexpected_frame(frame),
+        }
+    } else {
+        (TryStage::Body, None, None)
+    };
+
+    loop {
+        let block = match stage {
+            TryStage::Body => body,
+            TryStage::Catch => &catch.as_ref().unwrap().1,
+            TryStage::Finally => match *finally {
+                // host limits are not handled by scripts at all
+                Some(ref block) if !is_fatal(&pending) => block,
+                _ => break,
+            },
+        };
+
+        // each clause has its own scope, the handler one is made with its variable
+        let scope = match clause_scope.take() {
+            Some(scope) => scope,
+            None => {
+                ctx.charge_scope(loc)?;
+                ctx.new_block_scope()
+            }
+        };
+
+        match ctx.in_scope(&scope, |ctx| exec(ctx, block)) {
+            Ok(_) => (),
+
+            Err(exc @ FlowExc::Suspend) | Err(exc @ FlowExc::Yield(..)) => {
+                return Err(ctx.save_frame(exc, Frame::Try{ stage, pending, scope }));
+            }
+
+            // error or return in `finally` overrides the pending one
+            Err(exc) => pending = Some(exc),
+        }
+
+        stage = match stage {
+            TryStage::Body => match pending {
+                Some(FlowExc::Error(ref err)) if catch.is_some() && err.is_catchable() => {
+                    let var = &catch.as_ref().unwrap().0;
+                    let err = Val::Error(Shared::new(err.clone()));
+
+                    ctx.charge_scope(loc)?;
+                    let scope = ctx.new_block_scope();
+                    ctx.in_scope(&scope, |ctx| ctx.decl_var(loc, var, err))?;
+                    clause_scope = Some(scope);
+
+                    pending = None;
+                    TryStage::Catch
+                }
+                _ => TryStage::Finally,
+            },
+            TryStage::Catch => TryStage::Finally,
+            TryStage::Finally => break,
+        };
+    }
+
+    match pending {
+        Some(exc) => Err(exc),
+        None => Ok(Val::None),
+    }
+}
+
+
+fn is_fatal(pending: &Option<FlowExc>) -> bool {
+    match *pending {
+        Some(FlowExc::Error(ref err)) => !err.is_catchable(),
+        _ => false,
+    }
+}
+
+
+fn unexpected_frame(frame: Frame) -> ! {
+    panic!("resumed execution doesn't match saved frames: {:?}", frame)
+}
+
+
+fn exec_numeric_op(expr: &AST, op: char, left: i32, right: i32) -> ExecResult {
+    if (op == '/' || op == '%') && right == 0 {
+        return expr.error_str("division by zero").into();
+    }
 
     let r = match op {
+        '+' => left.checked_add(right),
+        '-' => left.checked_sub(right),
+        '*' => left.checked_mul(right),
+        '/' => left.checked_div(right),
+        '%' => left.checked_rem(right),
+
+        _ => panic!("binary op '{}' not implemented", op),
+    };
+
+    match r {
+        Some(r) => Ok(Val::Num(r)),
+        None => expr.error(format!("'{}': integer overflow", op)).into(),
+    }
+}
+
+
+fn exec_float_op(op: char, left: f64, right: f64) -> f64 {
+    match op {
         '+' => left + right,
         '-' => left - right,
         '*' => left * right,
@@ -113,20 +514,30 @@ fn exec_numeric_op(op: char, left: Val, right: Val) -> ExecResult {
         '%' => left % right,
 
         _ => panic!("binary op '{}' not implemented", op),
-    };
-
-    Ok(Val::Num(r))
+    }
 }
 
 
 pub fn exec_func_call(ctx: &mut ExecContext, loc: &Loc, name: &str, args: Vec<Val>) -> ExecResult {
+    ctx.check_interrupt(loc)?;
+
+    if ctx.is_resuming() {
+        return resume_func_call(ctx, loc);
+    }
+
     let func_val = match ctx.lookup_name(name) {
         Some(val) => val,
         None => return loc.error(format!("'{}': undeclared function name", name)).into(),
     };
 
-    match func_val {
+    exec_call_val(ctx, loc, name, &func_val, args)
+}
+
+
+fn exec_call_val(ctx: &mut ExecContext, loc: &Loc, name: &str, func_val: &Val, args: Vec<Val>) -> ExecResult {
+    match *func_val {
         Val::Func{ ref decl, ref scope } => {
+            ctx.charge_scope(loc)?;
             let mut ctx = ctx.new_with(scope); // override it with nested context
 
             // allow return in nested context
@@ -139,20 +550,49 @@ pub fn exec_func_call(ctx: &mut ExecContext, loc: &Loc, name: &str, args: Vec<Va
             }
 
             for (arg_name, arg_val) in decl.args.iter().zip(args) {
+                ctx.charge_var(loc, arg_name)?;
                 ctx.set_var(arg_name, arg_val);
             }
 
-            let r = execute(&mut ctx, &decl.body);
+            if decl.generator {
+                // body is executed lazily on value requests
+                let gen = Generator::new(decl, Shared::clone(&ctx.scope));
+                return Ok(Val::Generator(Shared::new(gen)));
+            }
 
-            return match r {
-                Err(FlowExc::Return(val)) => Ok(val),
-                _ => r,
-            };
+            exec_func_body(&mut ctx, decl)
         }
 
         Val::NativeFunc(ref decl) => {
-            let r = (decl.callback)(args);
-            return Ok(r);
+            if !decl.arity.accepts(args.len()) {
+                return loc.error(
+                    format!("wrong arguments count, expected {} args",
+                            decl.arity)).into();
+            }
+
+            let callback = match decl.callback {
+                NativeCallback::Plain(ref callback) => callback,
+                NativeCallback::Context(ref callback) => {
+                    let r = callback(&mut CallContext::new(ctx, loc, name), args)?;
+                    ctx.charge(loc, r.heap_size())?;
+                    return Ok(r);
+                }
+            };
+
+            match callback(args) {
+                Poll::Ready(Ok(r)) => {
+                    ctx.charge(loc, r.heap_size())?;
+                    Ok(r)
+                }
+
+                Poll::Ready(Err(msg)) => {
+                    loc.error(format!("'{}': {}", name, msg)).into()
+                }
+
+                Poll::Pending => {
+                    Err(ctx.save_frame(FlowExc::Suspend, Frame::Native))
+                }
+            }
         }
 
         _ => {
@@ -164,3 +604,88 @@ pub fn exec_func_call(ctx: &mut ExecContext, loc: &Loc, name: &str, args: Vec<Va
 }
 
 
+fn resume_func_call(ctx: &mut ExecContext, loc: &Loc) -> ExecResult {
+    match ctx.pop_frame() {
+        Frame::Body{ ref decl, ref scope } => {
+            let mut ctx = ctx.resume_with_scope(scope);
+            ctx.allow_return = true;
+
+            exec_func_body(&mut ctx, decl)
+        }
+
+        Frame::Native => {
+            let r = ctx.take_resume_val()
+                .expect("resumed execution has no native function result");
+            ctx.charge(loc, r.heap_size())?;
+
+            Ok(r)
+        }
+
+        frame => unexpected_frame(frame),
+    }
+}
+
+
+/// Request the next value from a generator.
+pub fn exec_next(ctx: &mut ExecContext, loc: &Loc, iter: &Val) -> Result<Option<Val>, FlowExc> {
+    let gen = match *iter {
+        Val::Generator(ref gen) => gen,
+        _ => return Err(loc.error(format!(
+            "type mismatch, generator expected, found '{}'", iter)).into()),
+    };
+
+    // generator frames are already on the stack if outer execution resumes
+    if !ctx.is_resuming() {
+        let state = mem::replace(&mut *gen.state.borrow_mut(), GenState::Running);
+
+        match state {
+            GenState::Ready(frames) => ctx.restore_frames(frames),
+            GenState::Running => {
+                return Err(loc.error(format!(
+                    "'{}': generator is already running", gen.decl.name)).into());
+            }
+            GenState::Done => {
+                *gen.state.borrow_mut() = GenState::Done;
+                return Ok(None);
+            }
+        }
+    }
+
+    let mut gen_ctx = ctx.resume_with_scope(&gen.scope);
+    gen_ctx.allow_return = true;
+    gen_ctx.allow_yield = true;
+
+    match exec(&mut gen_ctx, &gen.decl.body) {
+        Err(FlowExc::Yield(val)) => {
+            *gen.state.borrow_mut() = GenState::Ready(ctx.take_frames());
+            ctx.charge(loc, val.heap_size())?;
+            Ok(Some(val))
+        }
+
+        // keep running state, frames are saved by the caller
+        Err(FlowExc::Suspend) => Err(FlowExc::Suspend),
+
+        r => {
+            *gen.state.borrow_mut() = GenState::Done;
+
+            match r {
+                Ok(_) | Err(FlowExc::Return(_)) => Ok(None),
+                Err(exc) => Err(exc),
+            }
+        }
+    }
+}
+
+
+fn exec_func_body(ctx: &mut ExecContext, decl: &Shared<FuncDecl>) -> ExecResult {
+    let r = exec(ctx, &decl.body);
+
+    match r {
+        Ok(_) => Ok(Val::None),
+        Err(FlowExc::Return(val)) => Ok(val),
+        Err(exc) => Err(ctx.save_frame(exc, Frame::Body{
+            decl: Shared::clone(decl),
+            scope: Shared::clone(&ctx.scope),
+        })),
+    }
+}
@@ -0,0 +1,69 @@
+use compiler;
+use compiler::*;
+use super::exec;
+
+
+/// Load the module imported by the script at `loc`, it is executed only the
+/// first time and cached then.
+pub fn exec_import(ctx: &mut ExecContext, loc: &Loc, path: &str) -> Result<Shared<Module>, FlowExc> {
+    let loader = match ctx.module_loader() {
+        Some(loader) => loader,
+        None => return Err(loc.error(format!("'{}': no module loader", path)).into()),
+    };
+
+    let name = loader.resolve(path, &loc.filename)
+        .map_err(|msg| loc.error(format!("'{}': {}", path, msg)))?;
+
+    if let Some(module) = ctx.cached_module(&name) {
+        return Ok(module);
+    }
+
+    ctx.start_import(&name)
+        .map_err(|chain| loc.error(format!("'{}': cyclic import ({})", path, chain.join(" -> "))))?;
+
+    let r = exec_module(ctx, loc, &*loader, path, &name);
+    ctx.finish_import(&name);
+
+    let module = Shared::new(r?);
+    ctx.cache_module(&module);
+
+    Ok(module)
+}
+
+
+fn exec_module(ctx: &mut ExecContext, loc: &Loc, loader: &dyn ModuleLoader, path: &str, name: &str) -> Result<Module, FlowExc> {
+    let text = loader.load(name)
+        .map_err(|msg| loc.error(format!("'{}': {}", path, msg)))?;
+    let prog = compiler::parse(&text, name)?;
+
+    ctx.charge_scope(loc)?;
+    let scope = ctx.new_module_scope();
+    let mut module_ctx = ctx.resume_with_scope(&scope);
+
+    match exec(&mut module_ctx, &prog) {
+        Ok(_) => (),
+
+        Err(FlowExc::Suspend) => {
+            // there is no way to resume module execution later
+            drop(ctx.take_frames());
+            return Err(loc.error(format!(
+                "'{}': native function result is pending while importing module", path)).into());
+        }
+
+        Err(exc) => return Err(exc),
+    }
+
+    Ok(Module {
+        name: String::from(name),
+        scope,
+    })
+}
+
+
+/// Variable exported by the module.
+pub fn module_var(loc: &Loc, module: &Module, name: &str) -> ExecResult {
+    match module.scope.get(name) {
+        Some(val) => Ok(val),
+        None => loc.error(format!("'{}': not found in module '{}'", name, module.name)).into(),
+    }
+}
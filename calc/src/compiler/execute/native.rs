@@ -0,0 +1,52 @@
+use compiler::*;
+use super::exec_call_val;
+
+
+/// Access to the interpreter from natives declared by
+/// `ExecContext::decl_context_func`, e.g. to call script functions passed
+/// as arguments.
+pub struct CallContext<'a> {
+    ctx: &'a mut ExecContext,
+    loc: &'a Loc,
+    name: &'a str,
+}
+
+
+impl<'a> CallContext<'a> {
+    pub fn new(ctx: &'a mut ExecContext, loc: &'a Loc, name: &'a str) -> Self {
+        CallContext { ctx, loc, name }
+    }
+
+    /// Call a script or native function value. Its errors are passed as is,
+    /// so they keep their location.
+    pub fn call(&mut self, func: &Val, args: Vec<Val>) -> ExecResult {
+        let name = match *func {
+            Val::Func{ ref decl, .. } => decl.name.clone(),
+            Val::NativeFunc(ref decl) => decl.name.clone(),
+            _ => String::from(self.name),
+        };
+
+        match exec_call_val(self.ctx, self.loc, &name, func, args) {
+            Err(FlowExc::Suspend) => {
+                // there is no way to resume in the middle of a native
+                drop(self.ctx.take_frames());
+                Err(self.error("native function result is pending inside a callback"))
+            }
+            r => r,
+        }
+    }
+
+    /// Error reported at the call site of the native.
+    pub fn error(&self, msg: &str) -> FlowExc {
+        self.loc.error(format!("'{}': {}", self.name, msg)).into()
+    }
+
+    /// Account memory allocated by the native, e.g. for growing a list.
+    pub fn charge(&self, bytes: usize) -> Result<(), FlowExc> {
+        self.ctx.charge(self.loc, bytes).map_err(FlowExc::from)
+    }
+
+    pub fn loc(&self) -> &Loc {
+        self.loc
+    }
+}
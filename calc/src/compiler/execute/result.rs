@@ -5,6 +5,10 @@ use compiler::*;
 pub enum FlowExc {
     Error(Error),
     Return(Val),
+    /// Native function result is pending, see `ExecContext::resume_with`.
+    Suspend,
+    /// Generator produced a value.
+    Yield(Val),
     // Break,
     // Continue,
 }
@@ -14,18 +18,37 @@ pub type ExecResult = Result<Val, FlowExc>;
 
 
 impl FlowExc {
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_error(self) -> Error {
         match self {
             FlowExc::Error(err) => err,
             _ => panic!("Unexpected flow exception: {:?}", self),
         }
     }
+
+    /// Error for the exception reaching the host, `loc` is used when the
+    /// exception doesn't carry its own location.
+    pub fn into_error(self, loc: &Loc) -> Error {
+        match self {
+            FlowExc::Error(err) => err,
+            FlowExc::Return(_) => loc.error_str("unexpected 'return' statement"),
+            FlowExc::Suspend => loc.error_str("execution suspended, native function result is pending"),
+            FlowExc::Yield(_) => loc.error_str("unexpected 'yield' statement"),
+        }
+    }
+}
+
+
+impl From<Error> for FlowExc {
+    fn from(err: Error) -> Self {
+        FlowExc::Error(err)
+    }
 }
 
 
-impl Into<ExecResult> for Error {
-    fn into(self) -> ExecResult {
-        Err(FlowExc::Error(self))
+impl From<Error> for ExecResult {
+    fn from(err: Error) -> Self {
+        Err(FlowExc::Error(err))
     }
 }
 
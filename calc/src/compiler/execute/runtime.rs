@@ -0,0 +1,556 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use compiler::*;
+use compiler::shared::Weak;
+use super::frame::{Frame, Entry};
+
+
+/// Part of the memory limit to charge between recounts of the used memory.
+const RECOUNT_FRACTION: usize = 8;
+
+
+/// State shared by an `ExecContext` and all nested contexts created for
+/// function calls.
+///
+/// Closures reference their defining scope, which in turn stores them, so
+/// scopes form `Rc` cycles, as do lists and maps that hold themselves. The
+/// runtime keeps track of every scope it has created and every list or map
+/// stored in a variable, breaks unreachable cycles from time to time and
+/// clears the remaining scopes once the last context is dropped.
+pub struct Runtime {
+    memory_limit: AtomicUsize,  // usize::MAX if there is no limit
+    memory_used: AtomicUsize,   // approximate, recounted on limit hit
+    memory_counted: AtomicUsize,    // usage right after the last recount
+    scopes: Lock<Vec<Weak<Scope>>>,
+    scopes_pruned: AtomicUsize, // registry size after the last cleanup
+    containers: Lock<HashMap<usize, WeakContainer>>,    // by address
+    containers_pruned: AtomicUsize,
+    interrupt: InterruptHandle,
+    frames: Lock<Vec<Frame>>,       // saved progress of a suspended execution
+    suspended: Lock<Option<Entry>>,
+    resume_val: Lock<Option<Val>>,
+    loader: Lock<Option<Shared<dyn ModuleLoader>>>,
+    modules: Lock<HashMap<String, Shared<Module>>>,
+    importing: Lock<Vec<String>>,   // modules being executed, outermost first
+}
+
+
+/// Stops execution of a script from any thread.
+///
+/// The script fails with `ErrorKind::Interrupted` the next time the
+/// interpreter checks the handle (before each statement and function call).
+/// A request made while no script is running is dropped when the next one
+/// starts, but it still stops a suspended script once it's resumed.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle {
+    flag: Arc<AtomicBool>,
+}
+
+
+impl InterruptHandle {
+    pub fn interrupt(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+
+    /// Cancel pending interrupt request.
+    pub fn reset(&self) {
+        self.flag.store(false, Ordering::SeqCst);
+    }
+}
+
+
+impl Runtime {
+    pub fn new() -> Self {
+        Runtime {
+            memory_limit: AtomicUsize::new(usize::MAX),
+            memory_used: AtomicUsize::new(0),
+            memory_counted: AtomicUsize::new(0),
+            scopes: Lock::new(Vec::new()),
+            scopes_pruned: AtomicUsize::new(0),
+            containers: Lock::new(HashMap::new()),
+            containers_pruned: AtomicUsize::new(0),
+            interrupt: InterruptHandle::default(),
+            frames: Lock::new(Vec::new()),
+            suspended: Lock::new(None),
+            resume_val: Lock::new(None),
+            loader: Lock::new(None),
+            modules: Lock::new(HashMap::new()),
+            importing: Lock::new(Vec::new()),
+        }
+    }
+
+    pub fn memory_limit(&self) -> Option<usize> {
+        match self.memory_limit.load(Ordering::Relaxed) {
+            usize::MAX => None,
+            limit => Some(limit),
+        }
+    }
+
+    pub fn set_memory_limit(&self, limit: Option<usize>) {
+        self.memory_limit.store(limit.unwrap_or(usize::MAX), Ordering::Relaxed);
+    }
+
+    pub fn interrupt_handle(&self) -> &InterruptHandle {
+        &self.interrupt
+    }
+
+    /// Fails if the interrupt was requested, the request is consumed so
+    /// the context could be used again.
+    pub fn check_interrupt(&self, loc: &Loc) -> Result<(), Error> {
+        if !self.interrupt.flag.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        Err(loc.error_str("execution interrupted")
+            .with_kind(ErrorKind::Interrupted))
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.borrow().is_some()
+    }
+
+    pub fn resume_with(&self, val: Val) {
+        *self.resume_val.borrow_mut() = Some(val);
+    }
+
+    pub fn take_resume_val(&self) -> Option<Val> {
+        self.resume_val.borrow_mut().take()
+    }
+
+    pub fn is_resuming(&self) -> bool {
+        !self.frames.borrow().is_empty()
+    }
+
+    pub fn take_frames(&self) -> Vec<Frame> {
+        mem::take(&mut *self.frames.borrow_mut())
+    }
+
+    /// Continue with frames saved earlier (e.g. by a generator).
+    pub fn restore_frames(&self, frames: Vec<Frame>) {
+        let mut cur = self.frames.borrow_mut();
+        debug_assert!(cur.is_empty());
+
+        *cur = frames;
+    }
+
+    pub fn push_frame(&self, frame: Frame) {
+        self.frames.borrow_mut().push(frame);
+    }
+
+    pub fn pop_frame(&self) -> Frame {
+        self.frames.borrow_mut().pop()
+            .expect("resumed execution has no saved frames")
+    }
+
+    pub fn peek_frame<F, R>(&self, callback: F) -> Option<R>
+        where F: FnOnce(&Frame) -> R
+    {
+        self.frames.borrow().last().map(callback)
+    }
+
+    /// Called by the host entry point before execution, checks that the
+    /// suspended execution (if any) is resumed correctly.
+    pub fn enter(&self, entry: &Entry, loc: &Loc) -> Result<(), Error> {
+        let mut suspended = self.suspended.borrow_mut();
+
+        match *suspended {
+            None => {
+                // stale result of a cancelled execution, and interrupt
+                // requested while nothing was running
+                self.resume_val.borrow_mut().take();
+                self.interrupt.reset();
+                return Ok(());
+            }
+
+            Some(ref expected) if expected != entry => {
+                return Err(loc.error_str(
+                    "execution is suspended, it should be resumed with the same program"));
+            }
+
+            _ => (),
+        }
+
+        if self.resume_val.borrow().is_none() {
+            return Err(loc.error_str(
+                "execution is suspended, native function result is expected"));
+        }
+
+        *suspended = None;
+        Ok(())
+    }
+
+    /// Called by the host entry point after execution.
+    pub fn leave(&self, entry: Entry, suspended: bool) {
+        if suspended {
+            *self.suspended.borrow_mut() = Some(entry);
+        } else {
+            self.cancel_suspended();
+        }
+    }
+
+    pub fn cancel_suspended(&self) {
+        let frames = mem::take(&mut *self.frames.borrow_mut());
+        drop(frames);
+
+        *self.suspended.borrow_mut() = None;
+        self.resume_val.borrow_mut().take();
+    }
+
+    pub fn set_module_loader(&self, loader: Shared<dyn ModuleLoader>) {
+        *self.loader.borrow_mut() = Some(loader);
+    }
+
+    pub fn module_loader(&self) -> Option<Shared<dyn ModuleLoader>> {
+        self.loader.borrow().clone()
+    }
+
+    pub fn cached_module(&self, name: &str) -> Option<Shared<Module>> {
+        self.modules.borrow().get(name).cloned()
+    }
+
+    pub fn cache_module(&self, module: &Shared<Module>) {
+        self.modules.borrow_mut().insert(module.name.clone(), Shared::clone(module));
+    }
+
+    /// Mark the module as being executed, fails with the chain of imports
+    /// if it is imported cyclically.
+    pub fn start_import(&self, name: &str) -> Result<(), Vec<String>> {
+        let mut importing = self.importing.borrow_mut();
+
+        if let Some(idx) = importing.iter().position(|cur| cur == name) {
+            let mut chain = importing[idx..].to_vec();
+            chain.push(String::from(name));
+            return Err(chain);
+        }
+
+        importing.push(String::from(name));
+        Ok(())
+    }
+
+    pub fn finish_import(&self, name: &str) {
+        let mut importing = self.importing.borrow_mut();
+        debug_assert_eq!(importing.last().map(String::as_str), Some(name));
+
+        importing.pop();
+    }
+
+    pub fn register_scope(&self, scope: &Shared<Scope>) {
+        let mut scopes = self.scopes.borrow_mut();
+
+        scopes.push(Shared::downgrade(scope));
+        let collect = scopes.len() >= 2 * self.scopes_pruned.load(Ordering::Relaxed).max(16);
+        drop(scopes);
+
+        // look for garbage once the registry doubles in size
+        if collect {
+            self.collect_cycles();
+        }
+    }
+
+    /// Track the list or map stored in a variable, so that it's collected
+    /// if it ends up referencing itself. Other values are ignored.
+    pub fn register_container(&self, val: &Val) {
+        let (addr, weak) = match *val {
+            Val::List(ref items) => (Shared::as_ptr(items) as usize, WeakContainer::List(Shared::downgrade(items))),
+            Val::Map(ref items) => (Shared::as_ptr(items) as usize, WeakContainer::Map(Shared::downgrade(items))),
+            _ => return,
+        };
+
+        let mut containers = self.containers.borrow_mut();
+
+        // replaces a dead entry if the address was reused
+        containers.insert(addr, weak);
+        let collect = containers.len() >= 2 * self.containers_pruned.load(Ordering::Relaxed).max(16);
+        drop(containers);
+
+        if collect {
+            self.collect_cycles();
+        }
+    }
+
+    /// Free scopes, lists and maps that are only referenced from other
+    /// unreachable ones, returns the number of freed values.
+    pub fn collect_cycles(&self) -> usize {
+        let freed = sweep(self.nodes());
+        self.prune();
+
+        freed
+    }
+
+    /// Live scopes and containers known to the runtime, and the containers
+    /// reachable from them.
+    fn nodes(&self) -> Vec<Node> {
+        self.prune();
+
+        let mut nodes: Vec<Node> = self.scopes.borrow().iter()
+            .filter_map(Weak::upgrade)
+            .map(Node::Scope)
+            .collect();
+        nodes.extend(self.containers.borrow().values().filter_map(WeakContainer::upgrade));
+
+        let mut seen: HashSet<usize> = nodes.iter().map(Node::addr).collect();
+        let mut idx = 0;
+
+        while idx < nodes.len() {
+            let mut found = Vec::new();
+            nodes[idx].for_each_ref(|child| {
+                if seen.insert(child.addr()) {
+                    found.push(child);
+                }
+            });
+
+            nodes.extend(found);
+            idx += 1;
+        }
+
+        nodes
+    }
+
+    /// Forget dropped scopes and containers.
+    fn prune(&self) {
+        let mut scopes = self.scopes.borrow_mut();
+        scopes.retain(|scope| scope.upgrade().is_some());
+        self.scopes_pruned.store(scopes.len(), Ordering::Relaxed);
+
+        let mut containers = self.containers.borrow_mut();
+        containers.retain(|_, container| container.upgrade().is_some());
+        self.containers_pruned.store(containers.len(), Ordering::Relaxed);
+    }
+
+    /// Count bytes held by values of all live scopes.
+    pub fn measure(&self) -> usize {
+        let mut seen = HashSet::new();
+        let mut total = 0;
+
+        for scope in self.scopes.borrow().iter() {
+            if let Some(scope) = scope.upgrade() {
+                total += scope_size(&scope, &mut seen);
+            }
+        }
+
+        total
+    }
+
+    /// Account `bytes` of a new allocation, fails when it doesn't fit into
+    /// the memory limit.
+    ///
+    /// Recounting is as slow as the values are many, so once over the limit
+    /// it's done only after another 1/8 of the limit is charged: a script
+    /// close to the limit may exceed it by that much before it fails.
+    pub fn charge(&self, loc: &Loc, bytes: usize) -> Result<(), Error> {
+        let used = self.memory_used.fetch_add(bytes, Ordering::Relaxed) + bytes;
+
+        let limit = match self.memory_limit() {
+            Some(limit) if used > limit => limit,
+            _ => return Ok(()),
+        };
+
+        let counted = self.memory_counted.load(Ordering::Relaxed);
+        if used.saturating_sub(counted) < limit / RECOUNT_FRACTION {
+            return Ok(());
+        }
+
+        // values dropped since the last recount are refunded here
+        let held = self.measure();
+        self.memory_used.store(held, Ordering::Relaxed);
+        self.memory_counted.store(held, Ordering::Relaxed);
+
+        if held + bytes > limit {
+            return Err(loc.error(format!(
+                "memory limit exceeded: {} bytes requested, {} of {} bytes in use",
+                bytes, held, limit)).with_kind(ErrorKind::MemoryLimit));
+        }
+
+        self.memory_used.store(held + bytes, Ordering::Relaxed);
+        self.memory_counted.store(held + bytes, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        // no context is alive anymore, so clear all remaining scopes
+        let nodes = self.nodes();
+        let mut garbage = Vec::new();
+
+        for node in nodes.iter().filter(|node| matches!(node, Node::Scope(..))) {
+            node.clear(&mut garbage);
+        }
+
+        drop(garbage);
+
+        // lists and maps may still be held by the host
+        let containers = nodes.into_iter()
+            .filter(|node| !matches!(node, Node::Scope(..)))
+            .collect();
+        sweep(containers);
+    }
+}
+
+
+/// Value that may take part in a reference cycle.
+enum Node {
+    Scope(Shared<Scope>),
+    List(Shared<Lock<Vec<Val>>>),
+    Map(Shared<Lock<BTreeMap<String, Val>>>),
+}
+
+
+enum WeakContainer {
+    List(Weak<Lock<Vec<Val>>>),
+    Map(Weak<Lock<BTreeMap<String, Val>>>),
+}
+
+
+impl Node {
+    fn from_val(val: &Val) -> Option<Node> {
+        match *val {
+            Val::Func{ ref scope, .. } => Some(Node::Scope(Shared::clone(scope))),
+            Val::Generator(ref gen) => Some(Node::Scope(Shared::clone(&gen.scope))),
+            Val::Module(ref module) => Some(Node::Scope(Shared::clone(&module.scope))),
+            Val::List(ref items) => Some(Node::List(Shared::clone(items))),
+            Val::Map(ref items) => Some(Node::Map(Shared::clone(items))),
+            _ => None,
+        }
+    }
+
+    fn addr(&self) -> usize {
+        match *self {
+            Node::Scope(ref scope) => Shared::as_ptr(scope) as *const () as usize,
+            Node::List(ref items) => Shared::as_ptr(items) as *const () as usize,
+            Node::Map(ref items) => Shared::as_ptr(items) as *const () as usize,
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match *self {
+            Node::Scope(ref scope) => Shared::strong_count(scope),
+            Node::List(ref items) => Shared::strong_count(items),
+            Node::Map(ref items) => Shared::strong_count(items),
+        }
+    }
+
+    /// Scopes and containers referenced from this one. Other values (e.g.
+    /// host objects) are not traversed, so whatever they reference is
+    /// treated as referenced from outside and kept alive.
+    fn for_each_ref<F>(&self, mut callback: F)
+        where F: FnMut(Node)
+    {
+        match *self {
+            Node::Scope(ref scope) => {
+                if let Some(ref parent) = scope.parent {
+                    callback(Node::Scope(Shared::clone(parent)));
+                }
+
+                scope.vals.borrow().values().filter_map(Node::from_val).for_each(callback);
+            }
+
+            Node::List(ref items) => items.borrow().iter().filter_map(Node::from_val).for_each(callback),
+            Node::Map(ref items) => items.borrow().values().filter_map(Node::from_val).for_each(callback),
+        }
+    }
+
+    /// Move the values out, dropping them breaks the cycles.
+    fn clear(&self, garbage: &mut Vec<Val>) {
+        match *self {
+            Node::Scope(ref scope) => garbage.extend(mem::take(&mut *scope.vals.borrow_mut()).into_values()),
+            Node::List(ref items) => garbage.append(&mut *items.borrow_mut()),
+            Node::Map(ref items) => garbage.extend(mem::take(&mut *items.borrow_mut()).into_values()),
+        }
+    }
+}
+
+
+impl WeakContainer {
+    fn upgrade(&self) -> Option<Node> {
+        match *self {
+            WeakContainer::List(ref items) => items.upgrade().map(Node::List),
+            WeakContainer::Map(ref items) => items.upgrade().map(Node::Map),
+        }
+    }
+}
+
+
+/// Clear the nodes not reachable from outside of the given ones, returns
+/// the number of cleared nodes.
+fn sweep(nodes: Vec<Node>) -> usize {
+    let index: HashMap<usize, usize> = nodes.iter()
+        .enumerate()
+        .map(|(idx, node)| (node.addr(), idx))
+        .collect();
+
+    // count references coming from the nodes themselves, the rest of
+    // references are held outside (stack, host, contexts)
+    let mut internal = vec![0; nodes.len()];
+    for node in &nodes {
+        node.for_each_ref(|child| {
+            if let Some(&idx) = index.get(&child.addr()) {
+                internal[idx] += 1;
+            }
+        });
+    }
+
+    // mark everything reachable from externally referenced nodes
+    let mut marked = vec![false; nodes.len()];
+    let mut stack: Vec<usize> = (0..nodes.len())
+        .filter(|&idx| nodes[idx].strong_count() - 1 > internal[idx])
+        .collect();
+
+    while let Some(idx) = stack.pop() {
+        if marked[idx] {
+            continue;
+        }
+
+        marked[idx] = true;
+        nodes[idx].for_each_ref(|child| {
+            if let Some(&child) = index.get(&child.addr()) {
+                stack.push(child);
+            }
+        });
+    }
+
+    // clearing values breaks the cycles, so the nodes are freed below
+    let mut garbage = Vec::new();
+    let mut freed = 0;
+
+    for (node, _) in nodes.iter().zip(&marked).filter(|&(_, &marked)| !marked) {
+        node.clear(&mut garbage);
+        freed += 1;
+    }
+
+    drop(garbage);
+    freed
+}
+
+
+fn scope_size(scope: &Scope, seen: &mut HashSet<*const ()>) -> usize {
+    let mut total = mem::size_of::<Scope>();
+
+    for (name, val) in scope.vals.borrow().iter() {
+        total += name.capacity() + mem::size_of::<(String, Val)>();
+
+        // count shared values only once
+        let ptr = match *val {
+            Val::Str(ref val) => Shared::as_ptr(val) as *const (),
+            Val::NativeFunc(ref decl) => Shared::as_ptr(decl) as *const (),
+            Val::Error(ref err) => Shared::as_ptr(err) as *const (),
+            Val::List(ref items) => Shared::as_ptr(items) as *const (),
+            Val::Map(ref items) => Shared::as_ptr(items) as *const (),
+            Val::Object(ref obj) => Shared::as_ptr(obj) as *const (),
+            _ => continue,
+        };
+
+        if seen.insert(ptr) {
+            total += val.heap_size();
+        }
+    }
+
+    total
+}
@@ -0,0 +1,356 @@
+use compiler;
+use compiler::*;
+use compiler::tokenizer;
+
+
+const INDENT: &str = "    ";
+
+
+/// Rewrite the script into the canonical layout: statements on their own
+/// lines indented by four spaces, single spaces around operators, blank
+/// lines of the source collapsed to one and blank lines around functions.
+///
+/// Comments are kept: a comment on the line of a statement end stays after
+/// it, other comments are placed on their own lines before the statement
+/// that follows them (comments inside an expression move there as well).
+/// Formatting a formatted text gives the same text.
+pub fn format(text: &str, filename: &str) -> Result<String, Error> {
+    let (_, comments) = tokenizer::tokenize_with_comments(text, filename)?;
+    let ast = compiler::parse(text, filename)?;
+
+    let mut f = Formatter {
+        comments: comments.into_iter().map(|comment| (comment, false)).collect(),
+        blank: text.lines().map(|line| line.trim().is_empty()).collect(),
+        out: String::new(),
+    };
+
+    if let AST::Block{ ref body, ref end, .. } = ast {
+        f.block(body, end, 0);
+    }
+
+    Ok(f.out)
+}
+
+
+struct Formatter<'a> {
+    comments: Vec<(Comment<'a>, bool)>,  // whether the comment is written
+    blank: Vec<bool>,   // blank lines of the source
+    out: String,
+}
+
+
+/// Last line written in the source terms, to keep blank lines.
+struct Prev {
+    line: i32,
+    is_fn: bool,
+}
+
+
+impl<'a> Formatter<'a> {
+    fn block(&mut self, body: &[AST], end: &Loc, depth: usize) {
+        let mut prev: Option<Prev> = None;
+
+        for (idx, st) in body.iter().enumerate() {
+            let is_fn = matches!(*st, AST::Func{..});
+            let (first, last) = lines(st);
+
+            // comments right before the statement go with it, others are
+            // separate paragraphs
+            let mut groups = self.take_comments(first_offset(st));
+            let attached = match groups.last() {
+                Some(group) if !self.has_blank(self.comments[*group.last().unwrap()].0.loc.line, first) => groups.pop(),
+                _ => None,
+            };
+
+            for group in groups {
+                self.comment_group(&group, &mut prev, depth);
+            }
+
+            let unit_line = attached.as_ref()
+                .map_or(first, |group| self.comments[group[0]].0.loc.line);
+            self.separate(&prev, unit_line, is_fn);
+
+            if let Some(group) = attached {
+                self.write_comments(&group, depth);
+            }
+
+            self.indent(depth);
+            self.statement(st, depth);
+
+            // comment at the end of the statement's last line
+            let next = body.get(idx + 1).map_or(end.start, first_offset);
+            let trailing = self.comments.iter()
+                .position(|c| !c.1 && c.0.loc.line == last && c.0.loc.start < next);
+
+            if let Some(pos) = trailing {
+                self.comments[pos].1 = true;
+                self.out.push(' ');
+                self.out.push_str(self.comments[pos].0.text);
+            }
+
+            self.out.push('\n');
+            prev = Some(Prev { line: last, is_fn });
+        }
+
+        for group in self.take_comments(end.start) {
+            self.comment_group(&group, &mut prev, depth);
+        }
+    }
+
+    /// Blank line between paragraphs, functions are always separated.
+    fn separate(&mut self, prev: &Option<Prev>, line: i32, is_fn: bool) {
+        if let Some(ref prev) = *prev {
+            if self.has_blank(prev.line, line) || prev.is_fn || is_fn {
+                self.out.push('\n');
+            }
+        }
+    }
+
+    /// Whether the source has a blank line between the given ones.
+    fn has_blank(&self, from: i32, to: i32) -> bool {
+        (from + 1..to).any(|line| self.blank.get(line as usize - 1).cloned().unwrap_or(false))
+    }
+
+    /// Comments before the given offset, split by blank lines.
+    fn take_comments(&mut self, offset: usize) -> Vec<Vec<usize>> {
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut prev_line = 0;
+
+        for idx in 0..self.comments.len() {
+            let (line, start) = (self.comments[idx].0.loc.line, self.comments[idx].0.loc.start);
+
+            if self.comments[idx].1 || start >= offset {
+                continue;
+            }
+
+            self.comments[idx].1 = true;
+
+            match groups.last_mut() {
+                Some(group) if !self.has_blank(prev_line, line) => group.push(idx),
+                _ => groups.push(vec![idx]),
+            }
+
+            prev_line = line;
+        }
+
+        groups
+    }
+
+    fn comment_group(&mut self, group: &[usize], prev: &mut Option<Prev>, depth: usize) {
+        let first = self.comments[group[0]].0.loc.line;
+        let last = self.comments[*group.last().unwrap()].0.loc.line;
+
+        self.separate(prev, first, false);
+        self.write_comments(group, depth);
+        *prev = Some(Prev { line: last, is_fn: false });
+    }
+
+    fn write_comments(&mut self, group: &[usize], depth: usize) {
+        for idx in group {
+            self.indent(depth);
+            self.out.push_str(self.comments[*idx].0.text);
+            self.out.push('\n');
+        }
+    }
+
+    fn indent(&mut self, depth: usize) {
+        for _ in 0..depth {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn statement(&mut self, st: &AST, depth: usize) {
+        use self::AST::*;
+
+        match *st {
+            DeclVar{ ref name, ref init, .. } => self.push(&format!("let {} = {};", name, expr(init))),
+            Assign{ ref name, ref init, .. } => self.push(&format!("{} = {};", name, expr(init))),
+            Return{ ref ret, .. } => self.push(&format!("return {};", expr(ret))),
+            Yield{ ref val, .. } => self.push(&format!("yield {};", expr(val))),
+            Throw{ ref val, .. } => self.push(&format!("throw {};", expr(val))),
+
+            Import{ ref path, ref alias, .. } => self.push(&format!("import {} as {};", quote(path), alias)),
+            FromImport{ ref path, ref names, .. } => {
+                self.push(&format!("from {} import {};", quote(path), names.join(", ")))
+            }
+
+            SetIndex{ ref obj, ref index, ref val, .. } => {
+                self.push(&format!("{}[{}] = {};", operand(obj), expr(index), expr(val)))
+            }
+            SetProp{ ref obj, ref name, ref val, .. } => {
+                self.push(&format!("{}.{} = {};", operand(obj), name, expr(val)))
+            }
+
+            Func{ ref decl, .. } => {
+                self.push(&format!("fn {}({}) ", decl.name, decl.args.join(", ")));
+                self.braced(&decl.body, depth);
+            }
+
+            For{ ref var, ref iter, ref body, .. } => {
+                self.push(&format!("for {} in {} ", var, expr(iter)));
+                self.braced(body, depth);
+            }
+
+            Try{ ref body, ref catch, ref finally, .. } => {
+                self.push("try ");
+                self.braced(body, depth);
+
+                if let Some((ref var, ref handler)) = *catch {
+                    self.push(&format!(" catch ({}) ", var));
+                    self.braced(handler, depth);
+                }
+
+                if let Some(ref finally) = *finally {
+                    self.push(" finally ");
+                    self.braced(finally, depth);
+                }
+            }
+
+            _ => self.push(&format!("{};", expr(st))),
+        }
+    }
+
+    fn braced(&mut self, block: &AST, depth: usize) {
+        let (body, end) = match *block {
+            AST::Block{ ref body, ref end, .. } => (body, end),
+            _ => unreachable!("block expected"),
+        };
+
+        let has_comments = self.comments.iter().any(|c| !c.1 && c.0.loc.start < end.start);
+
+        if body.is_empty() && !has_comments {
+            self.out.push_str("{}");
+            return;
+        }
+
+        self.out.push_str("{\n");
+        self.block(body, end, depth + 1);
+        self.indent(depth);
+        self.out.push('}');
+    }
+
+    fn push(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+}
+
+
+fn expr(ast: &AST) -> String {
+    use self::AST::*;
+
+    match *ast {
+        Num{ val, .. } => val.to_string(),
+        Float{ val, .. } => {
+            // `Display` never uses the exponent, which isn't supported
+            let text = val.to_string();
+            if text.contains('.') { text } else { text + ".0" }
+        }
+        Str{ ref val, .. } => quote(val),
+        Var{ ref name, .. } => name.clone(),
+        Call{ ref name, ref args, .. } => format!("{}({})", name, list(args)),
+
+        BinOp{ op, ref left, ref right, .. } => {
+            // operators are left-associative
+            let left = if precedence(left) < precedence(ast) { format!("({})", expr(left)) } else { expr(left) };
+            let right = if precedence(right) <= precedence(ast) { format!("({})", expr(right)) } else { expr(right) };
+
+            format!("{} {} {}", left, op, right)
+        }
+
+        List{ ref items, .. } => format!("[{}]", list(items)),
+        Map{ ref items, .. } => {
+            let items: Vec<String> = items.iter()
+                .map(|(key, val)| format!("{}: {}", quote(key), expr(val)))
+                .collect();
+
+            format!("{{{}}}", items.join(", "))
+        }
+
+        Index{ ref obj, ref index, .. } => format!("{}[{}]", operand(obj), expr(index)),
+        GetProp{ ref obj, ref name, .. } => format!("{}.{}", operand(obj), name),
+        MethodCall{ ref obj, ref name, ref args, .. } => format!("{}.{}({})", operand(obj), name, list(args)),
+
+        _ => unreachable!("statement in expression: {:?}", ast),
+    }
+}
+
+
+/// Object of indexing or a member access.
+fn operand(ast: &AST) -> String {
+    if let AST::BinOp{..} = *ast {
+        format!("({})", expr(ast))
+    } else {
+        expr(ast)
+    }
+}
+
+
+fn list(items: &[AST]) -> String {
+    items.iter().map(expr).collect::<Vec<_>>().join(", ")
+}
+
+
+fn precedence(ast: &AST) -> i32 {
+    match *ast {
+        AST::BinOp{ op: '+', .. } | AST::BinOp{ op: '-', .. } => 1,
+        AST::BinOp{..} => 2,
+        _ => 3,
+    }
+}
+
+
+fn quote(text: &str) -> String {
+    let mut r = String::with_capacity(text.len() + 2);
+    r.push('"');
+
+    for ch in text.chars() {
+        match ch {
+            '\\' => r.push_str("\\\\"),
+            '"' => r.push_str("\\\""),
+            '\n' => r.push_str("\\n"),
+            '\t' => r.push_str("\\t"),
+            _ => r.push(ch),
+        }
+    }
+
+    r.push('"');
+    r
+}
+
+
+/// Offset of the first token of the statement (keywords aside).
+fn first_offset(ast: &AST) -> usize {
+    let mut offset = usize::MAX;
+    visit_locs(ast, |loc| offset = offset.min(loc.start));
+    offset
+}
+
+
+/// First and last lines of the statement.
+fn lines(ast: &AST) -> (i32, i32) {
+    let mut r = (i32::MAX, 0);
+    visit_locs(ast, |loc| r = (r.0.min(loc.line), r.1.max(loc.line)));
+    r
+}
+
+
+/// Calls the function with the location of every node (and of block ends).
+struct LocVisitor<F>(F);
+
+
+impl<'a, F: FnMut(&Loc)> Visitor<'a> for LocVisitor<F> {
+    fn visit(&mut self, ast: &'a AST) {
+        (self.0)(ast.loc());
+        walk(self, ast);
+    }
+
+    fn visit_block(&mut self, _loc: &'a Loc, body: &'a [AST], end: &'a Loc) {
+        body.iter().for_each(|st| self.visit(st));
+        (self.0)(end);
+    }
+}
+
+
+fn visit_locs<F: FnMut(&Loc)>(ast: &AST, f: F) {
+    LocVisitor(f).visit(ast);
+}
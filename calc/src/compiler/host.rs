@@ -0,0 +1,135 @@
+//! Rust objects exposed to scripts, e.g. `acct.balance()` or `req.path`.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt;
+use std::ptr;
+use compiler::*;
+
+
+/// Rust value that scripts could access by properties and methods.
+///
+/// Errors are reported to the script at the access site, prefixed by the
+/// type and member names (`'Account.deposit': ...`). Use `FromArgs` to
+/// check method arguments.
+pub trait HostObject: Any + Threadsafe {
+    /// Type name used in error messages and the object's display.
+    fn type_name(&self) -> &str;
+
+    fn get(&self, name: &str) -> Result<Val, String> {
+        let _ = name;
+        Err(String::from("no such property"))
+    }
+
+    fn set(&mut self, name: &str, val: Val) -> Result<(), String> {
+        let _ = (name, val);
+        Err(String::from("property is read-only or missing"))
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Val>) -> Result<Val, String> {
+        let _ = (name, args);
+        Err(String::from("no such method"))
+    }
+}
+
+
+/// Host object owned by a `Val::Object`.
+///
+/// The type name is copied out of the object, so that errors and display
+/// don't need the lock, which is held while a method runs. The method may
+/// get its own object as an argument, accessing it then fails instead of
+/// locking the object again.
+pub struct HostRef {
+    name: String,
+    obj: Lock<Box<dyn HostObject>>,
+}
+
+
+impl HostRef {
+    pub fn new<T: HostObject>(obj: T) -> Self {
+        HostRef {
+            name: String::from(obj.type_name()),
+            obj: Lock::new(Box::new(obj)),
+        }
+    }
+
+    pub fn type_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get(&self, name: &str) -> Result<Val, String> {
+        let _in_use = InUse::enter(self)?;
+        self.obj.borrow().get(name)
+    }
+
+    pub fn set(&self, name: &str, val: Val) -> Result<(), String> {
+        let _in_use = InUse::enter(self)?;
+        self.obj.borrow_mut().set(name, val)
+    }
+
+    pub fn call(&self, name: &str, args: Vec<Val>) -> Result<Val, String> {
+        let _in_use = InUse::enter(self)?;
+        self.obj.borrow_mut().call(name, args)
+    }
+
+    /// Access the object as its concrete type, `None` if types differ or
+    /// the object is in use by its method up the stack.
+    pub fn with<T: HostObject, R, F: FnOnce(&mut T) -> R>(&self, f: F) -> Option<R> {
+        let _in_use = InUse::enter(self).ok()?;
+        let mut obj = self.obj.borrow_mut();
+        let obj: &mut dyn Any = &mut **obj;
+
+        obj.downcast_mut::<T>().map(f)
+    }
+}
+
+
+thread_local! {
+    // host objects accessed up the stack of the current thread, other
+    // threads wait for the lock instead
+    static IN_USE: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+
+/// Marks the object as accessed by the current thread until dropped.
+struct InUse(usize);
+
+
+impl InUse {
+    fn enter(obj: &HostRef) -> Result<Self, String> {
+        let addr = obj as *const HostRef as usize;
+
+        IN_USE.with(|in_use| {
+            let mut in_use = in_use.borrow_mut();
+
+            if in_use.contains(&addr) {
+                return Err(String::from("object is already in use by its method"));
+            }
+
+            in_use.push(addr);
+            Ok(InUse(addr))
+        })
+    }
+}
+
+
+impl Drop for InUse {
+    fn drop(&mut self) {
+        // a panic in the method may unwind past it
+        IN_USE.with(|in_use| in_use.borrow_mut().retain(|&addr| addr != self.0));
+    }
+}
+
+
+impl fmt::Debug for HostRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, r#"Val::Object("{}", ..)"#, self.type_name())
+    }
+}
+
+
+impl PartialEq for HostRef {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self, other)
+    }
+}
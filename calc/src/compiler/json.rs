@@ -0,0 +1,418 @@
+use std::fmt::{self, Write};
+use compiler::MAX_AST_DEPTH;
+
+
+/// JSON value, object members keep their order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+
+/// Arrays and objects nested deeper are rejected, so that hostile input
+/// can't overflow the stack. A node of the `AST` document takes up to 3
+/// levels (e.g. a `Map` item), so any tree the parser accepts fits.
+const MAX_DEPTH: usize = 4 * MAX_AST_DEPTH;
+
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,     // byte offset
+    depth: usize,   // arrays and objects being parsed
+}
+
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser { text, pos: 0, depth: 0 };
+
+        let r = parser.value()?;
+        parser.skip_ws();
+
+        if parser.pos < text.len() {
+            return Err(parser.error("unexpected data after the value"));
+        }
+
+        Ok(r)
+    }
+
+    /// Object from `(key, value)` pairs.
+    pub fn object<K: Into<String>>(members: Vec<(K, Json)>) -> Json {
+        Json::Object(members.into_iter().map(|(key, val)| (key.into(), val)).collect())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref members) => members.iter().find(|member| member.0 == key).map(|member| &member.1),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Json::Str(ref val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Json::Int(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Json::Int(val) => Some(val as f64),
+            Json::Float(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Json::Bool(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match *self {
+            Json::Array(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Json::Null
+    }
+
+    /// Text indented by two spaces, `Display` gives the compact form.
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, Some(0)).expect("writing to a string");
+        out
+    }
+
+    fn write<W: Write>(&self, out: &mut W, indent: Option<usize>) -> fmt::Result {
+        let (items, open, close): (Vec<(Option<&str>, &Json)>, char, char) = match *self {
+            Json::Null => return out.write_str("null"),
+            Json::Bool(val) => return write!(out, "{}", val),
+            Json::Int(val) => return write!(out, "{}", val),
+            // `Debug` is the shortest form that parses back to the same value
+            Json::Float(val) if val.is_finite() => return write!(out, "{:?}", val),
+            Json::Float(_) => return out.write_str("null"),
+            Json::Str(ref val) => return write_str(out, val),
+            Json::Array(ref items) => (items.iter().map(|item| (None, item)).collect(), '[', ']'),
+            Json::Object(ref members) => (members.iter().map(|m| (Some(m.0.as_str()), &m.1)).collect(), '{', '}'),
+        };
+
+        out.write_char(open)?;
+
+        for (idx, &(key, val)) in items.iter().enumerate() {
+            if idx > 0 {
+                out.write_char(',')?;
+            }
+
+            if let Some(indent) = indent {
+                newline(out, indent + 1)?;
+            }
+
+            if let Some(key) = key {
+                write_str(out, key)?;
+                out.write_str(if indent.is_some() { ": " } else { ":" })?;
+            }
+
+            val.write(out, indent.map(|indent| indent + 1))?;
+        }
+
+        if let Some(indent) = indent {
+            if !items.is_empty() {
+                newline(out, indent)?;
+            }
+        }
+
+        out.write_char(close)
+    }
+}
+
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, None)
+    }
+}
+
+
+impl<'a> From<&'a str> for Json {
+    fn from(val: &'a str) -> Self {
+        Json::Str(String::from(val))
+    }
+}
+
+
+impl From<String> for Json {
+    fn from(val: String) -> Self {
+        Json::Str(val)
+    }
+}
+
+
+impl From<i64> for Json {
+    fn from(val: i64) -> Self {
+        Json::Int(val)
+    }
+}
+
+
+impl From<usize> for Json {
+    fn from(val: usize) -> Self {
+        Json::Int(val as i64)
+    }
+}
+
+
+impl From<bool> for Json {
+    fn from(val: bool) -> Self {
+        Json::Bool(val)
+    }
+}
+
+
+impl From<Vec<Json>> for Json {
+    fn from(items: Vec<Json>) -> Self {
+        Json::Array(items)
+    }
+}
+
+
+fn newline<W: Write>(out: &mut W, indent: usize) -> fmt::Result {
+    out.write_char('\n')?;
+    for _ in 0..indent {
+        out.write_str("  ")?;
+    }
+    Ok(())
+}
+
+
+fn write_str<W: Write>(out: &mut W, text: &str) -> fmt::Result {
+    out.write_char('"')?;
+
+    for ch in text.chars() {
+        match ch {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            ch if (ch as u32) < 0x20 => write!(out, "\\u{:04x}", ch as u32)?,
+            ch => out.write_char(ch)?,
+        }
+    }
+
+    out.write_char('"')
+}
+
+
+impl<'a> Parser<'a> {
+    fn error(&self, msg: &str) -> String {
+        format!("offset {}: {}", self.pos, msg)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn skip_ws(&mut self) {
+        while let Some(ch) = self.peek() {
+            if !matches!(ch, ' ' | '\t' | '\n' | '\r') {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_ws();
+
+        match self.next() {
+            Some(ch) if ch == expected => Ok(()),
+            _ => Err(self.error(&format!("'{}' expected", expected))),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_ws();
+
+        match self.peek() {
+            Some('{') => self.nested(Parser::object),
+            Some('[') => self.nested(Parser::array),
+            Some('"') => self.string().map(Json::Str),
+            Some('-') | Some('0'..='9') => self.number(),
+            Some(_) => self.literal(),
+            None => Err(self.error("unexpected end of text")),
+        }
+    }
+
+    fn nested<F>(&mut self, parse: F) -> Result<Json, String>
+        where F: FnOnce(&mut Self) -> Result<Json, String>
+    {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("nesting is too deep"));
+        }
+
+        self.depth += 1;
+        let r = parse(self);
+        self.depth -= 1;
+
+        r
+    }
+
+    fn literal(&mut self) -> Result<Json, String> {
+        for &(word, ref val) in &[("null", Json::Null), ("true", Json::Bool(true)), ("false", Json::Bool(false))] {
+            if self.text[self.pos..].starts_with(word) {
+                self.pos += word.len();
+                return Ok(val.clone());
+            }
+        }
+
+        Err(self.error("unexpected symbol"))
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        let mut float = false;
+
+        while let Some(ch) = self.peek() {
+            match ch {
+                '0'..='9' | '-' | '+' => {}
+                '.' | 'e' | 'E' => float = true,
+                _ => break,
+            }
+            self.pos += 1;
+        }
+
+        let text = &self.text[start..self.pos];
+
+        let r = if float {
+            text.parse().map(Json::Float).ok()
+        } else {
+            text.parse().map(Json::Int).ok()
+        };
+
+        r.ok_or_else(|| format!("offset {}: '{}': invalid number", start, text))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut r = String::new();
+
+        loop {
+            match self.next() {
+                Some('"') => return Ok(r),
+                Some('\\') => {
+                    let ch = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    r.push(ch);
+                }
+                Some(ch) => r.push(ch),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let first = self.hex4()?;
+
+        // characters out of the basic plane are written as surrogate pairs
+        let code = if (0xd800..0xdc00).contains(&first) && self.text[self.pos..].starts_with("\\u") {
+            self.pos += 2;
+            let second = self.hex4()?;
+            0x10000 + ((first - 0xd800) << 10) + (second.wrapping_sub(0xdc00) & 0x3ff)
+        } else {
+            first
+        };
+
+        std::char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let hex = self.text.get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        let code = u32::from_str_radix(hex, 16)
+            .map_err(|_| self.error("invalid unicode escape"))?;
+
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+
+        self.skip_ws();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+            self.skip_ws();
+
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(items)),
+                _ => return Err(self.error("',' or ']' expected")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+
+        self.skip_ws();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.skip_ws();
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_ws();
+
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(members)),
+                _ => return Err(self.error("',' or '}' expected")),
+            }
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use compiler::*;
+
+
+/// Kinds of warnings, codes and names are stable and used to suppress them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedVariable,
+    UnusedArgument,
+    UnusedFunction,
+    UnreachableCode,
+    ShadowedVariable,
+}
+
+
+const LINTS: [Lint; 5] = [
+    Lint::UnusedVariable,
+    Lint::UnusedArgument,
+    Lint::UnusedFunction,
+    Lint::UnreachableCode,
+    Lint::ShadowedVariable,
+];
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub lint: Lint,
+    pub description: String,
+    pub loc: Loc,
+    pub labels: Vec<Label>,
+    pub notes: Vec<Note>,
+}
+
+
+/// Checks the `AST` for code that is valid but likely wrong.
+///
+/// A warning is suppressed by `allow`, by a `// calc:allow(<lint>, ...)`
+/// comment on the same or the previous line (see `allow_comments`), or,
+/// for unused names, by `_` at the beginning of the name. Lints are given
+/// by code (`W001`) or name (`unused-variable`).
+#[derive(Default)]
+pub struct Linter {
+    allowed: HashSet<Lint>,
+    allowed_lines: HashMap<i32, HashSet<Lint>>,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Var,
+    Arg,
+    Func,
+    Other,  // loop and catch variables, imports
+}
+
+
+struct Binding<'a> {
+    name: &'a str,
+    loc: &'a Loc,
+    kind: Kind,
+    used: bool,
+}
+
+
+/// Scope of the program, a function call, a loop iteration or a clause of
+/// `try`, other blocks don't make scopes.
+struct LintScope<'a> {
+    vars: Vec<Binding<'a>>,
+    owner: Option<(usize, usize)>,  // binding of the function itself
+}
+
+
+struct Walker<'a> {
+    scopes: Vec<LintScope<'a>>,
+    unresolved: HashSet<&'a str>,   // may be declared later, see `read`
+    warnings: Vec<Warning>,
+}
+
+
+impl Lint {
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Lint::UnusedVariable => "W001",
+            Lint::UnusedArgument => "W002",
+            Lint::UnusedFunction => "W003",
+            Lint::UnreachableCode => "W004",
+            Lint::ShadowedVariable => "W005",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Lint::UnusedVariable => "unused-variable",
+            Lint::UnusedArgument => "unused-argument",
+            Lint::UnusedFunction => "unused-function",
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::ShadowedVariable => "shadowed-variable",
+        }
+    }
+}
+
+
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        LINTS.iter()
+            .find(|lint| lint.code() == text || lint.name() == text)
+            .cloned()
+            .ok_or_else(|| format!("'{}': unknown lint", text))
+    }
+}
+
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}({}): warning[{}]: {}", self.loc.filename, self.loc.line, self.lint.code(), self.description)
+    }
+}
+
+
+impl Linter {
+    pub fn new() -> Self {
+        Linter::default()
+    }
+
+    pub fn allow(mut self, lint: Lint) -> Self {
+        self.allowed.insert(lint);
+        self
+    }
+
+    /// Skip the lints for unused names, for a tree returned by `parse_recover`
+    /// with errors: the statements left out of it may use the names.
+    pub fn allow_unused(self) -> Self {
+        self.allow(Lint::UnusedVariable)
+            .allow(Lint::UnusedArgument)
+            .allow(Lint::UnusedFunction)
+    }
+
+    /// Collect `// calc:allow(...)` comments of the source text. A comment
+    /// after code suppresses warnings on its own line, a comment on a line
+    /// of its own on the next line.
+    pub fn allow_comments(mut self, text: &str) -> Result<Self, String> {
+        const DIRECTIVE: &str = "calc:allow(";
+
+        // errors are reported by the parser, comments after them still count
+        let (_, comments, _) = tokenize_recover(text, "");
+
+        for comment in &comments {
+            let body = comment.text[2..].trim();     // after `//`
+
+            if !body.starts_with(DIRECTIVE) {
+                continue;
+            }
+
+            let list = body[DIRECTIVE.len()..].split(')').next().unwrap_or("");
+            let line = comment.loc.line;
+
+            let line_start = text[..comment.loc.start].rfind('\n').map_or(0, |pos| pos + 1);
+            let trailing = !text[line_start..comment.loc.start].trim().is_empty();
+            let target = if trailing { line } else { line + 1 };
+
+            for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                let lint = name.parse()
+                    .map_err(|err| format!("line {}: {}", line, err))?;
+
+                self.allowed_lines.entry(target).or_default().insert(lint);
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Warnings in the order of the text.
+    pub fn check(&self, ast: &AST) -> Vec<Warning> {
+        let mut walker = Walker {
+            scopes: vec![LintScope { vars: Vec::new(), owner: None }],
+            unresolved: HashSet::new(),
+            warnings: Vec::new(),
+        };
+
+        walker.visit(ast);
+        walker.pop_scope();
+
+        let mut warnings: Vec<Warning> = walker.warnings.into_iter()
+            .filter(|warn| !self.is_allowed(warn))
+            .collect();
+
+        warnings.sort_by_key(|warn| (warn.loc.line, warn.loc.col));
+        warnings
+    }
+
+    fn is_allowed(&self, warn: &Warning) -> bool {
+        self.allowed.contains(&warn.lint)
+            || self.allowed_lines.get(&warn.loc.line).is_some_and(|lints| lints.contains(&warn.lint))
+    }
+}
+
+
+impl<'a> Visitor<'a> for Walker<'a> {
+    fn visit_block(&mut self, _loc: &'a Loc, body: &'a [AST], _end: &'a Loc) {
+        let mut reported = false;
+
+        for (idx, st) in body.iter().enumerate() {
+            let after_exit = idx > 0 && matches!(body[idx - 1], AST::Return{..} | AST::Throw{..});
+
+            if after_exit && !reported {
+                let exit = body[idx - 1].loc();
+                self.warnings.push(Warning {
+                    lint: Lint::UnreachableCode,
+                    description: String::from("unreachable statement"),
+                    loc: st.loc().clone(),
+                    labels: vec![Label { loc: exit.clone(), text: String::from("any code after this is unreachable") }],
+                    notes: Vec::new(),
+                });
+                reported = true;
+            }
+
+            self.visit(st);
+        }
+    }
+
+    fn visit_decl_var(&mut self, loc: &'a Loc, name: &'a str, init: &'a AST) {
+        // `let x = x;` reads the outer variable
+        self.visit(init);
+        self.check_shadowing(name, loc);
+        self.declare(name, loc, Kind::Var);
+    }
+
+    fn visit_func(&mut self, loc: &'a Loc, decl: &'a FuncDecl) {
+        let owner = self.declare(&decl.name, loc, Kind::Func);
+        self.scopes.push(LintScope { vars: Vec::new(), owner: Some(owner) });
+
+        for (arg, arg_loc) in decl.args.iter().zip(&decl.arg_locs) {
+            self.declare(arg, arg_loc, Kind::Arg);
+        }
+
+        self.visit(&decl.body);
+        self.pop_scope();
+    }
+
+    fn visit_for(&mut self, loc: &'a Loc, var: &'a str, iter: &'a AST, body: &'a AST) {
+        self.visit(iter);
+        self.visit_nested(body, Some((var, loc)));
+    }
+
+    fn visit_try(&mut self, loc: &'a Loc, body: &'a AST, catch: Option<(&'a str, &'a AST)>, finally: Option<&'a AST>) {
+        self.visit_nested(body, None);
+
+        if let Some((var, handler)) = catch {
+            self.visit_nested(handler, Some((var, loc)));
+        }
+
+        if let Some(finally) = finally {
+            self.visit_nested(finally, None);
+        }
+    }
+
+    fn visit_import(&mut self, loc: &'a Loc, _path: &'a str, alias: &'a str) {
+        self.declare(alias, loc, Kind::Other);
+    }
+
+    fn visit_from_import(&mut self, loc: &'a Loc, _path: &'a str, names: &'a [String]) {
+        for name in names {
+            self.declare(name, loc, Kind::Other);
+        }
+    }
+
+    fn visit_var(&mut self, _loc: &'a Loc, name: &'a str) {
+        self.read(name);
+    }
+
+    fn visit_call(&mut self, _loc: &'a Loc, name: &'a str, args: &'a [AST]) {
+        self.read(name);
+        args.iter().for_each(|arg| self.visit(arg));
+    }
+}
+
+
+impl<'a> Walker<'a> {
+    /// Visit the loop body or a clause of `try` in its own scope, with the
+    /// loop or catch variable.
+    fn visit_nested(&mut self, body: &'a AST, var: Option<(&'a str, &'a Loc)>) {
+        self.scopes.push(LintScope { vars: Vec::new(), owner: None });
+
+        if let Some((name, loc)) = var {
+            self.declare(name, loc, Kind::Other);
+        }
+
+        self.visit(body);
+        self.pop_scope();
+    }
+
+    fn declare(&mut self, name: &'a str, loc: &'a Loc, kind: Kind) -> (usize, usize) {
+        let scope_idx = self.scopes.len() - 1;
+        let vars = &mut self.scopes[scope_idx].vars;
+
+        vars.push(Binding { name, loc, kind, used: false });
+        (scope_idx, vars.len() - 1)
+    }
+
+    fn lookup(&self, name: &str) -> Option<(usize, usize)> {
+        self.scopes.iter().enumerate().rev()
+            .filter_map(|(scope_idx, scope)| {
+                scope.vars.iter().rposition(|var| var.name == name)
+                    .map(|var_idx| (scope_idx, var_idx))
+            })
+            .next()
+    }
+
+    fn read(&mut self, name: &'a str) {
+        let pos = match self.lookup(name) {
+            Some(pos) => pos,
+            None => {
+                // function bodies may refer to names declared after them
+                self.unresolved.insert(name);
+                return;
+            }
+        };
+
+        // recursive calls don't count as uses
+        if self.scopes.iter().any(|scope| scope.owner == Some(pos)) {
+            return;
+        }
+
+        self.scopes[pos.0].vars[pos.1].used = true;
+    }
+
+    fn check_shadowing(&mut self, name: &str, loc: &Loc) {
+        let outer = self.scopes.len() - 1;
+
+        if let Some((scope_idx, var_idx)) = self.lookup(name) {
+            if scope_idx < outer {
+                let outer = self.scopes[scope_idx].vars[var_idx].loc;
+
+                self.warnings.push(Warning {
+                    lint: Lint::ShadowedVariable,
+                    description: format!("'{}': variable shadows an outer one", name),
+                    loc: loc.clone(),
+                    labels: vec![Label { loc: outer.clone(), text: String::from("outer variable declared here") }],
+                    notes: vec![Note::Info(String::from("reads before this declaration refer to the outer variable"))],
+                });
+            }
+        }
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().expect("scope to pop");
+
+        for var in scope.vars {
+            if var.used || var.name.starts_with('_') || self.unresolved.contains(var.name) {
+                continue;
+            }
+
+            let (lint, what) = match var.kind {
+                Kind::Var => (Lint::UnusedVariable, "variable"),
+                Kind::Arg => (Lint::UnusedArgument, "argument"),
+                Kind::Func => (Lint::UnusedFunction, "function"),
+                Kind::Other => continue,
+            };
+
+            let verb = if var.kind == Kind::Func { "called" } else { "read" };
+
+            self.warnings.push(Warning {
+                lint,
+                description: format!("'{}': {} is never {}", var.name, what, verb),
+                loc: var.loc.clone(),
+                labels: Vec::new(),
+                notes: vec![Note::Help(format!("if this is intentional, prefix it with an underscore: '_{}'", var.name))],
+            });
+        }
+    }
+}
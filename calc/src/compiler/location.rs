@@ -1,21 +1,42 @@
-use std::rc::Rc;
 use super::error::*;
+use super::shared::Shared;
 
 
+/// Position in the source text. `col` counts chars (not bytes) from 1,
+/// `start`..`end` is the byte range of the token. Locations made up by the
+/// host have no column (zero) and an empty range.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Loc {
-    pub filename: Rc<String>,
+    pub filename: Shared<String>,
     pub line: i32,
+    pub col: i32,
+    pub start: usize,
+    pub end: usize,
 }
 
 
 impl Loc {
     pub fn new(filename: &str, line: i32) -> Self {
         Loc {
-            filename: Rc::new(String::from(filename)),
+            filename: Shared::new(String::from(filename)),
             line,
+            col: 0,
+            start: 0,
+            end: 0,
         }
     }
+
+    /// Same location with the column and byte range set.
+    pub fn at(mut self, col: i32, start: usize, end: usize) -> Self {
+        self.col = col;
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    pub fn has_span(&self) -> bool {
+        self.col > 0
+    }
 }
 
 
@@ -24,8 +45,11 @@ pub trait Location {
 
     fn error(&self, description: String) -> Error {
         Error {
-            description: description,
+            description,
             loc: self.loc().clone(),
+            kind: ErrorKind::General,
+            labels: Vec::new(),
+            notes: Vec::new(),
         }
     }
 
@@ -33,6 +57,9 @@ pub trait Location {
         Error {
             description: String::from(description),
             loc: self.loc().clone(),
+            kind: ErrorKind::General,
+            labels: Vec::new(),
+            notes: Vec::new(),
         }
     }
 }
@@ -45,8 +72,8 @@ impl Location for Loc {
 }
 
 
-impl<T> Into<Result<T, Error>> for Error {
-    fn into(self) -> Result<T, Error> {
-        Err(self)
+impl<T> From<Error> for Result<T, Error> {
+    fn from(err: Error) -> Self {
+        Err(err)
     }
 }
@@ -1,10 +1,22 @@
 
+mod shared;
 mod location;
 mod error;
 mod token;
 mod ast;
 mod value;
 mod scope;
+mod convert;
+mod host;
+mod module;
+mod engine;
+mod diagnostic;
+mod lint;
+mod formatter;
+mod visitor;
+mod json;
+mod ast_json;
+pub mod stdlib;
 
 mod tokenizer;
 mod parser;
@@ -14,19 +26,35 @@ mod execute;
 mod tests;
 
 
+pub use self::shared::{Shared, Lock, Threadsafe};
 pub use self::location::Loc;
 pub use self::location::Location;
-pub use self::token::Token;
+pub use self::token::{Token, Comment};
 pub use self::ast::AST;
 pub use self::ast::FuncDecl;
+pub use self::ast::MAX_AST_DEPTH;
+pub use self::visitor::{Visitor, Folder, walk, fold_children};
 
 pub use self::scope::Scope;
-pub use self::value::Val;
+pub use self::value::{Val, IntoNativeResult, Arity};
+pub use self::execute::native::CallContext;
+pub use self::host::{HostObject, HostRef};
+pub use self::module::{Module, ModuleLoader, FileLoader, MemoryLoader};
+pub use self::convert::{FromVal, FromArgs, IntoVal, IntoValResult, TypedFunc};
 
 pub use self::parser::context::ParseContext;
 pub use self::execute::context::ExecContext;
-
-pub use self::error::Error;
+pub use self::execute::runtime::InterruptHandle;
+pub use self::execute::generator::Generator;
+
+pub use self::error::{Error, ErrorKind, Label, Note};
+pub use self::diagnostic::Renderer;
+pub use self::lint::{Lint, Linter, Warning};
+pub use self::formatter::format;
+pub use self::tokenizer::{tokenize_with_comments, tokenize_recover};
+pub use self::json::Json;
+pub use self::ast_json::{ast_to_json, ast_from_json, AST_JSON_VERSION};
+pub use self::engine::Engine;
 
 pub type TokenizeResult<'a> = Result<Vec<Token<'a>>, Error>;
 pub type ParseResult = Result<AST, Error>;
@@ -47,6 +75,7 @@ pub fn parse_expr(text: &str, filename: &str) -> ParseResult {
 	// make sure there is no data left
 	ctx.match_eof()?;
 
+	parser::block::check_depth(&expr, 0)?;
 	Ok(expr)
 }
 
@@ -61,9 +90,83 @@ pub fn parse(text: &str, filename: &str) -> ParseResult {
 	// make sure there is no data left
 	ctx.match_eof()?;
 
+	parser::block::check_depth(&expr, 0)?;
 	Ok(expr)
 }
 
 
+/// Parse the whole text even if it has syntax errors: statements with errors
+/// are skipped and left out of the returned block (as are unknown symbols),
+/// all errors are returned in the order of the text.
+pub fn parse_recover(text: &str, filename: &str) -> (AST, Vec<Error>) {
+	let (tokens, _, errors) = tokenizer::tokenize_recover(text, filename);
+
+	let mut ctx = ParseContext::new(tokens);
+	ctx.set_recovering(true);
+
+	let loc = ctx.loc().clone();
+	let mut body = Vec::new();
+	let mut too_deep = Vec::new();
+
+	loop {
+		if let Ok(AST::Block{ body: part, .. }) = parser::block::parse_block(&mut ctx) {
+			// statements are children of the returned block
+			for st in part {
+				match parser::block::check_depth(&st, 1) {
+					Ok(()) => body.push(st),
+					Err(err) => too_deep.push(err),
+				}
+			}
+		}
+
+		if ctx.match_eof().is_ok() {
+			break;
+		}
+
+		// only a stray `}` stops the block before the end
+		let err = ctx.error(format!("'{}': unexpected token", ctx.token()));
+		ctx.recover(err, ctx.offset()).ok();
+		ctx.match_any();
+	}
+
+	let end = ctx.loc().clone();
+
+	// tokens in place of bad ones (e.g. too large integers) are reported already
+	let parse_errors: Vec<Error> = ctx.take_errors().into_iter()
+		.filter(|err| !errors.iter().any(|bad| bad.loc.start == err.loc.start))
+		.collect();
+
+	// keep the errors in the order of the text
+	let mut errors: Vec<Error> = errors.into_iter().chain(parse_errors).chain(too_deep).collect();
+	errors.sort_by_key(|err| err.loc.start);
+
+	(AST::Block{ loc, body, end }, errors)
+}
+
+
+/// Whether the text ends inside an unclosed `{`, `(` or `[`, so an
+/// interactive session should read more lines before parsing it.
+pub fn is_incomplete(text: &str) -> bool {
+	let tokens = match tokenizer::tokenize(text, "") {
+		Ok(tokens) => tokens,
+		Err(_) => return false,
+	};
+
+	let mut depth = 0;
+
+	for token in &tokens {
+		if let Token::Symbol(_, ch) = *token {
+			match ch {
+				'{' | '(' | '[' => depth += 1,
+				'}' | ')' | ']' => depth -= 1,
+				_ => {}
+			}
+		}
+	}
+
+	depth > 0
+}
+
+
 pub use self::execute::execute;
 
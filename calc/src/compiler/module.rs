@@ -0,0 +1,92 @@
+//! Script modules, loaded by `import` statements through a host provided
+//! `ModuleLoader`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use compiler::*;
+
+
+/// Source of module texts: files, memory, a bundle embedded into the host
+/// binary, etc.
+pub trait ModuleLoader: Threadsafe {
+    /// Canonical name of the module imported as `path` by the script
+    /// `base`. It identifies the module in the cache and error locations.
+    fn resolve(&self, path: &str, base: &str) -> Result<String, String> {
+        let _ = base;
+        Ok(String::from(path))
+    }
+
+    fn load(&self, name: &str) -> Result<String, String>;
+}
+
+
+/// Executed module, its variables are available as `name.var`.
+#[derive(Debug, PartialEq)]
+pub struct Module {
+    pub name: String,
+    pub scope: Shared<Scope>,
+}
+
+
+/// Loads modules from disk, paths are relative to the importing script (or
+/// to the root for scripts that don't come from files).
+pub struct FileLoader {
+    root: PathBuf,
+}
+
+
+impl FileLoader {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        FileLoader {
+            root: root.into(),
+        }
+    }
+}
+
+
+impl ModuleLoader for FileLoader {
+    fn resolve(&self, path: &str, base: &str) -> Result<String, String> {
+        let dir = match Path::new(base).parent() {
+            Some(dir) if !base.starts_with('<') => dir,
+            _ => &self.root,
+        };
+
+        // the same file could be imported by different relative paths
+        let path = dir.join(path).canonicalize()
+            .map_err(|err| format!("can't find module: {}", err))?;
+
+        Ok(path.display().to_string())
+    }
+
+    fn load(&self, name: &str) -> Result<String, String> {
+        fs::read_to_string(name)
+            .map_err(|err| format!("can't read module: {}", err))
+    }
+}
+
+
+/// Modules kept in memory, e.g. embedded into the host binary.
+#[derive(Debug, Default)]
+pub struct MemoryLoader {
+    modules: HashMap<String, String>,
+}
+
+
+impl MemoryLoader {
+    pub fn new() -> Self {
+        MemoryLoader::default()
+    }
+
+    pub fn add(&mut self, name: &str, text: &str) {
+        self.modules.insert(String::from(name), String::from(text));
+    }
+}
+
+
+impl ModuleLoader for MemoryLoader {
+    fn load(&self, name: &str) -> Result<String, String> {
+        self.modules.get(name).cloned()
+            .ok_or_else(|| String::from("module not found"))
+    }
+}
@@ -1,39 +1,70 @@
-use std::rc::Rc;
 use compiler::*;
-use super::expr::parse_expr;
+use super::expr::{parse_expr, literal_to_string};
 use self::AST::*;
 
 
 pub fn parse_block(ctx: &mut ParseContext) -> ParseResult {
+    let depth = ctx.depth();
+    let r = ctx.enter().and_then(|_| parse_statements(ctx));
+    ctx.set_depth(depth);
+    r
+}
+
+
+fn parse_statements(ctx: &mut ParseContext) -> ParseResult {
     let loc = ctx.loc().clone();    // store block beginning
     let mut body: Vec<AST> = Vec::new();
 
-    while let Token::Ident(_, name) = *ctx.token() {
-        let st = match name {
-            "let"    => parse_st_let(ctx)?,
-            "return" => parse_st_return(ctx)?,
-            "fn"     => parse_st_fn(ctx)?,
-            _        => {
-                if ctx.get_next().is_symbol('=') {
-                    parse_st_assign(ctx)?
-                } else {
-                    let r = parse_expr(ctx)?;
-                    ctx.match_symbol(';')?;
-                    r
-                }
-            }
-        };
+    loop {
+        let start = ctx.offset();
 
-        body.push(st);
+        match *ctx.token() {
+            Token::Ident(_, name) => match parse_statement(ctx, name) {
+                Ok(st) => body.push(st),
+                Err(err) => ctx.recover(err, start)?,
+            },
+
+            // the end of the block is checked by the caller
+            Token::Symbol(_, '}') | Token::Eof(_) => break,
+            _ if !ctx.is_recovering() => break,
+
+            _ => {
+                let err = ctx.error(format!("'{}': unexpected token", ctx.token()));
+                ctx.recover(err, start)?;
+            }
+        }
     }
 
     Ok(Block{
         loc: loc.clone(),
         body,
+        end: ctx.loc().clone(),
     })
 }
 
 
+fn parse_statement(ctx: &mut ParseContext, keyword: &str) -> ParseResult {
+    match keyword {
+        "let"    => parse_st_let(ctx),
+        "return" => parse_st_return(ctx),
+        "fn"     => parse_st_fn(ctx),
+        "yield"  => parse_st_yield(ctx),
+        "for"    => parse_st_for(ctx),
+        "throw"  => parse_st_throw(ctx),
+        "try"    => parse_st_try(ctx),
+        "import" => parse_st_import(ctx),
+        "from"   => parse_st_from(ctx),
+        _        => {
+            if ctx.get_next().is_symbol('=') {
+                parse_st_assign(ctx)
+            } else {
+                parse_st_expr(ctx)
+            }
+        }
+    }
+}
+
+
 fn parse_st_let(ctx: &mut ParseContext) -> ParseResult {
     // let <ident> = <expr> ;
     ctx.match_keyword("let")?;
@@ -52,6 +83,84 @@ fn parse_st_let(ctx: &mut ParseContext) -> ParseResult {
 }
 
 
+fn parse_st_expr(ctx: &mut ParseContext) -> ParseResult {
+    // <expr> ;
+    // <expr> . <ident> = <expr> ;
+    // <expr> [ <expr> ] = <expr> ;
+    let expr = parse_expr(ctx)?;
+
+    let r = match expr {
+        GetProp{ loc, obj, name } if ctx.token().is_symbol('=') => {
+            ctx.match_symbol('=')?;
+            let val = parse_expr(ctx)?;
+
+            SetProp{ loc, obj, name, val: Box::new(val) }
+        }
+        Index{ loc, obj, index } if ctx.token().is_symbol('=') => {
+            ctx.match_symbol('=')?;
+            let val = parse_expr(ctx)?;
+
+            SetIndex{ loc, obj, index, val: Box::new(val) }
+        }
+        expr => expr,
+    };
+
+    ctx.match_symbol(';')?;
+    Ok(r)
+}
+
+
+fn parse_st_import(ctx: &mut ParseContext) -> ParseResult {
+    // import <str> as <ident> ;
+    let loc = ctx.match_keyword("import")?;
+    let path = parse_module_path(ctx)?;
+
+    ctx.match_keyword("as")?;
+    let (_, alias) = ctx.match_ident()?;
+
+    ctx.match_symbol(';')?;
+
+    Ok(Import{
+        loc, path,
+        alias: String::from(alias),
+    })
+}
+
+
+fn parse_st_from(ctx: &mut ParseContext) -> ParseResult {
+    // from <str> import <ident> [, <ident>]* ;
+    let loc = ctx.match_keyword("from")?;
+    let path = parse_module_path(ctx)?;
+
+    ctx.match_keyword("import")?;
+    let mut names = Vec::new();
+
+    loop {
+        let (_, name) = ctx.match_ident()?;
+        names.push(String::from(name));
+
+        if !ctx.token().is_symbol(',') {
+            break;
+        }
+        ctx.match_symbol(',')?;
+    }
+
+    ctx.match_symbol(';')?;
+
+    Ok(FromImport{ loc, path, names })
+}
+
+
+fn parse_module_path(ctx: &mut ParseContext) -> Result<String, Error> {
+    if let Token::Str(_, val) = *ctx.token() {
+        let loc = ctx.match_any();
+        return literal_to_string(&loc, val);
+    }
+
+    ctx.error(format!("'{}': module path expected", ctx.token())).into()
+}
+
+
 fn parse_st_return(ctx: &mut ParseContext) -> ParseResult {
     // return <expr> ;
     let loc = ctx.match_keyword("return")?;
@@ -85,12 +194,14 @@ fn parse_st_fn(ctx: &mut ParseContext) -> ParseResult {
     ctx.match_keyword("fn")?;
     let (loc, name) = ctx.match_ident()?;
     let mut args: Vec<String> = Vec::new();
+    let mut arg_locs: Vec<Loc> = Vec::new();
 
     ctx.match_symbol('(')?;
     if !ctx.token().is_symbol(')') {
         loop {
-            let (_, arg_name) = ctx.match_ident()?;
+            let (arg_loc, arg_name) = ctx.match_ident()?;
             args.push(String::from(arg_name));
+            arg_locs.push(arg_loc);
 
             if !ctx.token().is_symbol(',') {
                 break;
@@ -104,12 +215,153 @@ fn parse_st_fn(ctx: &mut ParseContext) -> ParseResult {
     let body = parse_block(ctx)?;
     ctx.match_symbol('}')?;
 
+    let generator = has_yield(&body);
+
     Ok(Func{
         loc,
-        decl: Rc::new(FuncDecl {
+        decl: Shared::new(FuncDecl {
             name: String::from(name),
-            args, body,
+            args, arg_locs, body, generator,
         }),
     })
 }
 
+
+fn has_yield(body: &AST) -> bool {
+    struct Finder(bool);
+
+    impl<'a> Visitor<'a> for Finder {
+        fn visit_yield(&mut self, _loc: &'a Loc, _val: &'a AST) {
+            self.0 = true;
+        }
+
+        // nested functions are not taken into account
+        fn visit_func(&mut self, _loc: &'a Loc, _decl: &'a FuncDecl) {}
+    }
+
+    let mut finder = Finder(false);
+    finder.visit(body);
+    finder.0
+}
+
+
+fn parse_st_yield(ctx: &mut ParseContext) -> ParseResult {
+    // yield <expr> ;
+    let loc = ctx.match_keyword("yield")?;
+    let expr = parse_expr(ctx)?;
+
+    ctx.match_symbol(';')?;
+
+    Ok(Yield{ loc, val: Box::new(expr) })
+}
+
+
+fn parse_st_for(ctx: &mut ParseContext) -> ParseResult {
+    // for <ident> in <expr> { <block> }
+    let loc = ctx.match_keyword("for")?;
+    let (_, var) = ctx.match_ident()?;
+
+    ctx.match_keyword("in")?;
+    let iter = parse_expr(ctx)?;
+
+    ctx.match_symbol('{')?;
+    let body = parse_block(ctx)?;
+    ctx.match_symbol('}')?;
+
+    Ok(For{
+        loc,
+        var: String::from(var),
+        iter: Box::new(iter),
+        body: Box::new(body),
+    })
+}
+
+
+
+fn parse_st_throw(ctx: &mut ParseContext) -> ParseResult {
+    // throw <expr> ;
+    let loc = ctx.match_keyword("throw")?;
+    let expr = parse_expr(ctx)?;
+
+    ctx.match_symbol(';')?;
+
+    Ok(Throw{ loc, val: Box::new(expr) })
+}
+
+
+fn parse_st_try(ctx: &mut ParseContext) -> ParseResult {
+    // try { <block> } catch ( <ident> ) { <block> } finally { <block> }
+    let loc = ctx.match_keyword("try")?;
+
+    ctx.match_symbol('{')?;
+    let body = parse_block(ctx)?;
+    ctx.match_symbol('}')?;
+
+    let mut catch = None;
+    if ctx.token().is_keyword("catch") {
+        ctx.match_keyword("catch")?;
+
+        ctx.match_symbol('(')?;
+        let (_, var) = ctx.match_ident()?;
+        ctx.match_symbol(')')?;
+
+        ctx.match_symbol('{')?;
+        let handler = parse_block(ctx)?;
+        ctx.match_symbol('}')?;
+
+        catch = Some((String::from(var), Box::new(handler)));
+    }
+
+    let mut finally = None;
+    if catch.is_none() || ctx.token().is_keyword("finally") {
+        // at least one of the clauses is required
+        ctx.match_keyword("finally")?;
+
+        ctx.match_symbol('{')?;
+        finally = Some(Box::new(parse_block(ctx)?));
+        ctx.match_symbol('}')?;
+    }
+
+    Ok(Try{
+        loc,
+        body: Box::new(body),
+        catch, finally,
+    })
+}
+
+
+/// Fails at the first node nested deeper than `MAX_AST_DEPTH`, `depth` is
+/// the depth of the given node. The parser only bounds its own recursion,
+/// e.g. an operand parsed before a long chain of operators ends up deeper
+/// than it was counted.
+pub fn check_depth(ast: &AST, depth: usize) -> Result<(), Error> {
+    struct Depth {
+        depth: usize,
+        too_deep: Option<Error>,
+    }
+
+    impl<'a> Visitor<'a> for Depth {
+        fn visit(&mut self, ast: &'a AST) {
+            if self.too_deep.is_some() {
+                return;
+            }
+
+            if self.depth == MAX_AST_DEPTH {
+                self.too_deep = Some(ast.error_str("nesting is too deep"));
+                return;
+            }
+
+            self.depth += 1;
+            walk(self, ast);
+            self.depth -= 1;
+        }
+    }
+
+    let mut check = Depth { depth, too_deep: None };
+    check.visit(ast);
+
+    match check.too_deep {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
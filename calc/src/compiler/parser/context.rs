@@ -4,6 +4,9 @@ use compiler::*;
 pub struct ParseContext<'a> {
 	tokens: Vec<Token<'a>>,
 	offset: usize,	// cur token index
+	depth: usize,	// nested blocks, expressions and operators being parsed
+	recovering: bool,
+	errors: Vec<Error>,	// errors skipped in the recovery mode
 }
 
 
@@ -22,9 +25,102 @@ impl<'a> ParseContext<'a> {
 		ParseContext {
 			tokens,
 			offset: 0,
+			depth: 0,
+			recovering: false,
+			errors: Vec::new(),
 		}
 	}
 
+	/// In the recovery mode statements with syntax errors are skipped (see
+	/// `recover`), so that all errors of the text are reported at once.
+	pub fn set_recovering(&mut self, recovering: bool) {
+		self.recovering = recovering;
+	}
+
+	pub fn is_recovering(&self) -> bool {
+		self.recovering
+	}
+
+	pub fn take_errors(&mut self) -> Vec<Error> {
+		std::mem::take(&mut self.errors)
+	}
+
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	pub fn depth(&self) -> usize {
+		self.depth
+	}
+
+	/// Restore the depth saved before a nested part, which may have failed
+	/// with its levels still counted.
+	pub fn set_depth(&mut self, depth: usize) {
+		self.depth = depth;
+	}
+
+	/// Count one more level of nesting, up to `MAX_AST_DEPTH`, so that
+	/// the recursion of the parser can't overflow the stack.
+	pub fn enter(&mut self) -> MatchResult<'a> {
+		if self.depth == MAX_AST_DEPTH {
+			return self.error_str("nesting is too deep").into();
+		}
+
+		self.depth += 1;
+		Ok(())
+	}
+
+	/// Handle an error of the statement started at `start`: outside of the
+	/// recovery mode it's returned back, otherwise it's stored and tokens
+	/// are skipped up to the end of the statement, that is `;` or `}`
+	/// closing the braces opened by the statement, or `}` of the enclosing
+	/// block (left for the block).
+	pub fn recover(&mut self, err: Error, start: usize) -> MatchResult<'a> {
+		if !self.recovering {
+			return Err(err);
+		}
+
+		self.errors.push(err);
+
+		let mut depth = self.tokens[start..self.offset].iter()
+			.fold(0, |depth, token| match *token {
+				Token::Symbol(_, '{') => depth + 1,
+				Token::Symbol(_, '}') => depth - 1,
+				_ => depth,
+			});
+
+		loop {
+			match *self.token() {
+				Token::Eof(_) => break,
+				Token::Symbol(_, ';') if depth <= 0 => {
+					self.match_any();
+					break;
+				}
+				Token::Symbol(_, '}') if depth <= 0 => break,
+				Token::Symbol(_, '}') => {
+					self.match_any();
+					depth -= 1;
+
+					if depth == 0 {
+						if self.token().is_symbol(';') {
+							self.match_any();
+						}
+						break;
+					}
+				}
+				Token::Symbol(_, '{') => {
+					self.match_any();
+					depth += 1;
+				}
+				_ => {
+					self.match_any();
+				}
+			}
+		}
+
+		Ok(())
+	}
+
 	pub fn token(&self) -> &Token<'a> {
 		&self.tokens[self.offset]
 	}
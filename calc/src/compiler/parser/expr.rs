@@ -3,7 +3,11 @@ use self::AST::*;
 
 
 pub fn parse_expr<'a>(ctx: &mut ParseContext<'a>) -> ParseResult {
-	parse_expr_add(ctx)
+	// operators of the chains below count as nesting too
+	let depth = ctx.depth();
+	let r = ctx.enter().and_then(|_| parse_expr_add(ctx));
+	ctx.set_depth(depth);
+	r
 }
 
 
@@ -15,6 +19,7 @@ fn parse_expr_add<'a>(ctx: &mut ParseContext<'a>) -> ParseResult {
 			break;
 		}
 
+		ctx.enter()?;
         let loc = ctx.match_any();
 		let right = parse_expr_mul(ctx)?;
 
@@ -26,7 +31,7 @@ fn parse_expr_add<'a>(ctx: &mut ParseContext<'a>) -> ParseResult {
 		};
 	}
 
-	return Ok(left);
+	Ok(left)
 }
 
 
@@ -38,6 +43,7 @@ fn parse_expr_mul<'a>(ctx: &mut ParseContext<'a>) -> ParseResult {
 			break;
 		}
 
+		ctx.enter()?;
         let loc = ctx.match_any();
 		let right = parse_val(ctx)?;
 
@@ -48,11 +54,62 @@ fn parse_expr_mul<'a>(ctx: &mut ParseContext<'a>) -> ParseResult {
 		};
 	}
 
-	return Ok(left);
+	Ok(left)
 }
 
 
 fn parse_val<'a>(ctx: &mut ParseContext<'a>) -> ParseResult {
+    let mut val = parse_primary(ctx)?;
+
+    // property access, method calls and indexing:
+    // <val> . <ident> [ ( <args> ) ]
+    // <val> [ <expr> ]
+    loop {
+        if ctx.token().is_symbol('[') || ctx.token().is_symbol('.') {
+            ctx.enter()?;
+        }
+
+        if ctx.token().is_symbol('[') {
+            let loc = ctx.match_any();
+            let index = parse_expr(ctx)?;
+            ctx.match_symbol(']')?;
+
+            val = Index{
+                loc,
+                obj: Box::new(val),
+                index: Box::new(index),
+            };
+            continue;
+        }
+
+        if !ctx.token().is_symbol('.') {
+            break;
+        }
+
+        ctx.match_any();
+        let (loc, name) = ctx.match_ident()?;
+
+        val = if ctx.token().is_symbol('(') {
+            MethodCall{
+                loc,
+                obj: Box::new(val),
+                name: String::from(name),
+                args: parse_args(ctx)?,
+            }
+        } else {
+            GetProp{
+                loc,
+                obj: Box::new(val),
+                name: String::from(name),
+            }
+        };
+    }
+
+    Ok(val)
+}
+
+
+fn parse_primary<'a>(ctx: &mut ParseContext<'a>) -> ParseResult {
 	match *ctx.token() {
 		Token::Ident(_, name) => {
             if ctx.get_next().is_symbol('(') {
@@ -73,15 +130,22 @@ fn parse_val<'a>(ctx: &mut ParseContext<'a>) -> ParseResult {
 			Ok(Num{loc, val})
 		}
 
+		Token::Float(_, val) => {
+            let loc = ctx.match_any();
+
+			Ok(Float{loc, val})
+		}
+
         Token::Str(_, val) => {
             let loc = ctx.match_any();
+            let val = literal_to_string(&loc, val)?;
 
-			Ok(Str{
-                loc,
-                val: literal_to_string(val),
-            })
+			Ok(Str{loc, val})
         }
 
+		Token::Symbol(_, '[') => parse_list(ctx),
+		Token::Symbol(_, '{') => parse_map(ctx),
+
 		Token::Symbol(_, '(') => {
 			ctx.match_any();
 			let expr = parse_expr(ctx)?;
@@ -94,40 +158,95 @@ fn parse_val<'a>(ctx: &mut ParseContext<'a>) -> ParseResult {
 }
 
 
-fn literal_to_string(literal: &str) -> String {
-    debug_assert!(literal.len() >= 2);
+/// Value of the string literal token, fails on unknown escape sequences and
+/// literals left unclosed at the end of the text.
+pub fn literal_to_string(loc: &Loc, literal: &str) -> Result<String, Error> {
     let mut r = String::with_capacity(literal.len());
-    let mut escape = false;
-
-    for ch in literal[1..literal.len()-1].chars() {
-        if escape {
-            let ch = match ch {
-                '\\' => '\\',
-                '\"' => '\"',
-                'n'  => '\n',
-                't'  => '\t',
-                _    => panic!("unexpected escape sequence: '\\{}'", ch),
-            };
-
-            r.push(ch);
-            escape = false;
-            continue;
-        }
-
-        if ch == '\\' {
-            escape = true;
-            continue;
-        }
+    let mut chars = literal.chars().skip(1);    // opening quote
+
+    loop {
+        let ch = match chars.next() {
+            Some('"') => return Ok(r),
+            Some('\\') => match chars.next() {
+                Some('\\') => '\\',
+                Some('"')  => '"',
+                Some('n')  => '\n',
+                Some('t')  => '\t',
+                Some(ch)   => return loc.error(format!("'\\{}': unknown escape sequence", ch)).into(),
+                None       => break,
+            },
+            Some(ch) => ch,
+            None => break,
+        };
 
         r.push(ch);
     }
 
-    return r;
+    loc.error_str("unterminated string literal").into()
 }
 
 
 fn parse_func_call<'a>(ctx: &mut ParseContext<'a>) -> ParseResult {
     let (loc, name) = ctx.match_ident()?;
+    let args = parse_args(ctx)?;
+
+    Ok(Call{
+       loc,
+       name: String::from(name),
+       args,
+    })
+}
+
+
+fn parse_list<'a>(ctx: &mut ParseContext<'a>) -> ParseResult {
+    // [ <expr> [, <expr>]* [,] ]
+    let loc = ctx.match_any();
+    let mut items = Vec::new();
+
+    while !ctx.token().is_symbol(']') {
+        items.push(parse_expr(ctx)?);
+
+        if !ctx.token().is_symbol(',') {
+            break;
+        }
+        ctx.match_symbol(',')?;
+    }
+
+    ctx.match_symbol(']')?;
+
+    Ok(List{ loc, items })
+}
+
+
+fn parse_map<'a>(ctx: &mut ParseContext<'a>) -> ParseResult {
+    // { <key> : <expr> [, <key> : <expr>]* [,] }, key is a string or identifier
+    let loc = ctx.match_any();
+    let mut items = Vec::new();
+
+    while !ctx.token().is_symbol('}') {
+        let key = match *ctx.token() {
+            Token::Str(ref loc, val) => literal_to_string(loc, val)?,
+            Token::Ident(_, name) => String::from(name),
+            _ => return ctx.error(format!("'{}': map key expected", ctx.token())).into(),
+        };
+        ctx.match_any();
+
+        ctx.match_symbol(':')?;
+        items.push((key, parse_expr(ctx)?));
+
+        if !ctx.token().is_symbol(',') {
+            break;
+        }
+        ctx.match_symbol(',')?;
+    }
+
+    ctx.match_symbol('}')?;
+
+    Ok(Map{ loc, items })
+}
+
+
+fn parse_args<'a>(ctx: &mut ParseContext<'a>) -> Result<Vec<AST>, Error> {
     let mut args: Vec<AST> = Vec::new();
 
     ctx.match_symbol('(')?;
@@ -146,10 +265,6 @@ fn parse_func_call<'a>(ctx: &mut ParseContext<'a>) -> ParseResult {
 
     ctx.match_symbol(')')?;
 
-    Ok(Call{
-       loc,
-       name: String::from(name),
-       args,
-    })
+    Ok(args)
 }
 
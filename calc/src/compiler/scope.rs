@@ -1,6 +1,4 @@
 use std::collections::HashMap;
-use std::cell::RefCell;
-use std::rc::Rc;
 
 use compiler::*;
 
@@ -9,23 +7,38 @@ pub type ValMap = HashMap<String, Val>;
 
 #[derive(Debug, PartialEq)]
 pub struct Scope {
-    pub vals: RefCell<ValMap>,
-    pub parent: Option<Rc<Scope>>,
+    pub vals: Lock<ValMap>,
+    pub decls: Lock<HashMap<String, Loc>>,    // where the script declared its variables
+    pub parent: Option<Shared<Scope>>,
+    pub is_block: bool,     // loop or `try` body, assignments reach the parent
 }
 
 
 impl Scope {
-    pub fn new() -> Rc<Self> {
-        Rc::new(Scope {
-            vals: RefCell::new(HashMap::new()),
+    pub fn new() -> Shared<Self> {
+        Shared::new(Scope {
+            vals: Lock::new(HashMap::new()),
+            decls: Lock::new(HashMap::new()),
             parent: None,
+            is_block: false,
         })
     }
 
-    pub fn new_nested(other: &Rc<Self>) -> Rc<Self> {
-        Rc::new(Scope {
-            vals: RefCell::new(HashMap::new()),
-            parent: Some(Rc::clone(other)),
+    pub fn new_nested(other: &Shared<Self>) -> Shared<Self> {
+        Shared::new(Scope {
+            vals: Lock::new(HashMap::new()),
+            decls: Lock::new(HashMap::new()),
+            parent: Some(Shared::clone(other)),
+            is_block: false,
+        })
+    }
+
+    pub fn new_block(other: &Shared<Self>) -> Shared<Self> {
+        Shared::new(Scope {
+            vals: Lock::new(HashMap::new()),
+            decls: Lock::new(HashMap::new()),
+            parent: Some(Shared::clone(other)),
+            is_block: true,
         })
     }
 
@@ -34,10 +47,11 @@ impl Scope {
     }
 
     pub fn get(&self, key: &str) -> Option<Val> {
-        match self.vals.borrow().get(key) {
-            Some(val) => Some(val.clone()),
-            None => None,
-        }
+        self.vals.borrow().get(key).cloned()
+    }
+
+    pub fn decl_loc(&self, key: &str) -> Option<Loc> {
+        self.decls.borrow().get(key).cloned()
     }
 
     pub fn get_val(&self, key: &str) -> Val {
@@ -0,0 +1,72 @@
+//! Reference counting primitives used by the interpreter.
+//!
+//! By default these are `Rc` and `RefCell`. The `sync` feature switches them
+//! to `Arc` and `RwLock`, so that parsed programs could be shared between
+//! threads and contexts could be moved to other threads.
+
+
+#[cfg(not(feature = "sync"))]
+mod imp {
+    pub use std::rc::{Rc as Shared, Weak};
+    pub use std::cell::RefCell as Lock;
+
+
+    /// Bounds required from values provided by the host (e.g. native
+    /// functions), `Send + Sync` with the `sync` feature.
+    pub trait Threadsafe {}
+
+    impl<T: ?Sized> Threadsafe for T {}
+}
+
+
+#[cfg(feature = "sync")]
+mod imp {
+    use std::fmt;
+    use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, PoisonError};
+
+    pub use std::sync::{Arc as Shared, Weak};
+
+
+    /// Bounds required from values provided by the host (e.g. native
+    /// functions), `Send + Sync` with the `sync` feature.
+    pub trait Threadsafe: Send + Sync {}
+
+    impl<T: ?Sized + Send + Sync> Threadsafe for T {}
+
+
+    /// `RefCell` lookalike on top of `RwLock`.
+    pub struct Lock<T>(RwLock<T>);
+
+
+    impl<T> Lock<T> {
+        pub fn new(val: T) -> Self {
+            Lock(RwLock::new(val))
+        }
+
+        pub fn borrow(&self) -> RwLockReadGuard<'_, T> {
+            // a panic in a native function must not brick the context
+            self.0.read().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        pub fn borrow_mut(&self) -> RwLockWriteGuard<'_, T> {
+            self.0.write().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+
+
+    impl<T: fmt::Debug> fmt::Debug for Lock<T> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.debug_tuple("Lock").field(&*self.borrow()).finish()
+        }
+    }
+
+
+    impl<T: PartialEq> PartialEq for Lock<T> {
+        fn eq(&self, other: &Self) -> bool {
+            *self.borrow() == *other.borrow()
+        }
+    }
+}
+
+
+pub use self::imp::*;
@@ -0,0 +1,233 @@
+//! List and map functions, including higher-order ones (`map`, `filter`,
+//! `reduce`, `sort`) that call script functions passed to them.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::mem;
+use compiler::*;
+use compiler::convert::mismatch;
+
+
+type List = Shared<Lock<Vec<Val>>>;
+type Map = Shared<Lock<BTreeMap<String, Val>>>;
+
+
+pub fn register(ctx: &mut ExecContext) {
+    ctx.decl_typed_func("len", len);
+    ctx.decl_typed_func("keys", |map: Map| -> Vec<String> { map.borrow().keys().cloned().collect() });
+    ctx.decl_typed_func("values", |map: Map| -> Vec<Val> { map.borrow().values().cloned().collect() });
+    ctx.decl_typed_func("pop", |list: List| list.borrow_mut().pop().ok_or("list is empty"));
+    ctx.decl_typed_func("remove", remove);
+
+    decl(ctx, "push", |call, (list, val): (List, Val)| {
+        call.charge(mem::size_of::<Val>())?;
+        list.borrow_mut().push(val);
+        Ok(Val::None)
+    });
+    decl(ctx, "insert", insert);
+    decl(ctx, "range", range);
+
+    decl(ctx, "map", |call, (list, func): (List, Val)| {
+        let mut r = Vec::new();
+        for item in snapshot(&list) {
+            r.push(call.call(&func, vec![item])?);
+        }
+        Ok(Val::new_list(r))
+    });
+
+    decl(ctx, "filter", |call, (list, func): (List, Val)| {
+        let mut r = Vec::new();
+        for item in snapshot(&list) {
+            if call.call(&func, vec![item.clone()])?.is_truthy() {
+                r.push(item);
+            }
+        }
+        Ok(Val::new_list(r))
+    });
+
+    decl(ctx, "reduce", |call, (list, func, init): (List, Val, Option<Val>)| {
+        let mut items = snapshot(&list).into_iter();
+        let mut acc = match init {
+            Some(init) => init,
+            None => items.next().ok_or_else(|| call.error("list is empty and there is no initial value"))?,
+        };
+
+        for item in items {
+            acc = call.call(&func, vec![acc, item])?;
+        }
+        Ok(acc)
+    });
+
+    // sorts in place, the list is left as is if a comparison fails
+    decl(ctx, "sort", |call, (list, func): (List, Option<Val>)| {
+        let items = snapshot(&list);
+        let sorted = match func {
+            Some(func) => merge_sort(items, &mut |a, b| compare_by(call, &func, a, b))?,
+            None => merge_sort(items, &mut |a, b| compare(call, a, b))?,
+        };
+        *list.borrow_mut() = sorted;
+        Ok(Val::None)
+    });
+}
+
+
+/// Declare native with arguments converted to `A`, it may call back into
+/// the script.
+fn decl<A, F>(ctx: &mut ExecContext, name: &str, func: F)
+    where A: FromArgs,
+          F: Fn(&mut CallContext, A) -> ExecResult + Threadsafe + 'static
+{
+    ctx.decl_context_func(name, A::arity(), move |call, args| {
+        let args = A::from_args(&args).map_err(|msg| call.error(&msg))?;
+        func(call, args)
+    });
+}
+
+
+/// Number of chars in a string or items in a collection.
+pub fn len(val: Val) -> Result<i32, String> {
+    let len = match val {
+        Val::Str(ref s) => s.chars().count(),
+        Val::List(ref items) => items.borrow().len(),
+        Val::Map(ref items) => items.borrow().len(),
+        _ => return Err(mismatch("string, list or map", &val)),
+    };
+
+    Ok(len as i32)
+}
+
+
+/// Items of the list, so that callbacks are free to modify it.
+fn snapshot(list: &List) -> Vec<Val> {
+    list.borrow().clone()
+}
+
+
+fn insert(call: &mut CallContext, (coll, key, val): (Val, Val, Val)) -> ExecResult {
+    call.charge(mem::size_of::<Val>())?;
+
+    match (&coll, &key) {
+        (Val::List(items), &Val::Num(idx)) => {
+            let mut items = items.borrow_mut();
+
+            if idx < 0 || idx as usize > items.len() {
+                return Err(call.error(&format!("index {} is out of range (length {})", idx, items.len())));
+            }
+            items.insert(idx as usize, val);
+        }
+
+        (Val::Map(items), Val::Str(key)) => {
+            call.charge(key.len())?;
+            items.borrow_mut().insert((**key).clone(), val);
+        }
+
+        _ => return Err(call.error(&format!("type mismatch, can't insert into '{}' by '{}'", coll, key))),
+    }
+
+    Ok(Val::None)
+}
+
+
+fn remove(coll: Val, key: Val) -> Result<Val, String> {
+    match (&coll, &key) {
+        (Val::List(items), &Val::Num(idx)) => {
+            let mut items = items.borrow_mut();
+
+            if idx < 0 || idx as usize >= items.len() {
+                return Err(format!("index {} is out of range (length {})", idx, items.len()));
+            }
+            Ok(items.remove(idx as usize))
+        }
+
+        (Val::Map(items), Val::Str(key)) => {
+            items.borrow_mut().remove(key.as_str())
+                .ok_or_else(|| format!("'{}': no such key", key))
+        }
+
+        _ => Err(format!("type mismatch, can't remove from '{}' by '{}'", coll, key)),
+    }
+}
+
+
+/// `range(end)`, `range(start, end)` or `range(start, end, step)`.
+fn range(call: &mut CallContext, (first, end, step): (i32, Option<i32>, Option<i32>)) -> ExecResult {
+    let (start, end) = match end {
+        Some(end) => (first, end),
+        None => (0, first),
+    };
+    let step = step.unwrap_or(1);
+
+    if step == 0 {
+        return Err(call.error("step must not be zero"));
+    }
+
+    let (start, end, step) = (i64::from(start), i64::from(end), i64::from(step));
+    let count = if step > 0 {
+        (end - start + step - 1).max(0) / step
+    } else {
+        (start - end - step - 1).max(0) / -step
+    };
+
+    // account the list before allocating it
+    call.charge(count as usize * mem::size_of::<Val>())?;
+
+    let items = (0..count).map(|idx| Val::Num((start + idx * step) as i32)).collect();
+    Ok(Val::new_list(items))
+}
+
+
+fn compare(call: &CallContext, a: &Val, b: &Val) -> Result<Ordering, FlowExc> {
+    let r = match (a, b) {
+        (&Val::Num(a), &Val::Num(b)) => Some(a.cmp(&b)),
+        (Val::Str(a), Val::Str(b)) => Some(a.cmp(b)),
+        _ => match (a.as_float(), b.as_float()) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => None,
+        },
+    };
+
+    r.ok_or_else(|| call.error(&format!("can't compare '{}' and '{}'", a, b)))
+}
+
+
+fn compare_by(call: &mut CallContext, func: &Val, a: &Val, b: &Val) -> Result<Ordering, FlowExc> {
+    let r = call.call(func, vec![a.clone(), b.clone()])?;
+
+    match r.as_float() {
+        Some(r) if r < 0.0 => Ok(Ordering::Less),
+        Some(r) if r > 0.0 => Ok(Ordering::Greater),
+        Some(_) => Ok(Ordering::Equal),
+        None => Err(call.error(&format!("comparator should return a number, found '{}'", r))),
+    }
+}
+
+
+/// Stable sort that stops on the first comparison error. Comparators come
+/// from scripts, so they aren't guaranteed to be consistent.
+fn merge_sort<F>(mut items: Vec<Val>, cmp: &mut F) -> Result<Vec<Val>, FlowExc>
+    where F: FnMut(&Val, &Val) -> Result<Ordering, FlowExc>
+{
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+
+    let right = items.split_off(items.len() / 2);
+    let left = merge_sort(items, cmp)?;
+    let right = merge_sort(right, cmp)?;
+
+    let mut r = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        if cmp(b, a)? == Ordering::Less {
+            r.push(right.next().unwrap());
+        } else {
+            r.push(left.next().unwrap());
+        }
+    }
+
+    r.extend(left);
+    r.extend(right);
+    Ok(r)
+}
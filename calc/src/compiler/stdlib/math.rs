@@ -0,0 +1,162 @@
+//! Math functions and constants: `abs`, `min`, `max`, `pow`, `sqrt`,
+//! rounding, trigonometry, `gcd`/`lcm`, `PI` and `E`. The constants are
+//! read-only, like the library functions.
+
+use std::convert::TryFrom;
+use std::f64::consts;
+use compiler::*;
+use compiler::convert::mismatch;
+
+
+/// Integer or float argument, integers are kept as is where possible.
+#[derive(Debug, Clone, Copy)]
+enum Number {
+    Int(i32),
+    Float(f64),
+}
+
+
+impl FromVal for Number {
+    fn from_val(val: &Val) -> Result<Self, String> {
+        match *val {
+            Val::Num(val) => Ok(Number::Int(val)),
+            Val::Float(val) => Ok(Number::Float(val)),
+            _ => Err(mismatch("number", val)),
+        }
+    }
+}
+
+
+impl IntoVal for Number {
+    fn into_val(self) -> Val {
+        match self {
+            Number::Int(val) => Val::Num(val),
+            Number::Float(val) => Val::Float(val),
+        }
+    }
+}
+
+
+pub fn register(ctx: &mut ExecContext) {
+    ctx.decl_const("PI", Val::Float(consts::PI));
+    ctx.decl_const("E", Val::Float(consts::E));
+
+    ctx.decl_typed_func("abs", abs);
+    ctx.decl_func_arity("min", Arity::Variadic(1), |args| extremum(args, |x, best| x < best));
+    ctx.decl_func_arity("max", Arity::Variadic(1), |args| extremum(args, |x, best| x > best));
+    ctx.decl_typed_func("pow", pow);
+    ctx.decl_typed_func("sqrt", |x: f64| domain(x.sqrt()));
+
+    ctx.decl_typed_func("floor", |x: Number| to_int(x, f64::floor));
+    ctx.decl_typed_func("ceil", |x: Number| to_int(x, f64::ceil));
+    ctx.decl_typed_func("round", |x: Number| to_int(x, f64::round));
+
+    ctx.decl_typed_func("sin", f64::sin);
+    ctx.decl_typed_func("cos", f64::cos);
+    ctx.decl_typed_func("tan", f64::tan);
+    ctx.decl_typed_func("asin", |x: f64| domain(x.asin()));
+    ctx.decl_typed_func("acos", |x: f64| domain(x.acos()));
+    ctx.decl_typed_func("atan", f64::atan);
+    ctx.decl_typed_func("atan2", f64::atan2);
+
+    ctx.decl_typed_func("gcd", |a: i32, b: i32| to_i32(gcd(a, b)));
+    ctx.decl_typed_func("lcm", lcm);
+}
+
+
+fn abs(x: Number) -> Result<Number, &'static str> {
+    match x {
+        Number::Int(val) => val.checked_abs().map(Number::Int).ok_or("integer overflow"),
+        Number::Float(val) => Ok(Number::Float(val.abs())),
+    }
+}
+
+
+fn extremum(args: Vec<Val>, better: fn(f64, f64) -> bool) -> Result<Val, String> {
+    let mut best: Option<(f64, &Val)> = None;
+
+    for (idx, arg) in args.iter().enumerate() {
+        let x = arg.as_float()
+            .ok_or_else(|| format!("argument {}: {}", idx + 1, mismatch("number", arg)))?;
+
+        match best {
+            Some((cur, _)) if !better(x, cur) => (),
+            _ => best = Some((x, arg)),
+        }
+    }
+
+    // arity guarantees at least one argument
+    Ok(best.unwrap().1.clone())
+}
+
+
+fn pow(base: Number, exp: Number) -> Result<Number, &'static str> {
+    match (base, exp) {
+        (Number::Int(base), Number::Int(exp)) if exp >= 0 => {
+            base.checked_pow(exp as u32).map(Number::Int).ok_or("integer overflow")
+        }
+        _ => Ok(Number::Float(as_f64(base).powf(as_f64(exp)))),
+    }
+}
+
+
+fn as_f64(x: Number) -> f64 {
+    match x {
+        Number::Int(val) => f64::from(val),
+        Number::Float(val) => val,
+    }
+}
+
+
+/// Result of a function defined on a part of real numbers.
+fn domain(r: f64) -> Result<f64, &'static str> {
+    if r.is_nan() {
+        return Err("argument is out of the function domain");
+    }
+
+    Ok(r)
+}
+
+
+fn to_int(x: Number, round: fn(f64) -> f64) -> Result<i32, String> {
+    let val = match x {
+        Number::Int(val) => return Ok(val),
+        Number::Float(val) => round(val),
+    };
+
+    if val.is_nan() || val < f64::from(i32::MIN) || val > f64::from(i32::MAX) {
+        return Err(format!("'{:?}' is out of integer range", as_f64(x)));
+    }
+
+    Ok(val as i32)
+}
+
+
+fn gcd(a: i32, b: i32) -> u32 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+
+    a
+}
+
+
+fn to_i32(val: u32) -> Result<i32, &'static str> {
+    i32::try_from(val).map_err(|_| "integer overflow")
+}
+
+
+fn lcm(a: i32, b: i32) -> Result<i32, &'static str> {
+    if a == 0 || b == 0 {
+        return Ok(0);
+    }
+
+    (a.unsigned_abs() / gcd(a, b))
+        .checked_mul(b.unsigned_abs())
+        .ok_or("integer overflow")
+        .and_then(to_i32)
+}
@@ -0,0 +1,16 @@
+//! Standard library of native functions. It is opt-in, hosts register the
+//! parts they need into an `ExecContext`.
+
+pub mod math;
+pub mod string;
+pub mod collections;
+
+use compiler::*;
+
+
+/// Register all parts of the standard library.
+pub fn register(ctx: &mut ExecContext) {
+    math::register(ctx);
+    string::register(ctx);
+    collections::register(ctx);
+}
@@ -0,0 +1,103 @@
+//! String functions. Indices and lengths are counted in chars, strings are
+//! shared instead of copied when the result is the same as the argument.
+
+use compiler::*;
+
+
+pub fn register(ctx: &mut ExecContext) {
+    ctx.decl_typed_func("len", super::collections::len);
+    ctx.decl_typed_func("upper", |s: Shared<String>| s.to_uppercase());
+    ctx.decl_typed_func("lower", |s: Shared<String>| s.to_lowercase());
+    ctx.decl_typed_func("trim", trim);
+    ctx.decl_typed_func("split", split);
+    ctx.decl_typed_func("join", join);
+    ctx.decl_typed_func("replace", replace);
+    ctx.decl_typed_func("contains", |s: Shared<String>, sub: Shared<String>| s.contains(sub.as_str()) as i32);
+    ctx.decl_typed_func("starts_with", |s: Shared<String>, sub: Shared<String>| s.starts_with(sub.as_str()) as i32);
+    ctx.decl_typed_func("ends_with", |s: Shared<String>, sub: Shared<String>| s.ends_with(sub.as_str()) as i32);
+    ctx.decl_typed_func("find", find);
+    ctx.decl_typed_func("substr", substr);
+    ctx.decl_typed_func("str", to_str);
+    ctx.decl_typed_func("parse_int", parse_int);
+}
+
+
+fn trim(s: Shared<String>) -> Shared<String> {
+    let trimmed = s.trim();
+
+    if trimmed.len() == s.len() {
+        return s;
+    }
+
+    Shared::new(String::from(trimmed))
+}
+
+
+fn split(s: Shared<String>, sep: Shared<String>) -> Vec<String> {
+    // empty separator splits into chars
+    if sep.is_empty() {
+        return s.chars().map(String::from).collect();
+    }
+
+    s.split(sep.as_str()).map(String::from).collect()
+}
+
+
+fn join(items: Vec<Shared<String>>, sep: Shared<String>) -> String {
+    let parts: Vec<&str> = items.iter().map(|s| s.as_str()).collect();
+    parts.join(&sep)
+}
+
+
+fn replace(s: Shared<String>, from: Shared<String>, to: Shared<String>) -> Result<Shared<String>, &'static str> {
+    if from.is_empty() {
+        return Err("pattern is empty");
+    }
+
+    if !s.contains(from.as_str()) {
+        return Ok(s);
+    }
+
+    Ok(Shared::new(s.replace(from.as_str(), &to)))
+}
+
+
+fn find(s: Shared<String>, sub: Shared<String>) -> Option<i32> {
+    s.find(sub.as_str())
+        .map(|idx| s[..idx].chars().count() as i32)
+}
+
+
+fn substr(s: Shared<String>, start: usize, len: Option<usize>) -> Shared<String> {
+    let begin = byte_offset(&s, start);
+    let end = match len {
+        Some(len) => begin + byte_offset(&s[begin..], len),
+        None => s.len(),
+    };
+
+    if begin == 0 && end == s.len() {
+        return s;
+    }
+
+    Shared::new(String::from(&s[begin..end]))
+}
+
+
+/// Offset of the char `idx`, the end of the string if it's out of range.
+fn byte_offset(s: &str, idx: usize) -> usize {
+    s.char_indices().nth(idx).map_or(s.len(), |(offset, _)| offset)
+}
+
+
+fn to_str(val: Val) -> Val {
+    match val {
+        Val::Str(_) => val,
+        _ => Val::Str(Shared::new(val.to_string())),
+    }
+}
+
+
+fn parse_int(s: Shared<String>) -> Result<i32, String> {
+    s.trim().parse()
+        .map_err(|_| format!("'{}' is not an integer", s))
+}
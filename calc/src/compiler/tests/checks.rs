@@ -2,6 +2,9 @@ use compiler::*;
 use self::AST::*;
 
 
+pub type Checker = Box<dyn Fn(&AST)>;
+
+
 pub fn check_var(expr: &AST, expected: &str) {
 	if let Var{ ref name, .. } = *expr {
 		assert_eq!(expected, name);
@@ -83,6 +86,68 @@ pub fn check_return<F>(expr: &AST, check: F)
 }
 
 
+pub fn check_yield<F>(expr: &AST, check: F)
+    where F: Fn(&AST)
+{
+    if let Yield{ ref val, .. } = *expr {
+        check(val);
+    } else {
+        panic!("Yield type expected: {:#?}", expr);
+    }
+}
+
+
+pub fn check_for<F1, F2>(expr: &AST, expected_var: &str, check_iter: F1, check_body: F2)
+    where F1: Fn(&AST), F2: Fn(&AST)
+{
+    if let For{ ref var, ref iter, ref body, .. } = *expr {
+        assert_eq!(expected_var, var);
+
+        check_iter(iter);
+        check_body(body);
+    } else {
+        panic!("For type expected: {:#?}", expr);
+    }
+}
+
+
+pub fn check_throw<F>(expr: &AST, check: F)
+    where F: Fn(&AST)
+{
+    if let Throw{ ref val, .. } = *expr {
+        check(val);
+    } else {
+        panic!("Throw type expected: {:#?}", expr);
+    }
+}
+
+
+pub fn check_try<F1, F2, F3>(expr: &AST, check_body: F1, expected_catch: Option<(&str, F2)>, check_finally: Option<F3>)
+    where F1: Fn(&AST), F2: Fn(&AST), F3: Fn(&AST)
+{
+    if let Try{ ref body, ref catch, ref finally, .. } = *expr {
+        check_body(body);
+
+        match (catch, expected_catch) {
+            (Some((var, handler)), Some((expected_var, check))) => {
+                assert_eq!(expected_var, var);
+                check(handler);
+            }
+            (None, None) => (),
+            _ => panic!("catch clause mismatch: {:#?}", expr),
+        }
+
+        match (finally, check_finally) {
+            (Some(block), Some(check)) => check(block),
+            (None, None) => (),
+            _ => panic!("finally clause mismatch: {:#?}", expr),
+        }
+    } else {
+        panic!("Try type expected: {:#?}", expr);
+    }
+}
+
+
 pub fn check_func<F>(expr: &AST, expected_name: &str, expected_args: &[&str], check_body: F)
     where F: Fn(&AST)
 {
@@ -103,14 +168,14 @@ pub fn check_func<F>(expr: &AST, expected_name: &str, expected_args: &[&str], ch
 }
 
 
-pub fn check_block(expr: &AST, checkers: &[Box<Fn(&AST)>]) {
+pub fn check_block(expr: &AST, checkers: &[Checker]) {
     if let Block{ ref body, .. } = *expr {
         if body.len() != checkers.len() {
             panic!("Wrong elements count: {} expected: {:#?}", checkers.len(), expr);
         }
 
         for (st, ch) in body.iter().zip(checkers) {
-            ch(&st);
+            ch(st);
         }
     } else {
         panic!("Block type expected: {:#?}", expr);
@@ -118,7 +183,7 @@ pub fn check_block(expr: &AST, checkers: &[Box<Fn(&AST)>]) {
 }
 
 
-pub fn check_call(expr: &AST, expected_name: &str, args_checkers: &[Box<Fn(&AST)>]) {
+pub fn check_call(expr: &AST, expected_name: &str, args_checkers: &[Checker]) {
     if let Call{ ref name, ref args, .. } = *expr {
         assert_eq!(expected_name, name);
 
@@ -127,7 +192,7 @@ pub fn check_call(expr: &AST, expected_name: &str, args_checkers: &[Box<Fn(&AST)
         }
 
         for (st, ch) in args.iter().zip(args_checkers) {
-            ch(&st);
+            ch(st);
         }
     } else {
         panic!("FuncCall type expected: {:#?}", expr);
@@ -135,3 +200,51 @@ pub fn check_call(expr: &AST, expected_name: &str, args_checkers: &[Box<Fn(&AST)
 }
 
 
+
+
+pub fn check_get_prop<F>(expr: &AST, expected_name: &str, check_obj: F)
+    where F: Fn(&AST)
+{
+    if let GetProp{ ref obj, ref name, .. } = *expr {
+        assert_eq!(expected_name, name);
+
+        check_obj(obj);
+    } else {
+        panic!("GetProp type expected: {:#?}", expr);
+    }
+}
+
+
+pub fn check_set_prop<F1, F2>(expr: &AST, expected_name: &str, check_obj: F1, check_val: F2)
+    where F1: Fn(&AST), F2: Fn(&AST)
+{
+    if let SetProp{ ref obj, ref name, ref val, .. } = *expr {
+        assert_eq!(expected_name, name);
+
+        check_obj(obj);
+        check_val(val);
+    } else {
+        panic!("SetProp type expected: {:#?}", expr);
+    }
+}
+
+
+pub fn check_method<F>(expr: &AST, expected_name: &str, check_obj: F, args_checkers: &[Checker])
+    where F: Fn(&AST)
+{
+    if let MethodCall{ ref obj, ref name, ref args, .. } = *expr {
+        assert_eq!(expected_name, name);
+
+        check_obj(obj);
+
+        if args.len() != args_checkers.len() {
+            panic!("Wrong elements count: {} expected: {:#?}", args_checkers.len(), expr);
+        }
+
+        for (st, ch) in args.iter().zip(args_checkers) {
+            ch(st);
+        }
+    } else {
+        panic!("MethodCall type expected: {:#?}", expr);
+    }
+}
@@ -6,3 +6,25 @@ mod test_parse_expr;
 mod test_parse_block;
 mod test_execute;
 mod test_exec_functions;
+mod test_exec_memory;
+mod test_exec_gc;
+mod test_exec_interrupt;
+mod test_exec_async;
+mod test_exec_generators;
+mod test_exec_exceptions;
+mod test_exec_typed;
+mod test_exec_objects;
+mod test_engine;
+mod test_diagnostic;
+mod test_lint;
+mod test_formatter;
+mod test_json;
+mod test_visitor;
+mod test_exec_modules;
+mod test_exec_collections;
+mod test_stdlib_math;
+mod test_stdlib_string;
+mod test_stdlib_collections;
+
+#[cfg(feature = "sync")]
+mod test_sync;
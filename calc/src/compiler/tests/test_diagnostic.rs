@@ -0,0 +1,81 @@
+use compiler;
+use compiler::*;
+use super::test_execute::*;
+
+
+#[test]
+fn primary_span() {
+    let text = "let x = 1;\nlet y = x +   (1 / 0);";
+    let err = exec_error(&mut ExecContext::new(), text);
+
+    assert_eq!(
+"error: division by zero
+ --> <test>:2:18
+  |
+2 | let y = x +   (1 / 0);
+  |                  ^
+", Renderer::new().source("<test>", text).render(&err));
+}
+
+
+#[test]
+fn multibyte_and_tabs() {
+    let text = "let ёж = 1;\n\tlet ёж = \"ёжик\";";
+    let err = exec_error(&mut ExecContext::new(), text);
+
+    assert_eq!(
+"error: 'ёж': redefinition
+ --> <test>:2:6
+  |
+1 | let ёж = 1;
+  |     -- first declared here
+2 | \tlet ёж = \"ёжик\";
+  | \t    ^^
+", Renderer::new().source("<test>", text).render(&err));
+}
+
+
+#[test]
+fn distant_label() {
+    let text = "fn f() {\n}\n\n\nlet f = 1;";
+    let err = exec_error(&mut ExecContext::new(), text);
+
+    assert_eq!(
+"error: 'f': redefinition
+ --> <test>:5:5
+  |
+1 | fn f() {
+  |    - first declared here
+...
+5 | let f = 1;
+  |     ^
+", Renderer::new().source("<test>", text).render(&err));
+}
+
+
+#[test]
+fn notes_and_unknown_source() {
+    let err = Loc::new("<host>", 3).error_str("bad input")
+        .with_label(Loc::new("lib.calc", 10).at(2, 40, 43), "declared here")
+        .with_note("input comes from the host")
+        .with_help("check the arguments");
+
+    assert_eq!(
+"error: bad input
+  --> <host>:3
+   = declared here (lib.calc:10:2)
+   = note: input comes from the host
+   = help: check the arguments
+", Renderer::new().render(&err));
+}
+
+
+#[test]
+fn color_output() {
+    let text = "x";
+    let err = compiler::parse(text, "main.calc").unwrap_err();
+    let out = Renderer::new().source("main.calc", text).color(true).render(&err);
+
+    assert!(out.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: "), "{:?}", out);
+    assert!(out.contains("\x1b[1;31m^\x1b[0m"), "{:?}", out);
+}
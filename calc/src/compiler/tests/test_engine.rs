@@ -0,0 +1,141 @@
+use std::env;
+use std::fs;
+use std::task::Poll;
+use compiler::*;
+
+
+#[test]
+fn eval_last_value() {
+    let mut engine = Engine::new();
+
+    let r = engine.eval(r#"
+        let x = 2;
+        x * 21;
+    "#);
+    assert_eq!(Ok(Val::Num(42)), r);
+
+    assert_eq!(Ok(Val::None), engine.eval("let y = 1;"));
+    assert_eq!(Ok(Val::Num(43)), engine.eval("x * 21 + y"));
+}
+
+
+#[test]
+fn eval_errors() {
+    let mut engine = Engine::new();
+
+    let err = engine.eval("1 +").unwrap_err();
+    assert_eq!(1, err.loc.line);
+
+    let err = engine.eval("return 1;").unwrap_err();
+    assert!(err.description.contains("unexpected 'return' statement"));
+}
+
+
+#[test]
+fn parse_then_execute() {
+    let mut engine = Engine::new();
+
+    let err = engine.parse("let x = ;", "main.calc").unwrap_err();
+    assert_eq!("main.calc", *err.loc.filename);
+    assert_eq!("';': unexpected token", err.description);
+
+    // errors of the expression grammar, if it gets further
+    let err = engine.parse("1 +", "main.calc").unwrap_err();
+    assert_eq!("unexpected end of file", err.description);
+    let err = engine.parse(r#""\q""#, "main.calc").unwrap_err();
+    assert_eq!("'\\q': unknown escape sequence", err.description);
+
+    let prog = engine.parse("let x = 20;", "main.calc").unwrap();
+    assert_eq!(Ok(Val::None), engine.execute(&prog));
+
+    let prog = engine.parse("x + 1", "main.calc").unwrap();
+    assert_eq!(Ok(Val::Num(21)), engine.execute(&prog));
+    assert_eq!(Ok(Val::Num(21)), engine.execute(&prog));
+
+    let prog = engine.parse("x / 0", "main.calc").unwrap();
+    assert!(engine.execute(&prog).is_err());
+}
+
+
+#[test]
+fn globals() {
+    let mut engine = Engine::new();
+
+    engine.set_global("name", "calc");
+    engine.set_global("count", 3);
+    engine.eval("let total = count * 2;").unwrap();
+
+    assert_eq!(Ok(6), engine.get_global::<i32>("total"));
+    assert_eq!(Ok(String::from("calc")), engine.get_global::<String>("name"));
+
+    let err = engine.get_global::<i32>("name").unwrap_err();
+    assert_eq!(r#"'name': type mismatch, number expected, found '"calc"'"#, err.description);
+
+    let err = engine.get_global::<i32>("missing").unwrap_err();
+    assert_eq!("'missing': undeclared variable", err.description);
+}
+
+
+#[test]
+fn call() {
+    let mut engine = Engine::new();
+
+    engine.eval(r#"
+        fn add(a, b) {
+            return a + b;
+        }
+
+        fn fail() {
+            throw "oops";
+        }
+    "#).unwrap();
+
+    assert_eq!(Ok(Val::Num(5)), engine.call("add", vec![Val::Num(2), Val::Num(3)]));
+    assert_eq!("oops", engine.call("fail", Vec::new()).unwrap_err().description);
+}
+
+
+#[test]
+fn run_file() {
+    let path = env::temp_dir().join(format!("calc_engine_{}.calc", std::process::id()));
+    fs::write(&path, "let x = 1;\nx + oops;\n").unwrap();
+
+    let mut engine = Engine::new();
+    let err = engine.run_file(&path).unwrap_err();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!("'oops': undeclared variable", err.description);
+    assert_eq!(path.display().to_string(), *err.loc.filename);
+    assert_eq!(2, err.loc.line);
+
+    let err = engine.run_file(&path).unwrap_err();
+    assert!(err.description.starts_with("can't read file"));
+}
+
+
+#[test]
+fn pending_native() {
+    let mut engine = Engine::new();
+
+    engine.context_mut().decl_func("wait", |_args| Poll::Pending);
+
+    let err = engine.eval("wait();").unwrap_err();
+    assert!(err.description.contains("suspended"));
+
+    // context is not left suspended
+    assert_eq!(Ok(Val::Num(1)), engine.eval("1"));
+}
+
+
+#[test]
+fn error_columns() {
+    let mut engine = Engine::new();
+
+    let err = engine.eval("let ё = 1;\nlet y = ё + 1 / 0;").unwrap_err();
+    assert_eq!("division by zero", err.description);
+    assert_eq!((2, 15), (err.loc.line, err.loc.col));
+    assert_eq!("/", &"let ё = 1;\nlet y = ё + 1 / 0;"[err.loc.start..err.loc.end]);
+
+    let err = engine.eval("let z = (1 + 2;").unwrap_err();
+    assert_eq!((1, 15), (err.loc.line, err.loc.col));
+}
@@ -0,0 +1,212 @@
+use std::collections::VecDeque;
+use std::task::Poll;
+use compiler;
+use compiler::*;
+
+
+type Jobs = Shared<Lock<VecDeque<(usize, Val)>>>;
+
+
+/// Stand-in executor: natives enqueue jobs, the loop completes them one by
+/// one and resumes the scripts that wait for them.
+struct Executor {
+    jobs: Jobs,
+    scripts: Vec<(ExecContext, AST)>,
+}
+
+
+impl Executor {
+    fn new() -> Self {
+        Executor {
+            jobs: Shared::new(Lock::new(VecDeque::new())),
+            scripts: Vec::new(),
+        }
+    }
+
+    fn spawn(&mut self, text: &str) {
+        let prog = compiler::parse(text, "<test>")
+            .expect("parse should not fail");
+
+        let mut ctx = ExecContext::new();
+        let id = self.scripts.len();
+
+        // async native: doubles its argument "in background"
+        let jobs = Shared::clone(&self.jobs);
+        ctx.decl_func("fetch", move |args: Vec<Val>| {
+            jobs.borrow_mut().push_back((id, args[0].clone()));
+            Poll::Pending
+        });
+
+        self.scripts.push((ctx, prog));
+    }
+
+    fn run(&mut self) -> Vec<ExecContext> {
+        for &mut (ref mut ctx, ref prog) in self.scripts.iter_mut() {
+            check_run(ctx, prog);
+        }
+
+        loop {
+            let job = self.jobs.borrow_mut().pop_front();
+            let (id, arg) = match job {
+                Some(job) => job,
+                None => break,
+            };
+
+            let (ref mut ctx, ref prog) = self.scripts[id];
+            assert!(ctx.is_suspended());
+
+            ctx.resume_with(Val::Num(arg.as_num().unwrap() * 2));
+            check_run(ctx, prog);
+        }
+
+        self.scripts.drain(..).map(|(ctx, _)| ctx).collect()
+    }
+}
+
+
+fn check_run(ctx: &mut ExecContext, prog: &AST) {
+    match compiler::execute(ctx, prog) {
+        Ok(_) | Err(FlowExc::Suspend) => (),
+        Err(err) => panic!("execution should not fail: {}", err.to_error()),
+    }
+}
+
+
+#[test]
+fn suspend_and_resume() {
+    let mut ex = Executor::new();
+    ex.spawn(r#"
+        let x = fetch(21);
+    "#);
+
+    let ctx = ex.run().remove(0);
+
+    assert!(!ctx.is_suspended());
+    assert_eq!(Val::Num(42), ctx.scope.get_val("x"));
+}
+
+
+#[test]
+fn resume_inside_expressions_and_calls() {
+    let mut ex = Executor::new();
+    ex.spawn(r#"
+        fn quad(x) {
+            let y = fetch(x);
+            return 2 * fetch(y);
+        }
+
+        let a = 1 + quad(fetch(5)) * fetch(1);  // 1 + 80 * 2
+        let b = a - 1;
+    "#);
+
+    let ctx = ex.run().remove(0);
+
+    assert_eq!(Val::Num(161), ctx.scope.get_val("a"));
+    assert_eq!(Val::Num(160), ctx.scope.get_val("b"));
+}
+
+
+#[test]
+fn side_effects_are_not_repeated() {
+    let mut ex = Executor::new();
+    ex.spawn(r#"
+        fn twice() {
+            return tick() + fetch(tick());
+        }
+
+        let r = tick() + twice() + tick();  // 1 + (2 + 6) + 4
+    "#);
+
+    let ticks = Shared::new(Lock::new(0));
+    let ticks_cl = Shared::clone(&ticks);
+    ex.scripts[0].0.decl_func("tick", move |_args| {
+        *ticks_cl.borrow_mut() += 1;
+        Val::Num(*ticks_cl.borrow())
+    });
+
+    let ctx = ex.run().remove(0);
+
+    assert_eq!(Val::Num(13), ctx.scope.get_val("r"));
+    assert_eq!(4, *ticks.borrow());
+}
+
+
+#[test]
+fn drive_many_scripts() {
+    let mut ex = Executor::new();
+    for idx in 0..10 {
+        ex.spawn(&format!(r#"
+            let a = fetch({});
+            let b = fetch(a);
+        "#, idx));
+    }
+
+    for (idx, ctx) in ex.run().into_iter().enumerate() {
+        assert_eq!(Val::Num(idx as i32 * 4), ctx.scope.get_val("b"));
+    }
+}
+
+
+#[test]
+fn resume_exec_func() {
+    let mut ctx = ExecContext::new();
+    ctx.decl_func("wait", |_args: Vec<Val>| Poll::Pending);
+
+    let prog = compiler::parse(r#"
+        fn main(x) {
+            return x + wait();
+        }
+    "#, "<test>").unwrap();
+    compiler::execute(&mut ctx, &prog).unwrap();
+
+    match ctx.exec_func("main", vec![Val::Num(2)]) {
+        Err(FlowExc::Suspend) => (),
+        r => panic!("function should be suspended: {:?}", r),
+    }
+
+    ctx.resume_with(Val::Num(40));
+    let r = ctx.exec_func("main", Vec::new())
+        .expect("function should succeed");
+
+    assert_eq!(Val::Num(42), r);
+}
+
+
+#[test]
+fn resume_requires_result() {
+    let mut ctx = ExecContext::new();
+    ctx.decl_func("wait", |_args: Vec<Val>| Poll::Pending);
+
+    let prog = compiler::parse("let x = wait();", "<test>").unwrap();
+    let other = compiler::parse("let y = 1;", "<test>").unwrap();
+
+    assert!(matches!(compiler::execute(&mut ctx, &prog), Err(FlowExc::Suspend)));
+
+    let err = compiler::execute(&mut ctx, &prog).unwrap_err().to_error();
+    assert!(err.description.contains("result is expected"));
+
+    ctx.resume_with(Val::Num(1));
+    let err = compiler::execute(&mut ctx, &other).unwrap_err().to_error();
+    assert!(err.description.contains("same program"));
+
+    // still suspended, so it could be finished
+    compiler::execute(&mut ctx, &prog).unwrap();
+    assert_eq!(Val::Num(1), ctx.scope.get_val("x"));
+}
+
+
+#[test]
+fn cancel_suspended() {
+    let mut ctx = ExecContext::new();
+    ctx.decl_func("wait", |_args: Vec<Val>| Poll::Pending);
+
+    let prog = compiler::parse("let x = wait();", "<test>").unwrap();
+    let other = compiler::parse("let y = 1;", "<test>").unwrap();
+
+    assert!(matches!(compiler::execute(&mut ctx, &prog), Err(FlowExc::Suspend)));
+    ctx.cancel_suspended();
+
+    compiler::execute(&mut ctx, &other).unwrap();
+    assert!(!ctx.is_suspended());
+    assert_eq!(Val::Num(1), ctx.scope.get_val("y"));
+}
@@ -0,0 +1,115 @@
+use compiler::*;
+use super::test_execute::*;
+
+
+#[test]
+fn literals() {
+    let ctx = exec(r#"
+        let x = 2;
+        let list = [1, x, "three"];
+        let map = {"a": x * 2, b: [x]};
+        let empty = {};
+    "#);
+
+    assert_eq!(r#"[1, 2, "three"]"#, ctx.scope.get_val("list").to_string());
+    assert_eq!(r#"{"a": 4, "b": [2]}"#, ctx.scope.get_val("map").to_string());
+    assert_eq!("{}", ctx.scope.get_val("empty").to_string());
+}
+
+
+#[test]
+fn indexing() {
+    let ctx = exec(r#"
+        let list = [1, 2, 3];
+        let map = {"a": 1};
+
+        list[1] = list[0] + 10;
+        map["b"] = map["a"] + 1;
+        map.c = map.b + 1;
+
+        let first = list[0];
+        let second = list[1];
+        let c = map.c;
+        let ch = "ёж"[1];
+    "#);
+
+    assert_eq!(Val::Num(1), ctx.scope.get_val("first"));
+    assert_eq!(Val::Num(11), ctx.scope.get_val("second"));
+    assert_eq!(Val::Num(3), ctx.scope.get_val("c"));
+    assert_eq!("ж", ctx.scope.get_val("ch").as_str().unwrap());
+    assert_eq!(r#"{"a": 1, "b": 2, "c": 3}"#, ctx.scope.get_val("map").to_string());
+}
+
+
+#[test]
+fn shared_mutation() {
+    let ctx = exec(r#"
+        fn set_first(list, val) {
+            list[0] = val;
+        }
+
+        let list = [1];
+        set_first(list, 42);
+    "#);
+
+    assert_eq!("[42]", ctx.scope.get_val("list").to_string());
+}
+
+
+#[test]
+fn index_errors() {
+    expect_error("let l = [1]; let x = l[1];", "index 1 out of range (length 1)");
+    expect_error(r#"let m = {}; let x = m["k"];"#, "'k': no such key");
+    expect_error(r#"let l = [1]; l["k"] = 1;"#, "type mismatch can't index");
+    expect_error("let x = 5; let y = x[0];", "type mismatch can't index '5' by '0'");
+}
+
+
+#[test]
+fn for_over_collections() {
+    let mut ctx = ExecContext::new();
+    let out = Shared::new(Lock::new(Vec::new()));
+    let sink = Shared::clone(&out);
+
+    ctx.decl_func("out", move |args: Vec<Val>| {
+        sink.borrow_mut().push(args[0].to_string());
+        Val::None
+    });
+
+    exec_with(&mut ctx, r#"
+        for x in [1, 2] {
+            out(x);
+        }
+
+        let map = {"b": 2, "a": 1};
+        for key in map {
+            out(map[key]);
+        }
+    "#);
+
+    assert_eq!(vec!["1", "2", "1", "2"], *out.borrow());
+}
+
+
+#[test]
+fn self_referencing() {
+    let ctx = exec(r#"
+        let list = [1, 2];
+        list[0] = list;
+
+        let map = {"a": 1};
+        map.self = map;
+        map.list = [map];
+
+        let other = [1, 2];
+        other[0] = other;
+    "#);
+
+    let list = ctx.scope.get_val("list");
+    assert_eq!("[[...], 2]", list.to_string());
+    assert_eq!("List([List([...]), Num(2)])", format!("{:?}", list));
+    assert_eq!(r#"{"a": 1, "list": [{...}], "self": {...}}"#, ctx.scope.get_val("map").to_string());
+
+    assert_eq!(list, ctx.scope.get_val("other"));
+    assert_ne!(list, ctx.scope.get_val("map"));
+}
@@ -0,0 +1,293 @@
+use compiler;
+use compiler::*;
+use super::test_execute::*;
+
+
+#[test]
+fn catch_thrown_value() {
+    let ctx = exec(r#"
+        let r = 0;
+        let caught = 0;
+        try {
+            throw "oops";
+            r = 1;
+        } catch (e) {
+            r = 2;
+            caught = e;
+        }
+    "#);
+
+    assert_eq!(Val::Num(2), ctx.scope.get_val("r"));
+    assert_eq!(None, ctx.scope.get("e"));
+
+    let e = ctx.scope.get_val("caught");
+    let err = e.as_error().expect("error value expected");
+    assert_eq!("oops", err.description);
+    assert_eq!(5, err.loc.line);
+}
+
+
+#[test]
+fn clause_scopes() {
+    let ctx = exec(r#"
+        let e = "outer";
+        let message = 0;
+
+        try {
+            let x = 1;
+            throw "oops";
+        } catch (e) {
+            let x = 2;
+            message = e.message;
+        } finally {
+            let x = 3;
+        }
+
+        let x = 4;
+    "#);
+
+    assert_eq!("outer", ctx.scope.get_val("e").as_str().unwrap());
+    assert_eq!("oops", ctx.scope.get_val("message").as_str().unwrap());
+    assert_eq!(Val::Num(4), ctx.scope.get_val("x"));
+}
+
+
+#[test]
+fn uncaught_throw() {
+    expect_error(r#"
+        throw "custom failure";
+    "#, "custom failure");
+}
+
+
+#[test]
+fn catch_builtin_errors() {
+    let ctx = exec(r#"
+        let undeclared = 0;
+        let mismatch = 0;
+        let zero = 0;
+
+        try {
+            let x = y;
+        } catch (e) { undeclared = e; }
+
+        fn foo() {}
+        try {
+            let x = foo + 1;
+        } catch (e) { mismatch = e; }
+
+        try {
+            let x = 1 / 0;
+        } catch (e) { zero = e; }
+    "#);
+
+    let check = |name: &str, expected: &str, line: i32| {
+        let e = ctx.scope.get_val(name);
+        let err = e.as_error().expect("error value expected");
+
+        assert!(err.description.contains(expected), "{}", err.description);
+        assert_eq!(line, err.loc.line);
+    };
+
+    check("undeclared", "undeclared variable", 7);
+    check("mismatch", "type mismatch", 12);
+    check("zero", "division by zero", 16);
+}
+
+
+#[test]
+fn catch_across_calls() {
+    let ctx = exec(r#"
+        fn fail(msg) {
+            throw msg;
+        }
+
+        fn safe() {
+            try {
+                fail("deep");
+            } catch (e) {
+                return e;
+            }
+            return 0;
+        }
+
+        let r = safe();
+    "#);
+
+    let r = ctx.scope.get_val("r");
+    assert_eq!("deep", r.as_error().unwrap().description);
+}
+
+
+#[test]
+fn rethrow_keeps_location() {
+    let mut ctx = ExecContext::new();
+    let prog = compiler::parse(r#"
+        try {
+            throw "first";
+        } catch (e) {
+            throw e;
+        }
+    "#, "<test>").unwrap();
+
+    let err = compiler::execute(&mut ctx, &prog).unwrap_err().to_error();
+
+    assert_eq!("first", err.description);
+    assert_eq!(3, err.loc.line);
+}
+
+
+#[test]
+fn finally_always_runs() {
+    let ctx = exec(r#"
+        let a = 0;
+        let b = 0;
+        let c = 0;
+
+        try {} finally {
+            a = 1;
+        }
+
+        try {
+            throw "x";
+        } catch (e) {
+        } finally {
+            b = 1;
+        }
+
+        try {
+            try {
+                throw "y";
+            } finally {
+                c = 1;
+            }
+        } catch (e) {}
+    "#);
+
+    assert_eq!(Val::Num(1), ctx.scope.get_val("a"));
+    assert_eq!(Val::Num(1), ctx.scope.get_val("b"));
+    assert_eq!(Val::Num(1), ctx.scope.get_val("c"));
+}
+
+
+#[test]
+fn finally_runs_on_return() {
+    let mut ctx = ExecContext::new();
+    let log = Shared::new(Lock::new(Vec::new()));
+
+    let log_cl = Shared::clone(&log);
+    ctx.decl_func("log", move |args: Vec<Val>| {
+        log_cl.borrow_mut().push(args[0].clone());
+        Val::None
+    });
+
+    exec_with(&mut ctx, r#"
+        fn foo() {
+            try {
+                return 42;
+            } finally {
+                log(1);
+            }
+            log(2);
+        }
+
+        let r = foo();
+    "#);
+
+    assert_eq!(Val::Num(42), ctx.scope.get_val("r"));
+    assert_eq!(vec![Val::Num(1)], *log.borrow());
+}
+
+
+#[test]
+fn finally_overrides_outcome() {
+    let ctx = exec(r#"
+        fn foo() {
+            try {
+                throw "lost";
+            } finally {
+                return 1;
+            }
+        }
+
+        let r = foo();
+    "#);
+
+    assert_eq!(Val::Num(1), ctx.scope.get_val("r"));
+}
+
+
+#[test]
+fn error_in_catch_handler() {
+    expect_error(r#"
+        try {
+            throw "first";
+        } catch (e) {
+            throw "second";
+        }
+    "#, "second");
+}
+
+
+#[test]
+fn interrupt_is_not_catchable() {
+    let mut ctx = ExecContext::new();
+    let handle = ctx.interrupt_handle();
+
+    ctx.decl_func("stop", move |_args| {
+        handle.interrupt();
+        Val::None
+    });
+
+    let prog = compiler::parse(r#"
+        let r = 0;
+        try {
+            stop();
+            r = 1;
+        } catch (e) {
+            r = 2;
+        } finally {
+            r = 3;
+        }
+    "#, "<test>").unwrap();
+
+    let err = compiler::execute(&mut ctx, &prog).unwrap_err().to_error();
+
+    assert_eq!(ErrorKind::Interrupted, err.kind);
+    assert_eq!(Val::Num(0), ctx.scope.get_val("r"));
+}
+
+
+#[test]
+fn yield_inside_try() {
+    let mut ctx = exec(r#"
+        fn gen() {
+            try {
+                let n = 1;
+                yield n;
+                throw "stop";
+            } catch (e) {
+                yield 2;
+                yield e.message;
+            } finally {
+                yield 3;
+            }
+        }
+    "#);
+
+    let g = ctx.exec_func("gen", Vec::new()).unwrap();
+
+    assert_eq!(Some(Val::Num(1)), ctx.next_value(&g).unwrap());
+    assert_eq!(Some(Val::Num(2)), ctx.next_value(&g).unwrap());
+    assert_eq!("stop", ctx.next_value(&g).unwrap().unwrap().as_str().unwrap());
+    assert_eq!(Some(Val::Num(3)), ctx.next_value(&g).unwrap());
+    assert_eq!(None, ctx.next_value(&g).unwrap());
+}
+
+
+#[test]
+fn integer_overflow() {
+    expect_error(r#"
+        let x = 2147483647 + 1;
+    "#, "integer overflow");
+}
@@ -1,5 +1,4 @@
-use std::rc::Rc;
-use std::cell::RefCell;
+use compiler;
 use compiler::*;
 use super::test_execute::*;
 
@@ -151,9 +150,9 @@ fn capture_vals_dynamic() {
 #[test]
 fn hello_world() {
     let mut ctx = ExecContext::new();
-    let out = Rc::new(RefCell::new(String::new()));
+    let out = Shared::new(Lock::new(String::new()));
 
-    let out_cl = Rc::clone(&out);
+    let out_cl = Shared::clone(&out);
     ctx.decl_func("println", move |args| {
         for val in args {
             out_cl.borrow_mut().push_str(val.as_str().unwrap());
@@ -175,5 +174,61 @@ fn hello_world() {
 }
 
 
-// TODO: nested methods may fail! (e.g.: input args validation)
-// TODO: standard library
+#[test]
+fn native_func_errors() {
+    let mut ctx = ExecContext::new();
+
+    ctx.decl_func("half", |args: Vec<Val>| {
+        match args[0].as_num() {
+            Some(val) if val % 2 == 0 => Ok(Val::Num(val / 2)),
+            Some(val) => Err(format!("{} is odd", val)),
+            None => Err(format!("number expected, found '{}'", args[0])),
+        }
+    });
+
+    exec_with(&mut ctx, r#"
+        let a = half(42);
+        let b = 0;
+        let caught = 0;
+        try {
+            b = half(3);
+        } catch (e) {
+            caught = e;
+        }
+    "#);
+
+    assert_eq!(Val::Num(21), ctx.scope.get_val("a"));
+    assert_eq!(Val::Num(0), ctx.scope.get_val("b"));
+
+    let e = ctx.scope.get_val("caught");
+    let err = e.as_error().unwrap();
+    assert_eq!("'half': 3 is odd", err.description);
+    assert_eq!(6, err.loc.line);
+}
+
+
+#[test]
+fn native_func_arity() {
+    let mut ctx = ExecContext::new();
+
+    ctx.decl_func_arity("one", Arity::Fixed(1), |_args| Val::None);
+    ctx.decl_func_arity("few", Arity::Range(1, 2), |_args| Val::None);
+    ctx.decl_func_arity("many", Arity::Variadic(1), |_args| Val::None);
+
+    exec_with(&mut ctx, r#"
+        one(1);
+        few(1); few(1, 2);
+        many(1); many(1, 2, 3, 4);
+    "#);
+
+    let expect_arity_error = |ctx: &mut ExecContext, text: &str, expected: &str| {
+        let prog = compiler::parse(text, "<test>").unwrap();
+        let err = compiler::execute(ctx, &prog).unwrap_err().to_error();
+
+        assert_eq!(format!("wrong arguments count, expected {} args", expected), err.description);
+    };
+
+    expect_arity_error(&mut ctx, "one();", "1");
+    expect_arity_error(&mut ctx, "few(1, 2, 3);", "1 to 2");
+    expect_arity_error(&mut ctx, "many();", "at least 1");
+}
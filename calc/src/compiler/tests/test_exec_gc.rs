@@ -0,0 +1,174 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use compiler::*;
+use super::test_execute::*;
+
+
+struct DropFlag(Shared<AtomicBool>);
+
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+
+#[test]
+fn drop_context_frees_scopes() {
+    let mut ctx = ExecContext::new();
+    let dropped = Shared::new(AtomicBool::new(false));
+
+    let flag = DropFlag(Shared::clone(&dropped));
+    ctx.decl_func("native", move |_args| {
+        let _ = &flag;
+        Val::None
+    });
+
+    exec_with(&mut ctx, r#"
+        fn fact(n) {
+            return n * fact(n - 1);
+        }
+
+        fn make_func(x) {
+            fn mul(y) {
+                return x * y;
+            }
+            return mul;
+        }
+
+        let mul_10 = make_func(10);
+    "#);
+
+    let global = Shared::downgrade(&ctx.scope);
+    let closure = match ctx.scope.get_val("mul_10") {
+        Val::Func{ ref scope, .. } => Shared::downgrade(scope),
+        val => panic!("function expected: {}", val),
+    };
+
+    drop(ctx);
+
+    assert!(global.upgrade().is_none(), "global scope leaked");
+    assert!(closure.upgrade().is_none(), "closure scope leaked");
+    assert!(dropped.load(Ordering::Relaxed), "native function leaked");
+}
+
+
+#[test]
+fn collect_closure_cycles() {
+    let mut ctx = ExecContext::new();
+
+    exec_with(&mut ctx, r#"
+        fn make_func(x) {
+            fn mul(y) {
+                return x * y;
+            }
+            return mul;
+        }
+
+        let keep = make_func(1);
+    "#);
+
+    ctx.collect_garbage();
+    let used = ctx.memory_used();
+
+    exec_with(&mut ctx, r#"
+        make_func(2);
+        make_func(3);
+        make_func(4);
+    "#);
+
+    assert!(ctx.collect_garbage() > 0);
+    assert_eq!(used, ctx.memory_used());
+    assert_eq!(Val::Num(42), ctx.exec_func("keep", vec![Val::Num(42)]).unwrap());
+}
+
+
+#[test]
+fn collect_keeps_reachable_scopes() {
+    let mut ctx = ExecContext::new();
+
+    exec_with(&mut ctx, r#"
+        fn make_func(x) {
+            fn mul(y) {
+                return x * y;
+            }
+            return mul;
+        }
+    "#);
+
+    let mul = ctx.exec_func("make_func", vec![Val::Num(10)]).unwrap();
+    ctx.collect_garbage();
+
+    // the closure is held by the host only
+    ctx.set_var("mul", mul);
+    assert_eq!(Val::Num(420), ctx.exec_func("mul", vec![Val::Num(42)]).unwrap());
+}
+
+
+#[test]
+fn collect_container_cycles() {
+    let mut ctx = ExecContext::new();
+
+    exec_with(&mut ctx, r#"
+        fn make_cycles() {
+            let list = [1];
+            list[0] = list;
+
+            let map = {};
+            map.self = [map];
+
+            // the closure scope holds the list, which holds the closure
+            let funcs = [0];
+            fn get() {
+                return funcs;
+            }
+            funcs[0] = get;
+        }
+    "#);
+
+    exec_with(&mut ctx, "make_cycles();");
+    ctx.collect_garbage();
+    let used = ctx.memory_used();
+
+    exec_with(&mut ctx, r#"
+        make_cycles();
+        make_cycles();
+
+        let list = [1];
+        list[0] = list;
+    "#);
+
+    let list = match ctx.scope.get_val("list") {
+        Val::List(ref items) => Shared::downgrade(items),
+        val => panic!("list expected: {}", val),
+    };
+
+    ctx.scope.vals.borrow_mut().remove("list");
+    assert!(ctx.collect_garbage() > 0);
+    assert!(list.upgrade().is_none(), "list leaked");
+    assert_eq!(used, ctx.memory_used());
+}
+
+
+#[test]
+fn drop_context_frees_container_cycles() {
+    let mut ctx = ExecContext::new();
+
+    exec_with(&mut ctx, r#"
+        let list = [1];
+        list[0] = list;
+        let keep = [2];
+        let outer = [keep];
+    "#);
+
+    let list = match ctx.scope.get_val("list") {
+        Val::List(ref items) => Shared::downgrade(items),
+        val => panic!("list expected: {}", val),
+    };
+    let keep = ctx.scope.get_val("keep");
+
+    drop(ctx);
+
+    assert!(list.upgrade().is_none(), "list leaked");
+    assert_eq!("[2]", keep.to_string());
+}
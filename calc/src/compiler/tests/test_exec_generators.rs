@@ -0,0 +1,277 @@
+use std::task::Poll;
+use compiler;
+use compiler::*;
+use super::test_execute::*;
+
+
+fn collect(ctx: &mut ExecContext, iter: &Val) -> Vec<Val> {
+    let mut r = Vec::new();
+
+    while let Some(val) = ctx.next_value(iter).map_err(|err| err.to_error()).unwrap() {
+        r.push(val);
+    }
+
+    r
+}
+
+
+#[test]
+fn call_returns_generator() {
+    let ctx = exec(r#"
+        fn gen() {
+            yield 1;
+        }
+
+        let g = gen();
+    "#);
+
+    assert!(ctx.scope.get_val("gen").is_func());
+    assert!(ctx.scope.get_val("g").is_generator());
+}
+
+
+#[test]
+fn pull_values_from_host() {
+    let mut ctx = exec(r#"
+        fn gen(a) {
+            yield a;
+            yield a * 2;
+            return 0;
+            yield a * 3;
+        }
+
+        let g = gen(21);
+    "#);
+
+    let g = ctx.scope.get_val("g");
+
+    assert_eq!(vec![Val::Num(21), Val::Num(42)], collect(&mut ctx, &g));
+    assert_eq!(None, ctx.next_value(&g).unwrap());
+}
+
+
+#[test]
+fn generators_are_lazy() {
+    let mut ctx = ExecContext::new();
+    let log = Shared::new(Lock::new(Vec::new()));
+
+    let log_cl = Shared::clone(&log);
+    ctx.decl_func("log", move |args: Vec<Val>| {
+        log_cl.borrow_mut().push(args[0].clone());
+        Val::None
+    });
+
+    exec_with(&mut ctx, r#"
+        fn gen() {
+            log(1);
+            yield 10;
+            log(2);
+            yield 20;
+            log(3);
+        }
+
+        let g = gen();
+    "#);
+
+    let g = ctx.scope.get_val("g");
+    assert!(log.borrow().is_empty());
+
+    assert_eq!(Some(Val::Num(10)), ctx.next_value(&g).unwrap());
+    assert_eq!(vec![Val::Num(1)], *log.borrow());
+
+    assert_eq!(Some(Val::Num(20)), ctx.next_value(&g).unwrap());
+    assert_eq!(None, ctx.next_value(&g).unwrap());
+    assert_eq!(vec![Val::Num(1), Val::Num(2), Val::Num(3)], *log.borrow());
+}
+
+
+#[test]
+fn for_loop() {
+    let ctx = exec(r#"
+        fn gen(a) {
+            yield a;
+            yield a + 1;
+            yield a + 2;
+        }
+
+        let sum = 0;
+        for x in gen(10) {
+            sum = sum + x;
+        }
+    "#);
+
+    assert_eq!(Val::Num(33), ctx.scope.get_val("sum"));
+    assert_eq!(None, ctx.scope.get("x"));
+}
+
+
+#[test]
+fn declare_in_loop_body() {
+    let ctx = exec(r#"
+        let squares = [0, 0, 0];
+        for x in [1, 2, 3] {
+            let square = x * x;
+            fn get() {
+                return square;
+            }
+            squares[x - 1] = get;
+        }
+
+        let get = squares[2];
+        let last = get();
+        get = squares[0];
+        let first = get();
+    "#);
+
+    assert_eq!(Val::Num(9), ctx.scope.get_val("last"));
+    assert_eq!(Val::Num(1), ctx.scope.get_val("first"));
+    assert_eq!(None, ctx.scope.get("square"));
+}
+
+
+#[test]
+fn loop_variable_shadows_outer() {
+    let ctx = exec(r#"
+        let x = 42;
+        let seen = 0;
+        for x in [1, 2] {
+            seen = seen + x;
+        }
+    "#);
+
+    assert_eq!(Val::Num(3), ctx.scope.get_val("seen"));
+    assert_eq!(Val::Num(42), ctx.scope.get_val("x"));
+}
+
+
+#[test]
+fn keep_state_between_values() {
+    let mut ctx = exec(r#"
+        fn gen() {
+            let a = 1;
+            yield a;
+            let b = a + 1;
+            yield a + b * (10 + 0 * 0);
+        }
+    "#);
+
+    let g = ctx.exec_func("gen", Vec::new()).unwrap();
+
+    assert_eq!(vec![Val::Num(1), Val::Num(21)], collect(&mut ctx, &g));
+}
+
+
+#[test]
+fn nested_generators() {
+    let mut ctx = exec(r#"
+        fn range3(a) {
+            yield a;
+            yield a + 1;
+            yield a + 2;
+        }
+
+        fn squares(a) {
+            for x in range3(a) {
+                yield x * x;
+            }
+        }
+
+        // infinite, since it's lazy
+        fn naturals(n) {
+            yield n;
+            for x in naturals(n + 1) {
+                yield x;
+            }
+        }
+    "#);
+
+    let g = ctx.exec_func("squares", vec![Val::Num(2)]).unwrap();
+    assert_eq!(vec![Val::Num(4), Val::Num(9), Val::Num(16)], collect(&mut ctx, &g));
+
+    let g = ctx.exec_func("naturals", vec![Val::Num(1)]).unwrap();
+    for expected in 1..6 {
+        assert_eq!(Some(Val::Num(expected)), ctx.next_value(&g).unwrap());
+    }
+}
+
+
+#[test]
+fn suspend_inside_generator() {
+    let mut ctx = ExecContext::new();
+    ctx.decl_func("wait", |_args: Vec<Val>| Poll::Pending);
+
+    let prog = compiler::parse(r#"
+        fn gen() {
+            yield 1;
+            yield 1 + wait();
+            yield 3;
+        }
+
+        let sum = 0;
+        for x in gen() {
+            sum = sum + x * 10;
+        }
+    "#, "<test>").unwrap();
+
+    assert!(matches!(compiler::execute(&mut ctx, &prog), Err(FlowExc::Suspend)));
+
+    ctx.resume_with(Val::Num(1));
+    compiler::execute(&mut ctx, &prog)
+        .map_err(|err| err.to_error())
+        .unwrap();
+
+    assert_eq!(Val::Num(60), ctx.scope.get_val("sum"));
+}
+
+
+#[test]
+fn unexpected_yield() {
+    expect_error(r#"
+        yield 42;
+    "#, "unexpected yield statement");
+}
+
+
+#[test]
+fn iterate_non_iterable() {
+    expect_error(r#"
+        for x in 42 {}
+    "#, "type mismatch iterable 42");
+}
+
+
+#[test]
+fn generator_running() {
+    expect_error(r#"
+        fn gen() {
+            for x in g {
+                yield x;
+            }
+        }
+
+        let g = gen();
+        for x in g {}
+    "#, "generator already running");
+}
+
+
+#[test]
+fn suspend_while_pulled_from_host() {
+    let mut ctx = ExecContext::new();
+    ctx.decl_func("wait", |_args: Vec<Val>| Poll::Pending);
+
+    exec_with(&mut ctx, r#"
+        fn gen() {
+            yield wait() * 2;
+        }
+
+        let g = gen();
+    "#);
+
+    let g = ctx.scope.get_val("g");
+    assert!(matches!(ctx.next_value(&g), Err(FlowExc::Suspend)));
+
+    ctx.resume_with(Val::Num(21));
+    assert_eq!(Some(Val::Num(42)), ctx.next_value(&g).unwrap());
+    assert_eq!(None, ctx.next_value(&g).unwrap());
+}
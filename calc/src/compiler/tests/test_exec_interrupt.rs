@@ -0,0 +1,146 @@
+use std::sync::mpsc;
+use std::task::Poll;
+use std::thread;
+use std::time::Duration;
+use compiler;
+use compiler::*;
+use super::test_execute::*;
+
+
+#[test]
+fn interrupt_from_native() {
+    let mut ctx = ExecContext::new();
+    let handle = ctx.interrupt_handle();
+
+    ctx.decl_func("stop", move |_args| {
+        handle.interrupt();
+        Val::None
+    });
+
+    let err = exec_result(&mut ctx, r#"
+        let x = 1;
+        stop();
+        let y = 2;
+    "#).expect_err("script should be interrupted");
+
+    assert_eq!(ErrorKind::Interrupted, err.kind);
+    assert_eq!(4, err.loc.line);
+    assert_eq!(Val::Num(1), ctx.scope.get_val("x"));
+    assert_eq!(None, ctx.scope.get("y"));
+}
+
+
+#[test]
+fn interrupt_inside_function() {
+    let mut ctx = ExecContext::new();
+    let handle = ctx.interrupt_handle();
+
+    ctx.decl_func("stop", move |_args| {
+        handle.interrupt();
+        Val::None
+    });
+
+    let err = exec_result(&mut ctx, r#"
+        fn foo() {
+            stop();
+            return 42;
+        }
+
+        let x = foo();
+    "#).expect_err("script should be interrupted");
+
+    assert_eq!(ErrorKind::Interrupted, err.kind);
+    assert_eq!(None, ctx.scope.get("x"));
+}
+
+
+#[test]
+fn context_usable_after_interrupt() {
+    let mut ctx = ExecContext::new();
+    let handle = ctx.interrupt_handle();
+
+    ctx.decl_func("stop", move |_args| {
+        handle.interrupt();
+        Val::None
+    });
+
+    exec_result(&mut ctx, r#"
+        fn double(x) {
+            return x * 2;
+        }
+
+        stop();
+        double(1);
+    "#).expect_err("script should be interrupted");
+
+    exec_with(&mut ctx, "let r = double(21);");
+
+    assert_eq!(Val::Num(42), ctx.scope.get_val("r"));
+}
+
+
+#[test]
+fn interrupt_from_other_thread() {
+    let mut ctx = ExecContext::new();
+    let handle = ctx.interrupt_handle();
+    let (started, wait_started) = mpsc::channel();
+
+    ctx.decl_func("started", move |_args| {
+        started.send(()).unwrap();
+        Val::None
+    });
+    ctx.decl_func("wait", |_args| {
+        thread::sleep(Duration::from_millis(1));
+        Val::None
+    });
+
+    let watchdog = thread::spawn(move || {
+        wait_started.recv().unwrap();
+        handle.interrupt();
+    });
+
+    let text = format!("started();\n{}", "wait();\n".repeat(10000));
+    let err = exec_result(&mut ctx, &text)
+        .expect_err("script should be interrupted");
+
+    watchdog.join().unwrap();
+    assert_eq!(ErrorKind::Interrupted, err.kind);
+}
+
+
+#[test]
+fn interrupt_while_idle_is_dropped() {
+    let mut ctx = ExecContext::new();
+
+    ctx.interrupt_handle().interrupt();
+    exec_with(&mut ctx, "let x = 1;");
+
+    assert_eq!(Val::Num(1), ctx.scope.get_val("x"));
+}
+
+
+#[test]
+fn interrupt_while_suspended_stops_resumed_script() {
+    let mut ctx = ExecContext::new();
+    ctx.decl_func("fetch", |_args| Poll::Pending::<Val>);
+
+    let prog = compiler::parse(r#"
+        let x = fetch();
+        let y = x;
+    "#, "<test>").expect("parse should not fail");
+
+    match compiler::execute(&mut ctx, &prog) {
+        Err(FlowExc::Suspend) => (),
+        r => panic!("execution should be suspended: {:?}", r),
+    }
+
+    ctx.interrupt_handle().interrupt();
+    ctx.resume_with(Val::Num(1));
+
+    let err = compiler::execute(&mut ctx, &prog)
+        .map_err(|err| err.to_error())
+        .expect_err("script should be interrupted");
+
+    assert_eq!(ErrorKind::Interrupted, err.kind);
+    assert_eq!(None, ctx.scope.get("y"));
+}
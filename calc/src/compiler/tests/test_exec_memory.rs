@@ -0,0 +1,108 @@
+use compiler::*;
+use super::test_execute::*;
+
+
+fn decl_big_str(ctx: &mut ExecContext, size: usize) {
+    ctx.decl_func("big", move |_args| {
+        Val::Str(Shared::new("x".repeat(size)))
+    });
+}
+
+
+#[test]
+fn count_used_memory() {
+    let mut ctx = ExecContext::new();
+    let before = ctx.memory_used();
+
+    exec_with(&mut ctx, r#"
+        let s = "some long enough string";
+    "#);
+
+    assert!(ctx.memory_used() > before + "some long enough string".len());
+}
+
+
+#[test]
+fn count_shared_strings_once() {
+    let mut ctx = ExecContext::new();
+    decl_big_str(&mut ctx, 4096);
+
+    exec_with(&mut ctx, r#"
+        let a = big();
+    "#);
+    let used = ctx.memory_used();
+
+    exec_with(&mut ctx, r#"
+        let b = a;
+    "#);
+
+    assert!(ctx.memory_used() < used + 4096);
+}
+
+
+#[test]
+fn memory_limit_exceeded() {
+    let mut ctx = ExecContext::new();
+    decl_big_str(&mut ctx, 4096);
+    ctx.set_memory_limit(Some(1024));
+
+    let err = exec_result(&mut ctx, r#"
+        let s = big();
+    "#).expect_err("script should hit the memory limit");
+
+    assert!(err.description.contains("memory limit exceeded"));
+    assert_eq!(ErrorKind::MemoryLimit, err.kind);
+    assert_eq!(2, err.loc.line);
+}
+
+
+#[test]
+fn memory_limit_refunds_dropped_values() {
+    let mut ctx = ExecContext::new();
+    decl_big_str(&mut ctx, 4096);
+    ctx.set_memory_limit(Some(10000));
+
+    // each value is dropped right away, so only one is held at a time
+    exec_result(&mut ctx, r#"
+        big(); big(); big(); big(); big();
+        big(); big(); big(); big(); big();
+    "#).expect("temporary values should not exceed the limit");
+}
+
+
+#[test]
+fn memory_limit_in_nested_calls() {
+    let mut ctx = ExecContext::new();
+    decl_big_str(&mut ctx, 4096);
+    ctx.set_memory_limit(Some(10000));
+
+    let err = exec_result(&mut ctx, r#"
+        fn hold(a) {
+            let b = big();
+            let c = big();
+            return 0;
+        }
+
+        hold(big());
+    "#).expect_err("script should hit the memory limit");
+
+    assert!(err.description.contains("memory limit exceeded"));
+}
+
+
+#[test]
+fn memory_limit_churn_close_to_limit() {
+    let mut ctx = ExecContext::new();
+    decl_big_str(&mut ctx, 4096);
+    ctx.decl_func("small", |_args| Val::Str(Shared::new("x".repeat(64))));
+    ctx.set_memory_limit(Some(10000));
+
+    // the temporary strings are refunded by occasional recounts
+    let text = format!("let a = big();\nlet b = big();\n{}", "small();\n".repeat(5000));
+    exec_result(&mut ctx, &text)
+        .expect("temporary values should not exceed the limit");
+
+    let err = exec_result(&mut ctx, "let c = big();")
+        .expect_err("script should hit the memory limit");
+    assert_eq!(ErrorKind::MemoryLimit, err.kind);
+}
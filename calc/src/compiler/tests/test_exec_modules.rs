@@ -0,0 +1,216 @@
+use std::env;
+use std::fs;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use compiler::*;
+use super::test_execute::*;
+
+
+fn modules(list: &[(&str, &str)]) -> MemoryLoader {
+    let mut loader = MemoryLoader::new();
+
+    for &(name, text) in list {
+        loader.add(name, text);
+    }
+
+    loader
+}
+
+
+const UTIL: &str = r#"
+    let base = 10;
+
+    fn add(a, b) {
+        return base + a + b;
+    }
+"#;
+
+
+#[test]
+fn import_as() {
+    let mut ctx = ExecContext::new();
+    ctx.set_module_loader(modules(&[("util", UTIL)]));
+
+    exec_with(&mut ctx, r#"
+        import "util" as util;
+
+        let x = util.add(1, 2);
+        let y = util.base;
+    "#);
+
+    assert_eq!(Val::Num(13), ctx.scope.get_val("x"));
+    assert_eq!(Val::Num(10), ctx.scope.get_val("y"));
+    assert_eq!(r#"module "util""#, ctx.scope.get_val("util").to_string());
+}
+
+
+#[test]
+fn from_import() {
+    let mut ctx = ExecContext::new();
+    ctx.set_module_loader(modules(&[("util", UTIL)]));
+
+    exec_with(&mut ctx, r#"
+        from "util" import add, base;
+
+        let x = add(base, 0);
+    "#);
+
+    assert_eq!(Val::Num(20), ctx.scope.get_val("x"));
+
+    let err = exec_error(&mut ctx, r#"from "util" import sub;"#);
+    assert_eq!("'sub': not found in module 'util'", err.description);
+}
+
+
+struct CountingLoader {
+    inner: MemoryLoader,
+    loads: Arc<AtomicUsize>,
+}
+
+
+impl ModuleLoader for CountingLoader {
+    fn load(&self, name: &str) -> Result<String, String> {
+        self.loads.fetch_add(1, Ordering::SeqCst);
+        self.inner.load(name)
+    }
+}
+
+
+#[test]
+fn executed_once() {
+    let loads = Arc::new(AtomicUsize::new(0));
+    let mut ctx = ExecContext::new();
+
+    ctx.set_module_loader(CountingLoader {
+        inner: modules(&[
+            ("util", UTIL),
+            ("a", r#"import "util" as util; let x = util.add(0, 0);"#),
+        ]),
+        loads: Arc::clone(&loads),
+    });
+
+    exec_with(&mut ctx, r#"
+        import "util" as util;
+        import "a" as a;
+        from "util" import add;
+    "#);
+
+    assert_eq!(2, loads.load(Ordering::SeqCst));
+    assert_eq!(Val::Num(10), ctx.scope.get_val("a").as_module().unwrap().scope.get_val("x"));
+}
+
+
+#[test]
+fn cyclic_import() {
+    let mut ctx = ExecContext::new();
+    ctx.set_module_loader(modules(&[
+        ("a", r#"import "b" as b;"#),
+        ("b", r#"
+
+            import "a" as a;
+        "#),
+    ]));
+
+    let err = exec_error(&mut ctx, r#"import "a" as a;"#);
+
+    assert_eq!("'a': cyclic import (a -> b -> a)", err.description);
+    assert_eq!("b", *err.loc.filename);
+    assert_eq!(3, err.loc.line);
+
+    // nothing is left marked as being imported
+    let err = exec_error(&mut ctx, r#"import "b" as b;"#);
+    assert_eq!("'b': cyclic import (b -> a -> b)", err.description);
+}
+
+
+#[test]
+fn separate_scopes() {
+    let mut ctx = ExecContext::new();
+    ctx.set_module_loader(modules(&[
+        ("peek", "let x = secret;"),
+        ("util", UTIL),
+    ]));
+
+    let err = exec_error(&mut ctx, r#"
+        let secret = 1;
+        import "peek" as peek;
+    "#);
+
+    assert_eq!("'secret': undeclared variable", err.description);
+    assert_eq!("peek", *err.loc.filename);
+
+    let err = exec_error(&mut ctx, r#"
+        import "util" as util;
+        util.base = 0;
+    "#);
+    assert_eq!("'base': variables of module 'util' are read-only", err.description);
+}
+
+
+#[test]
+fn modules_see_natives() {
+    let mut ctx = ExecContext::new();
+    stdlib::register(&mut ctx);
+    ctx.decl_func("twice", |args: Vec<Val>| Val::Num(args[0].as_num().unwrap() * 2));
+    ctx.set_module_loader(modules(&[
+        ("strings", r#"
+            let size = len("abc");
+            let doubled = twice(size);
+
+            // natives may be shadowed
+            fn upper(text) {
+                return text;
+            }
+        "#),
+    ]));
+
+    exec_with(&mut ctx, r#"
+        import "strings" as strings;
+
+        let size = strings.size;
+        let doubled = strings.doubled;
+    "#);
+
+    assert_eq!(Val::Num(3), ctx.scope.get_val("size"));
+    assert_eq!(Val::Num(6), ctx.scope.get_val("doubled"));
+}
+
+
+#[test]
+fn loader_errors() {
+    let mut ctx = ExecContext::new();
+
+    let err = exec_error(&mut ctx, r#"import "util" as util;"#);
+    assert_eq!("'util': no module loader", err.description);
+
+    ctx.set_module_loader(modules(&[("broken", "let = 1;")]));
+
+    let err = exec_error(&mut ctx, r#"import "util" as util;"#);
+    assert_eq!("'util': module not found", err.description);
+
+    let err = exec_error(&mut ctx, r#"import "broken" as broken;"#);
+    assert_eq!("broken", *err.loc.filename);
+}
+
+
+#[test]
+fn file_loader() {
+    let dir = env::temp_dir().join(format!("calc_modules_{}", std::process::id()));
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(dir.join("lib/util.calc"), UTIL).unwrap();
+    fs::write(dir.join("lib/main.calc"), r#"from "util.calc" import add; let x = add(1, 1);"#).unwrap();
+
+    let mut engine = Engine::new();
+    engine.set_module_loader(FileLoader::new(&dir));
+
+    let r = engine.run_file(dir.join("lib/main.calc"));
+    let x = engine.get_global::<i32>("x");
+    let err = engine.eval(r#"import "util.calc" as util;"#).unwrap_err();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(Ok(Val::None), r);
+    assert_eq!(Ok(12), x);
+
+    // scripts that don't come from files import relative to the root
+    assert!(err.description.starts_with("'util.calc': can't find module"));
+}
@@ -0,0 +1,197 @@
+use compiler::*;
+use super::test_execute::*;
+
+
+struct Account {
+    owner: String,
+    balance: i32,
+    limit: i32,
+}
+
+
+impl HostObject for Account {
+    fn type_name(&self) -> &str {
+        "Account"
+    }
+
+    fn get(&self, name: &str) -> Result<Val, String> {
+        match name {
+            "owner" => Ok(self.owner.as_str().into_val()),
+            "limit" => Ok(Val::Num(self.limit)),
+            _ => Err(String::from("no such property")),
+        }
+    }
+
+    fn set(&mut self, name: &str, val: Val) -> Result<(), String> {
+        match name {
+            "limit" => {
+                self.limit = i32::from_val(&val)?;
+                Ok(())
+            }
+            _ => Err(String::from("property is read-only or missing")),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Val>) -> Result<Val, String> {
+        match name {
+            "balance" => {
+                <()>::from_args(&args)?;
+                Ok(Val::Num(self.balance))
+            }
+            "withdraw" => {
+                let (amount,) = <(i32,)>::from_args(&args)?;
+
+                if amount > self.limit {
+                    return Err(format!("{} is over the limit", amount));
+                }
+
+                self.balance -= amount;
+                Ok(Val::Num(self.balance))
+            }
+            "limit_of" => {
+                let (other,) = <(Val,)>::from_args(&args)?;
+                let other = other.as_object().ok_or_else(|| String::from("object expected"))?;
+
+                other.get("limit")
+            }
+            _ => Err(String::from("no such method")),
+        }
+    }
+}
+
+
+fn account_ctx() -> ExecContext {
+    let mut ctx = ExecContext::new();
+    let acct = Account {
+        owner: String::from("bob"),
+        balance: 100,
+        limit: 50,
+    };
+
+    ctx.set_var("acct", Val::new_object(acct));
+    ctx
+}
+
+
+#[test]
+fn properties() {
+    let mut ctx = account_ctx();
+
+    exec_with(&mut ctx, r#"
+        let owner = acct.owner;
+        acct.limit = acct.limit + 10;
+    "#);
+
+    assert_eq!("bob", ctx.scope.get_val("owner").as_str().unwrap());
+
+    let limit = ctx.scope.get_val("acct").as_object().unwrap()
+        .with(|acct: &mut Account| acct.limit);
+    assert_eq!(Some(60), limit);
+}
+
+
+#[test]
+fn methods() {
+    let mut ctx = account_ctx();
+
+    exec_with(&mut ctx, r#"
+        fn pay(a, amount) {
+            return a.withdraw(amount);
+        }
+
+        pay(acct, 30);
+        let left = acct.balance();
+    "#);
+
+    assert_eq!(Val::Num(70), ctx.scope.get_val("left"));
+    assert_eq!("object Account", ctx.scope.get_val("acct").to_string());
+}
+
+
+#[test]
+fn member_errors() {
+    let mut ctx = account_ctx();
+
+    let err = exec_error(&mut ctx, "acct.withdraw(80);");
+    assert_eq!("'Account.withdraw': 80 is over the limit", err.description);
+
+    let err = exec_error(&mut ctx, r#"acct.withdraw("all");"#);
+    assert_eq!(r#"'Account.withdraw': argument 1: type mismatch, number expected, found '"all"'"#, err.description);
+
+    // the object is locked while its method runs
+    let err = exec_error(&mut ctx, "acct.withdraw(acct);");
+    assert_eq!("'Account.withdraw': argument 1: type mismatch, number expected, found 'object Account'", err.description);
+
+    let err = exec_error(&mut ctx, "acct.balance(1);");
+    assert_eq!("'Account.balance': wrong arguments count, expected 0 args", err.description);
+
+    let err = exec_error(&mut ctx, r#"acct.owner = "eve";"#);
+    assert_eq!("'Account.owner': property is read-only or missing", err.description);
+
+    let err = exec_error(&mut ctx, "acct.missing;");
+    assert_eq!("'Account.missing': no such property", err.description);
+
+    let err = exec_error(&mut ctx, "let x = 5; x.foo();");
+    assert_eq!("'foo': type mismatch, object expected, found '5'", err.description);
+}
+
+
+#[test]
+fn method_accesses_own_object() {
+    let mut ctx = account_ctx();
+    ctx.set_var("other", Val::new_object(Account {
+        owner: String::from("eve"),
+        balance: 0,
+        limit: 10,
+    }));
+
+    exec_with(&mut ctx, "let limit = acct.limit_of(other);");
+    assert_eq!(Val::Num(10), ctx.scope.get_val("limit"));
+
+    // it used to panic, or to deadlock with the `sync` feature
+    let err = exec_error(&mut ctx, "acct.limit_of(acct);");
+    assert_eq!("'Account.limit_of': object is already in use by its method", err.description);
+
+    exec_with(&mut ctx, "limit = other.limit_of(acct);");
+    assert_eq!(Val::Num(50), ctx.scope.get_val("limit"));
+}
+
+
+#[test]
+fn error_fields() {
+    let ctx = exec(r#"
+        let msg = 0;
+        let line = 0;
+        let file = 0;
+
+        try {
+            throw "oops";
+        } catch (e) {
+            msg = e.message;
+            line = e.line;
+            file = e.file;
+        }
+    "#);
+
+    assert_eq!("oops", ctx.scope.get_val("msg").as_str().unwrap());
+    assert_eq!(Val::Num(7), ctx.scope.get_val("line"));
+    assert_eq!("<test>", ctx.scope.get_val("file").as_str().unwrap());
+}
+
+
+#[test]
+fn downcast_other_type() {
+    let ctx = account_ctx();
+    let acct = ctx.scope.get_val("acct");
+
+    assert_eq!(None, acct.as_object().unwrap().with(|_: &mut Other| ()));
+}
+
+
+struct Other;
+
+impl HostObject for Other {
+    fn type_name(&self) -> &str {
+        "Other"
+    }
+}
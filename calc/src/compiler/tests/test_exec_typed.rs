@@ -0,0 +1,114 @@
+use compiler::*;
+use super::test_execute::*;
+
+
+#[test]
+fn typed_args() {
+    let mut ctx = ExecContext::new();
+
+    ctx.decl_typed_func("add", |a: i32, b: i32| a + b);
+    ctx.decl_typed_func("repeat", |s: String, n: usize| s.repeat(n));
+
+    exec_with(&mut ctx, r#"
+        let x = add(2, 3);
+        let s = repeat("ab", 3);
+    "#);
+
+    assert_eq!(Val::Num(5), ctx.scope.get_val("x"));
+    assert_eq!("ababab", ctx.scope.get_val("s").as_str().unwrap());
+}
+
+
+#[test]
+fn typed_result() {
+    let mut ctx = ExecContext::new();
+
+    ctx.decl_typed_func("div", |a: i32, b: i32| -> Result<i32, String> {
+        a.checked_div(b).ok_or_else(|| String::from("division by zero"))
+    });
+
+    exec_with(&mut ctx, r#"
+        let x = div(10, 2);
+    "#);
+
+    assert_eq!(Val::Num(5), ctx.scope.get_val("x"));
+
+    let err = exec_error(&mut ctx, "div(1, 0);");
+    assert_eq!("'div': division by zero", err.description);
+}
+
+
+#[test]
+fn conversion_errors() {
+    let mut ctx = ExecContext::new();
+
+    ctx.decl_typed_func("add", |a: i32, b: i32| a + b);
+    ctx.decl_typed_func("repeat", |s: String, n: usize| s.repeat(n));
+
+    let err = exec_error(&mut ctx, r#"add(1, "2");"#);
+    assert_eq!(r#"'add': argument 2: type mismatch, number expected, found '"2"'"#, err.description);
+
+    let err = exec_error(&mut ctx, r#"repeat("a", 0 - 1);"#);
+    assert_eq!("'repeat': argument 2: non-negative number expected, found '-1'", err.description);
+
+    let err = exec_error(&mut ctx, "add(1);");
+    assert_eq!("wrong arguments count, expected 2 args", err.description);
+}
+
+
+#[test]
+fn optional_args() {
+    let mut ctx = ExecContext::new();
+
+    ctx.decl_typed_func("inc", |a: i32, step: Option<i32>| a + step.unwrap_or(1));
+
+    exec_with(&mut ctx, r#"
+        let x = inc(1);
+        let y = inc(1, 10);
+    "#);
+
+    assert_eq!(Val::Num(2), ctx.scope.get_val("x"));
+    assert_eq!(Val::Num(11), ctx.scope.get_val("y"));
+
+    let err = exec_error(&mut ctx, "inc();");
+    assert_eq!("wrong arguments count, expected 1 to 2 args", err.description);
+}
+
+
+#[test]
+fn option_return() {
+    let mut ctx = ExecContext::new();
+
+    ctx.decl_typed_func("half", |a: i32| if a % 2 == 0 { Some(a / 2) } else { None });
+
+    exec_with(&mut ctx, r#"
+        let x = half(4);
+        let y = half(3);
+    "#);
+
+    assert_eq!(Val::Num(2), ctx.scope.get_val("x"));
+    assert_eq!(Val::None, ctx.scope.get_val("y"));
+}
+
+
+#[test]
+fn list_conversions() {
+    let mut ctx = ExecContext::new();
+
+    ctx.decl_typed_func("range", |n: i32| (0..n).collect::<Vec<_>>());
+    ctx.decl_typed_func("sum", |items: Vec<i32>| items.iter().sum::<i32>());
+    ctx.decl_typed_func("divmod", |a: i32, b: i32| (a / b, a % b));
+
+    exec_with(&mut ctx, r#"
+        let r = range(3);
+        let s = sum(range(5));
+        let d = divmod(7, 2);
+    "#);
+
+    assert_eq!(Val::new_list(vec![Val::Num(0), Val::Num(1), Val::Num(2)]), ctx.scope.get_val("r"));
+    assert_eq!(Val::Num(10), ctx.scope.get_val("s"));
+    assert_eq!("[3, 1]", ctx.scope.get_val("d").to_string());
+
+    let err = exec_error(&mut ctx, "sum(1);");
+    assert_eq!("'sum': argument 1: type mismatch, list expected, found '1'", err.description);
+}
@@ -23,7 +23,40 @@ pub fn exec(text: &str) -> ExecContext {
     exec_with(&mut ctx, text);
 
     // return context so it could be checked for results
-    return ctx;
+    ctx
+}
+
+
+/// Outcome of the program, for tests that check how it fails.
+pub fn exec_result(ctx: &mut ExecContext, text: &str) -> Result<Val, Error> {
+    let prog = compiler::parse(text, "<test>")
+        .expect("parse should not fail");
+
+    compiler::execute(ctx, &prog)
+        .map_err(|err| err.to_error())
+}
+
+
+pub fn exec_error(ctx: &mut ExecContext, text: &str) -> Error {
+    exec_result(ctx, text)
+        .expect_err("program was expected to fail")
+}
+
+
+/// Context with the standard library registered.
+pub fn stdlib_ctx() -> ExecContext {
+    let mut ctx = ExecContext::new();
+    stdlib::register(&mut ctx);
+    ctx
+}
+
+
+/// Value of the expression, or description of the error.
+pub fn eval(ctx: &mut ExecContext, text: &str) -> Result<Val, String> {
+    let prog = compiler::parse_expr(text, "<test>").unwrap();
+
+    compiler::execute(ctx, &prog)
+        .map_err(|exc| exc.to_error().description)
 }
 
 
@@ -156,3 +189,18 @@ fn add_mismatched_types() {
     "#, "type mismatch foo 5");
 }
 
+
+
+#[test]
+fn float_arithmetic() {
+    let ctx = exec(r#"
+        let x = 1.5 * 2;    // 3.0
+        let y = 7 / 2.0;    // 3.5
+        let z = 1 + 2;
+    "#);
+
+    assert_eq!(Val::Float(3.0), ctx.scope.get_val("x"));
+    assert_eq!(Val::Float(3.5), ctx.scope.get_val("y"));
+    assert_eq!(Val::Num(3), ctx.scope.get_val("z"));
+    assert_eq!("3.0", ctx.scope.get_val("x").to_string());
+}
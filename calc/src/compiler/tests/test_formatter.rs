@@ -0,0 +1,164 @@
+use compiler;
+
+
+fn fmt(text: &str) -> String {
+    compiler::format(text, "<test>").expect("text should be formatted")
+}
+
+
+/// Debug output of the `AST` without locations.
+fn shape(text: &str) -> String {
+    let ast = format!("{:?}", compiler::parse(text, "<test>").unwrap());
+    let mut r = String::new();
+    let mut rest = ast.as_str();
+
+    while let Some(pos) = rest.find("Loc {") {
+        r.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        rest = &rest[rest.find('}').unwrap() + 1..];
+    }
+
+    r + rest
+}
+
+
+/// Formatting is stable and keeps the meaning.
+fn check(text: &str, expected: &str) {
+    let formatted = fmt(text);
+
+    assert_eq!(expected, formatted);
+    assert_eq!(formatted, fmt(&formatted), "formatting should be idempotent");
+    assert_eq!(shape(text), shape(&formatted), "formatting should keep the AST");
+}
+
+
+#[test]
+fn layout() {
+    check(
+        "let x=1+2*3;fn   f(a,b){let s=a+b;return s;}\n\n\n\nlet y = f( x,[1,2] [0] );\nfor i in range(3) { y=y+i; }",
+"let x = 1 + 2 * 3;
+
+fn f(a, b) {
+    let s = a + b;
+    return s;
+}
+
+let y = f(x, [1, 2][0]);
+for i in range(3) {
+    y = y + i;
+}
+");
+}
+
+
+#[test]
+fn statements() {
+    check(r#"
+import "lib/util.calc" as util;
+from "math.calc" import sqrt,pow;
+fn gen() { yield 1; }
+fn nothing() {}
+try { throw "x\"y\n"; } catch (e) { obj.count = obj.items[0]; } finally { obj.items[1] = {"a": 1.5, "b c": []}; }
+try { x.reset(); } finally {}
+"#,
+r#"import "lib/util.calc" as util;
+from "math.calc" import sqrt, pow;
+
+fn gen() {
+    yield 1;
+}
+
+fn nothing() {}
+
+try {
+    throw "x\"y\n";
+} catch (e) {
+    obj.count = obj.items[0];
+} finally {
+    obj.items[1] = {"a": 1.5, "b c": []};
+}
+try {
+    x.reset();
+} finally {}
+"#);
+}
+
+
+#[test]
+fn parentheses() {
+    check(
+        "let a = (1 + 2) * (3 - (4 - 5)) - (6 + 7) + 8 * (9 % 2);\nlet b = (a + 1).foo(2)[(a)];\nlet c = 2.50 + 100000000000000000000.0;",
+"let a = (1 + 2) * (3 - (4 - 5)) - (6 + 7) + 8 * (9 % 2);
+let b = (a + 1).foo(2)[a];
+let c = 2.5 + 100000000000000000000.0;
+");
+}
+
+
+#[test]
+fn comments() {
+    check(r#"
+// header
+
+
+// about x
+let x = 1;   // trailing
+fn f(a) {   // opening
+    // inside
+    return a;  // result
+
+    // at the end
+}
+// before y
+let y = f(
+  // in the args
+  x);
+// the last one
+"#,
+"// header
+
+// about x
+let x = 1; // trailing
+
+fn f(a) {
+    // opening
+    // inside
+    return a; // result
+
+    // at the end
+}
+
+// before y
+let y = f(x);
+// in the args
+// the last one
+");
+}
+
+
+#[test]
+fn empty_block_with_comment() {
+    check("fn todo() {\n// nothing yet\n}\nlet x = 1;",
+"fn todo() {
+    // nothing yet
+}
+
+let x = 1;
+");
+
+    check("", "");
+    check("// only\n", "// only\n");
+}
+
+
+#[test]
+fn syntax_errors() {
+    let err = compiler::format("let x = ;", "<test>").unwrap_err();
+    assert_eq!(9, err.loc.col);
+
+    let err = compiler::format("let u = \"x\\ry\";", "<test>").unwrap_err();
+    assert_eq!(r"'\r': unknown escape sequence", err.description);
+
+    let err = compiler::format("let s = \"", "<test>").unwrap_err();
+    assert_eq!("unterminated string literal", err.description);
+}
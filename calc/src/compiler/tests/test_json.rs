@@ -0,0 +1,193 @@
+use compiler;
+use compiler::*;
+
+
+const PROGRAM: &str = "\
+from \"util\" import a, b;
+import \"lib\" as lib;
+
+let m = {\"key\": \"line\\n\\\"quoted\\\" ё\", \"n\": [1, 2.5, 0.1]};
+m[\"n\"][0] = m.key;
+m.obj = lib.make(1, 2) % 3;
+
+fn gen(n, step) {
+    for i in range(n) {
+        yield i * step;
+    }
+}
+
+try {
+    throw \"x\";
+} catch (e) {
+    x = e;
+} finally {
+    return (1 - 2) / 3;
+}
+
+try { } finally { }
+";
+
+
+fn round_trip(ast: &AST) -> AST {
+    let text = compiler::ast_to_json(ast).to_string();
+    let json = Json::parse(&text).expect("JSON should be parsed");
+    compiler::ast_from_json(&json).expect("AST should be loaded")
+}
+
+
+fn load_error(text: &str) -> String {
+    let json = Json::parse(text).expect("JSON should be parsed");
+    compiler::ast_from_json(&json).expect_err("AST should not be loaded")
+}
+
+
+#[test]
+fn json_values() {
+    let text = r#"{"a": [1, -2, 0.5, 1e3, true, false, null], "b": "q\"\\\/\n\u00e9\ud83d\ude00", "c": {}}"#;
+    let json = Json::parse(text).unwrap();
+
+    assert_eq!(json.get("a"), Some(&Json::Array(vec![
+        Json::Int(1), Json::Int(-2), Json::Float(0.5), Json::Float(1000.0),
+        Json::Bool(true), Json::Bool(false), Json::Null,
+    ])));
+    assert_eq!(json.get("b").and_then(Json::as_str), Some("q\"\\/\né😀"));
+    assert_eq!(json.get("c"), Some(&Json::Object(Vec::new())));
+
+    // writing and parsing again gives the same value
+    assert_eq!(Json::parse(&json.to_string()).unwrap(), json);
+    assert_eq!(Json::parse(&json.pretty()).unwrap(), json);
+
+    assert_eq!(Json::Str(String::from("\u{1}\t")).to_string(), r#""\u0001\t""#);
+    assert_eq!(Json::object(vec![("a", Json::Array(vec![Json::Int(1)]))]).pretty(), "{\n  \"a\": [\n    1\n  ]\n}");
+}
+
+
+#[test]
+fn json_errors() {
+    for text in &["", "[1,", "{\"a\" 1}", "[1] 2", "\"abc", "nul", "1-2", "\"\\x\""] {
+        assert!(Json::parse(text).is_err(), "'{}' should not be parsed", text);
+    }
+}
+
+
+#[test]
+fn round_trip_program() {
+    let ast = compiler::parse(PROGRAM, "test.calc").unwrap();
+    assert_eq!(round_trip(&ast), ast);
+
+    let expr = compiler::parse_expr("[1.5, 0.1 + 0.2, 123456789.125, 0.000001]", "<expr>").unwrap();
+    assert_eq!(round_trip(&expr), expr);
+}
+
+
+#[test]
+fn dump_layout() {
+    let ast = compiler::parse_expr("f(x)", "a.calc").unwrap();
+
+    assert_eq!(compiler::ast_to_json(&ast).to_string(), concat!(
+        r#"{"version":1,"ast":{"type":"Call","loc":{"file":"a.calc","line":1,"col":1,"start":0,"end":1},"name":"f","#,
+        r#""args":[{"type":"Var","loc":{"file":"a.calc","line":1,"col":3,"start":2,"end":3},"name":"x"}]}}"#,
+    ));
+}
+
+
+#[test]
+fn execute_loaded() {
+    let mut engine = Engine::new();
+
+    let prog = engine.parse("fn f(n) { yield n; yield n * 2; } let r = 0; for i in f(3) { r = r + i; }", "<test>").unwrap();
+    assert_eq!(Ok(Val::None), engine.execute(&round_trip(&prog)));
+
+    let expr = engine.parse("r * 2", "<test>").unwrap();
+    assert_eq!(Ok(Val::Num(18)), engine.execute(&round_trip(&expr)));
+}
+
+
+#[test]
+fn load_errors() {
+    assert_eq!(load_error(r#"{"ast": 1}"#), "'version': member expected");
+    assert_eq!(load_error(r#"{"version": 2, "ast": 1}"#), "'version': 1 expected");
+    assert_eq!(load_error(r#"{"version": 1, "ast": {}}"#), "ast: 'type': member expected");
+
+    let loc = r#"{"file": "a", "line": 1, "col": 1, "start": 0, "end": 1}"#;
+
+    let text = format!(r#"{{"version": 1, "ast": {{"type": "Block", "loc": {0}, "end": {0}, "body": [{{"type": "Num", "loc": {0}, "val": 1.5}}]}}}}"#, loc);
+    assert_eq!(load_error(&text), "ast.body[0].val: integer expected");
+
+    let text = format!(r#"{{"version": 1, "ast": {{"type": "BinOp", "loc": {0}, "op": "^", "left": null, "right": null}}}}"#, loc);
+    assert_eq!(load_error(&text), "ast.op: '^': unknown operator");
+
+    let text = format!(r#"{{"version": 1, "ast": {{"type": "Neg", "loc": {0}}}}}"#, loc);
+    assert_eq!(load_error(&text), "ast.type: 'Neg': unknown node type");
+
+    let text = r#"{"version": 1, "ast": {"type": "Var", "loc": {"file": "a", "line": -1, "col": 1, "start": 0, "end": 1}, "name": "x"}}"#;
+    assert_eq!(load_error(text), "ast.loc.line: non-negative integer expected");
+}
+
+
+#[test]
+fn func_arg_locs() {
+    let ast = compiler::parse("fn f(a, b) {}", "a.calc").unwrap();
+    let json = compiler::ast_to_json(&ast).to_string();
+    assert!(json.contains(r#""args":["a","b"],"arg_locs":[{"file":"a.calc","line":1,"col":6,"start":5,"end":6},"#), "{}", json);
+
+    // older documents have no locs of the arguments
+    let loc = r#"{"file": "a", "line": 1, "col": 4, "start": 3, "end": 4}"#;
+    let func = |arg_locs: &str| format!(
+        r#"{{"version": 1, "ast": {{"type": "Func", "loc": {0}, "decl": {{"name": "f", "args": ["a"],{1} "body": {{"type": "Block", "loc": {0}, "end": {0}, "body": []}}, "generator": false}}}}}}"#,
+        loc, arg_locs);
+
+    let json = Json::parse(&func("")).unwrap();
+    match compiler::ast_from_json(&json).unwrap() {
+        AST::Func{ ref loc, ref decl } => assert_eq!(vec![loc.clone()], decl.arg_locs),
+        ast => panic!("function expected, found {:?}", ast),
+    }
+
+    assert_eq!(load_error(&func(r#" "arg_locs": [],"#)), "ast.decl.arg_locs: 1 locs expected");
+}
+
+
+#[test]
+fn nesting_limit() {
+    let err = Json::parse(&"[".repeat(200000)).unwrap_err();
+    assert_eq!(err, "offset 512: nesting is too deep");
+    assert!(Json::parse(&("[".repeat(512) + &"]".repeat(512))).is_ok());
+
+    let nested = |depth| {
+        let loc = Json::object(vec![
+            ("file", Json::from("a")), ("line", Json::Int(1)), ("col", Json::Int(1)),
+            ("start", Json::Int(0)), ("end", Json::Int(1)),
+        ]);
+        let mut node = Json::object(vec![("type", Json::from("Var")), ("loc", loc.clone()), ("name", Json::from("x"))]);
+
+        for _ in 1..depth {
+            node = Json::object(vec![("type", Json::from("Throw")), ("loc", loc.clone()), ("val", node)]);
+        }
+        Json::object(vec![("version", Json::Int(AST_JSON_VERSION)), ("ast", node)])
+    };
+
+    assert!(compiler::ast_from_json(&nested(MAX_AST_DEPTH)).is_ok());
+
+    let err = compiler::ast_from_json(&nested(MAX_AST_DEPTH + 1)).unwrap_err();
+    assert!(err.starts_with("ast.val.val."), "{}", err);
+    assert!(err.ends_with(".val: nesting is too deep"), "{}", err);
+}
+
+
+#[test]
+fn round_trip_deepest_program() {
+    // every tree the parser accepts loads back
+    let terms: Vec<String> = (0..MAX_AST_DEPTH - 2).map(|i| i.to_string()).collect();
+    let text = format!("let x = {};", terms.join(" + "));
+    let ast = compiler::parse(&text, "<test>").unwrap();
+
+    let json = Json::parse(&compiler::ast_to_json(&ast).to_string()).unwrap();
+    assert_eq!(compiler::ast_from_json(&json), Ok(ast));
+
+    // map items take the most levels of the JSON document
+    let text = format!("let m = {}1{};", "{\"k\": ".repeat(MAX_AST_DEPTH - 3), "}".repeat(MAX_AST_DEPTH - 3));
+    let ast = compiler::parse(&text, "<test>").unwrap();
+
+    let json = Json::parse(&compiler::ast_to_json(&ast).to_string()).unwrap();
+    assert_eq!(compiler::ast_from_json(&json), Ok(ast));
+}
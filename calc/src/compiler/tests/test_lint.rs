@@ -0,0 +1,222 @@
+use compiler;
+use compiler::*;
+
+
+fn lint_with(linter: &Linter, text: &str) -> Vec<(Lint, i32, String)> {
+    let ast = compiler::parse(text, "<test>").unwrap();
+
+    linter.check(&ast).into_iter()
+        .map(|warn| (warn.lint, warn.loc.line, warn.description))
+        .collect()
+}
+
+
+fn lint(text: &str) -> Vec<(Lint, i32, String)> {
+    lint_with(&Linter::new(), text)
+}
+
+
+#[test]
+fn clean_code() {
+    let warnings = lint(r#"
+        fn fact(n) {
+            return n * fact(n - 1);
+        }
+
+        fn main(args) {
+            let total = 0;
+            for arg in args {
+                total = total + helper(arg);
+            }
+            return total;
+        }
+
+        fn helper(x) {
+            return fact(x);
+        }
+
+        main([1, 2]);
+    "#);
+
+    assert_eq!(Vec::<(Lint, i32, String)>::new(), warnings);
+}
+
+
+#[test]
+fn unused_names() {
+    let warnings = lint(r#"
+        fn f(a, b, _c) {
+            let x = a;
+            let _y = 1;
+            return 0;
+        }
+
+        fn g() {
+            return g();
+        }
+
+        f(1, 2, 3);
+    "#);
+
+    assert_eq!(vec![
+        (Lint::UnusedArgument, 2, String::from("'b': argument is never read")),
+        (Lint::UnusedVariable, 3, String::from("'x': variable is never read")),
+        (Lint::UnusedFunction, 8, String::from("'g': function is never called")),
+    ], warnings);
+}
+
+
+#[test]
+fn unused_argument_location() {
+    let ast = compiler::parse("fn f(a,\n     b) {\n  return a;\n}\nf(1, 2);\n", "<test>").unwrap();
+    let warnings = Linter::new().check(&ast);
+
+    assert_eq!(1, warnings.len());
+    assert_eq!(Lint::UnusedArgument, warnings[0].lint);
+    assert_eq!((2, 6), (warnings[0].loc.line, warnings[0].loc.col));
+
+    // `calc:allow` applies to the line of the argument
+    let text = "fn f(a,\n  b) {  // calc:allow(W002)\n  return a;\n}\nf(1, 2);\n";
+    let linter = Linter::new().allow_comments(text).unwrap();
+    assert_eq!(Vec::<(Lint, i32, String)>::new(), lint_with(&linter, text));
+}
+
+
+#[test]
+fn unreachable_code() {
+    let warnings = lint(r#"
+        fn f(x) {
+            for i in x {
+                throw "no";
+                x = 1;
+            }
+            return x;
+            x = 2;
+            x = 3;
+        }
+
+        f(1);
+    "#);
+
+    assert_eq!(vec![
+        (Lint::UnreachableCode, 5, String::from("unreachable statement")),
+        (Lint::UnreachableCode, 8, String::from("unreachable statement")),
+    ], warnings);
+}
+
+
+#[test]
+fn shadowed_variable() {
+    // see `capture_vals_dynamic` in test_exec_functions
+    let text = r#"
+        let x = 5;
+
+        fn foo() {
+            let y = x;
+            let x = 10;
+            return x * y;
+        }
+
+        let r = foo();
+    "#;
+
+    assert_eq!(vec![
+        (Lint::ShadowedVariable, 6, String::from("'x': variable shadows an outer one")),
+        (Lint::UnusedVariable, 10, String::from("'r': variable is never read")),
+    ], lint(text));
+
+    let ast = compiler::parse(text, "main.calc").unwrap();
+    let warn = &Linter::new().check(&ast)[0];
+
+    assert_eq!(
+"warning[W005]: 'x': variable shadows an outer one
+ --> main.calc:6:17
+  |
+2 |         let x = 5;
+  |             - outer variable declared here
+...
+6 |             let x = 10;
+  |                 ^
+  = note: reads before this declaration refer to the outer variable
+", Renderer::new().source("main.calc", text).render_warning(warn));
+}
+
+
+#[test]
+fn block_scopes() {
+    assert_eq!(vec![
+        (Lint::ShadowedVariable, 4, String::from("'n': variable shadows an outer one")),
+        (Lint::UnusedVariable, 5, String::from("'last': variable is never read")),
+        (Lint::ShadowedVariable, 10, String::from("'n': variable shadows an outer one")),
+    ], lint(r#"
+        let n = 0;
+        for x in [1, 2] {
+            let n = x;
+            let last = n;
+        }
+        try {
+            print(n);
+        } catch (e) {
+            let n = e;
+            print(n);
+        }
+        print(n);
+    "#));
+}
+
+
+#[test]
+fn suppression() {
+    let text = r#"
+        let a = 1;
+        // calc:allow(W001, unused-function)
+        let b = 2;
+        fn f(x) {}  // calc:allow(unused-function)
+        let c = 3;
+        let d = 4;  // calc:allow(W001)
+        let e = 5;
+    "#;
+
+    // a trailing comment applies to its own line only
+    let linter = Linter::new().allow_comments(text).unwrap();
+    assert_eq!(vec![
+        (Lint::UnusedVariable, 2, String::from("'a': variable is never read")),
+        (Lint::UnusedArgument, 5, String::from("'x': argument is never read")),
+        (Lint::UnusedVariable, 6, String::from("'c': variable is never read")),
+        (Lint::UnusedVariable, 8, String::from("'e': variable is never read")),
+    ], lint_with(&linter, text));
+
+    let linter = Linter::new().allow(Lint::UnusedVariable).allow(Lint::UnusedArgument);
+    assert_eq!(vec![
+        (Lint::UnusedFunction, 5, String::from("'f': function is never called")),
+    ], lint_with(&linter, text));
+
+    let err = Linter::new().allow_comments("// calc:allow(W999)").err();
+    assert_eq!(Some(String::from("line 1: 'W999': unknown lint")), err);
+    assert_eq!(Ok(Lint::ShadowedVariable), "W005".parse());
+    assert_eq!(Ok(Lint::UnreachableCode), "unreachable-code".parse());
+}
+
+
+#[test]
+fn names_used_by_broken_statements() {
+    let (ast, errors) = compiler::parse_recover("let s = 1;\nfn f(x) { return x +; }\nlet t = s +\n", "<test>");
+    assert_eq!(2, errors.len());
+
+    // `s`, `f` and `x` look unused, since the code using them is gone
+    assert_eq!(3, Linter::new().check(&ast).len());
+    assert_eq!(Vec::<Warning>::new(), Linter::new().allow_unused().check(&ast));
+}
+
+
+#[test]
+fn suppression_ignores_strings() {
+    let text = r#"
+        let url = "http://x"; // calc:allow(W001)
+        let s = "// calc:allow(bogus)";
+        s;
+    "#;
+
+    let linter = Linter::new().allow_comments(text).unwrap();
+    assert_eq!(Vec::<(Lint, i32, String)>::new(), lint_with(&linter, text));
+}
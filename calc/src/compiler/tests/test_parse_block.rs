@@ -39,6 +39,29 @@ fn assign_statement() {
 }
 
 
+#[test]
+fn set_prop_statement() {
+    let block = parse_it("acct.limit = 5 * 2; acct.reset(); items[0] = 1;");
+
+    check_block(
+        &block, &[
+            Box::new(|st| check_set_prop(
+                st, "limit",
+                |obj| check_var(obj, "acct"),
+                |val| check_op(val, '*', |l| check_num(l, 5), |r| check_num(r, 2)))),
+            Box::new(|st| check_method(st, "reset", |obj| check_var(obj, "acct"), &[])),
+            Box::new(|st| match *st {
+                AST::SetIndex{ ref obj, ref index, ref val, .. } => {
+                    check_var(obj, "items");
+                    check_num(index, 0);
+                    check_num(val, 1);
+                }
+                _ => panic!("SetIndex type expected: {:#?}", st),
+            }),
+        ]);
+}
+
+
 #[test]
 fn fn_statement() {
 	let block = parse_it("fn foo() {}");
@@ -73,3 +96,285 @@ fn function_body () {
 }
 
 
+
+
+#[test]
+fn yield_statement() {
+	let block = parse_it(r#"
+        fn gen(a) {
+            yield a + 1;
+        }
+    "#);
+
+    check_block(
+        &block, &[Box::new(
+            |expr| check_func(
+                expr, "gen", &["a"],
+                |body| check_block(body, &[Box::new(
+                    |p| check_yield(
+                        p, |val| check_op(
+                            val, '+',
+                            |l| check_var(l, "a"),
+                            |r| check_num(r, 1)))),
+                ]))),
+        ]);
+}
+
+
+#[test]
+fn generator_flag() {
+	let block = parse_it(r#"
+        fn gen() {
+            for x in other() {
+                yield x;
+            }
+        }
+
+        fn outer() {
+            fn inner() {
+                yield 1;
+            }
+            return inner;
+        }
+    "#);
+
+    if let AST::Block{ ref body, .. } = block {
+        let is_generator = |st: &AST| match *st {
+            AST::Func{ ref decl, .. } => decl.generator,
+            _ => panic!("Func type expected: {:#?}", st),
+        };
+
+        assert!(is_generator(&body[0]));
+        assert!(!is_generator(&body[1]));
+    } else {
+        panic!("Block type expected: {:#?}", block);
+    }
+}
+
+
+#[test]
+fn for_statement() {
+	let block = parse_it(r#"
+        for x in gen(5) {
+            foo(x);
+        }
+    "#);
+
+    check_block(
+        &block, &[Box::new(
+            |expr| check_for(
+                expr, "x",
+                |iter| check_call(iter, "gen", &[Box::new(|p| check_num(p, 5))]),
+                |body| check_block(body, &[Box::new(
+                    |p| check_call(p, "foo", &[Box::new(|p| check_var(p, "x"))])),
+                ]))),
+        ]);
+}
+
+
+#[test]
+fn throw_statement() {
+	let block = parse_it(r#" throw "oops"; "#);
+
+    check_block(
+        &block, &[
+            Box::new(|st| check_throw(st, |p| check_str(p, "oops")))
+        ]);
+}
+
+
+#[test]
+fn try_statement() {
+	let block = parse_it(r#"
+        try {
+            foo();
+        } catch (e) {
+            bar(e);
+        } finally {
+            baz();
+        }
+
+        try {} catch (e) {}
+        try {} finally {}
+    "#);
+
+    let empty = |b: &AST| check_block(b, &[]);
+
+    check_block(
+        &block, &[
+            Box::new(|st| check_try(
+                st,
+                |b| check_block(b, &[Box::new(|p| check_call(p, "foo", &[]))]),
+                Some(("e", |b: &AST| check_block(b, &[
+                    Box::new(|p| check_call(p, "bar", &[Box::new(|e| check_var(e, "e"))]))]))),
+                Some(|b: &AST| check_block(b, &[Box::new(|p| check_call(p, "baz", &[]))])))),
+            Box::new(move |st| check_try(st, empty, Some(("e", empty)), None::<fn(&AST)>)),
+            Box::new(move |st| check_try(st, empty, None::<(&str, fn(&AST))>, Some(empty))),
+        ]);
+}
+
+
+#[test]
+fn import_statements() {
+    let block = parse_it(r#"
+        import "lib/util.calc" as util;
+        from "math.calc" import sqrt, pow;
+    "#);
+
+    if let AST::Block{ ref body, .. } = block {
+        assert_eq!(AST::Import{
+            loc: Loc::new("<test>", 2).at(9, 9, 15),
+            path: String::from("lib/util.calc"),
+            alias: String::from("util"),
+        }, body[0]);
+
+        assert_eq!(AST::FromImport{
+            loc: Loc::new("<test>", 3).at(9, 49, 53),
+            path: String::from("math.calc"),
+            names: vec![String::from("sqrt"), String::from("pow")],
+        }, body[1]);
+    } else {
+        panic!("Block type expected: {:#?}", block);
+    }
+
+    let r = compiler::parse("import util;", "<test>");
+    assert!(r.unwrap_err().description.contains("module path expected"));
+}
+
+
+#[test]
+fn try_without_clauses() {
+    let r = compiler::parse("try {} foo();", "<test>");
+
+    assert!(r.unwrap_err().description.contains("expected keyword 'finally'"));
+}
+
+
+#[test]
+fn incomplete_input() {
+    assert!(compiler::is_incomplete("fn f(x) {"));
+    assert!(compiler::is_incomplete("let x = max(1,\n 2"));
+    assert!(compiler::is_incomplete("let l = [1, \"]\""));
+    assert!(!compiler::is_incomplete("fn f(x) { return x; }"));
+    assert!(!compiler::is_incomplete("let x = 1 +"));
+    assert!(!compiler::is_incomplete("}"));
+}
+
+
+fn parse_recover(text: &str) -> (Vec<String>, Vec<(i32, String)>) {
+    let (block, errors) = compiler::parse_recover(text, "<test>");
+
+    let names = match block {
+        AST::Block{ body, .. } => body.iter()
+            .map(|st| match *st {
+                AST::DeclVar{ ref name, .. } => name.clone(),
+                AST::Func{ ref decl, .. } => decl.name.clone(),
+                _ => format!("{:?}", st),
+            })
+            .collect(),
+        _ => panic!("Block type expected: {:#?}", block),
+    };
+
+    (names, errors.into_iter().map(|err| (err.loc.line, err.description)).collect())
+}
+
+
+#[test]
+fn recover_statements() {
+    let (names, errors) = parse_recover(r#"
+        let a = 1;
+        let b = ;
+        fn f(x {
+            let y = 1;
+        }
+        let c = {"k": };
+        fn g() {
+            let d = 1 +;
+            let e = 2;
+        }
+        );
+        let h = 3;
+    "#);
+
+    assert_eq!(vec!["a", "g", "h"], names);
+    assert_eq!(vec![
+        (3, String::from("';': unexpected token")),
+        (4, String::from("'{': unexpected token, expected symbol ')'")),
+        (7, String::from("'}': unexpected token")),
+        (9, String::from("';': unexpected token")),
+        (12, String::from("')': unexpected token")),
+    ], errors);
+}
+
+
+#[test]
+fn recover_nested_blocks() {
+    let (block, errors) = compiler::parse_recover("fn f() { let x = ; let y = 2; }", "<test>");
+
+    assert_eq!(1, errors.len());
+    check_block(
+        &block, &[
+            Box::new(|st| match *st {
+                AST::Func{ ref decl, .. } => check_block(
+                    &decl.body, &[
+                        Box::new(|st| check_let(st, "y", |p| check_num(p, 2))),
+                    ]),
+                _ => panic!("Func type expected: {:#?}", st),
+            }),
+        ]);
+}
+
+
+#[test]
+fn recover_stray_braces() {
+    let (names, errors) = parse_recover("let a = 1; } let b = 2; }");
+
+    assert_eq!(vec!["a", "b"], names);
+    assert_eq!(2, errors.len());
+
+    // bad symbols are skipped, the rest of the text is still parsed
+    let (names, errors) = parse_recover("let a = 1 $;\nlet b = 99999999999;\nlet c = # 2;");
+    assert_eq!(vec!["a", "b", "c"], names);
+    assert_eq!(vec![
+        (1, String::from("'$': unexpected symbol")),
+        (2, String::from("'99999999999': integer is too large")),
+        (3, String::from("'#': unexpected symbol")),
+    ], errors);
+
+    // the token in place of a bad one is not reported again
+    let (names, errors) = parse_recover("99999999999;\nlet a = 1;");
+    assert_eq!(vec!["a"], names);
+    assert_eq!(vec![(1, String::from("'99999999999': integer is too large"))], errors);
+
+    // the plain parser still stops at the first error
+    let err = compiler::parse("let a = ; let b = ;", "<test>").unwrap_err();
+    assert_eq!(9, err.loc.col);
+}
+
+
+#[test]
+fn nesting_limit() {
+    // the first operand is the deepest node: block, `let` and one level
+    // per operator above it
+    let sum = |terms: usize| {
+        let terms: Vec<String> = (0..terms).map(|i| i.to_string()).collect();
+        format!("let x = {};\nlet y = 1;", terms.join(" + "))
+    };
+
+    assert!(compiler::parse(&sum(MAX_AST_DEPTH - 2), "<test>").is_ok());
+
+    let err = compiler::parse(&sum(MAX_AST_DEPTH - 1), "<test>").unwrap_err();
+    assert_eq!("nesting is too deep", err.description);
+
+    // the parser stops before its recursion gets too deep
+    let parens = format!("let x = {}1{};", "(".repeat(100000), ")".repeat(100000));
+    let err = compiler::parse(&parens, "<test>").unwrap_err();
+    assert_eq!("nesting is too deep", err.description);
+
+    let (names, errors) = parse_recover(&format!("{}\n{}", parens, sum(1000)));
+    assert_eq!(vec!["y"], names);
+    assert_eq!(vec![
+        (1, String::from("nesting is too deep")),
+        (2, String::from("nesting is too deep")),
+    ], errors);
+}
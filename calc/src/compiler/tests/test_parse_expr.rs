@@ -27,6 +27,14 @@ fn expr_num() {
 }
 
 
+#[test]
+fn expr_float() {
+	let expr = parse_expr("0.25");
+
+	assert_eq!(AST::Float{ loc: Loc::new("<test>", 1).at(1, 0, 4), val: 0.25 }, expr);
+}
+
+
 #[test]
 fn expr_binops() {
 	for op in "+-*/%".chars() {
@@ -128,6 +136,58 @@ fn call_func_with_args() {
 }
 
 
+#[test]
+fn get_prop() {
+    let expr = parse_expr("acct.owner.name + 1");
+
+    check_op(
+        &expr, '+',
+        |l| check_get_prop(l, "name", |obj| check_get_prop(obj, "owner", |p| check_var(p, "acct"))),
+        |r| check_num(r, 1));
+}
+
+
+#[test]
+fn method_call() {
+    let expr = parse_expr("get(1).balance().add(x, 2)");
+
+    check_method(
+        &expr, "add",
+        |obj| check_method(obj, "balance", |p| check_call(p, "get", &[Box::new(|a| check_num(a, 1))]), &[]),
+        &[
+            Box::new(|p| check_var(p, "x")),
+            Box::new(|p| check_num(p, 2)),
+        ]);
+}
+
+
+#[test]
+fn list_map_literals() {
+    let expr = parse_expr(r#"[1, {"a": x, b: 2,}, []][0]"#);
+
+    if let AST::Index{ ref obj, ref index, .. } = expr {
+        check_num(index, 0);
+
+        if let AST::List{ ref items, .. } = **obj {
+            assert_eq!(3, items.len());
+            check_num(&items[0], 1);
+
+            if let AST::Map{ ref items, .. } = items[1] {
+                assert_eq!(vec!["a", "b"], items.iter().map(|item| item.0.as_str()).collect::<Vec<_>>());
+                check_var(&items[0].1, "x");
+                check_num(&items[1].1, 2);
+            } else {
+                panic!("Map type expected: {:#?}", items[1]);
+            }
+        } else {
+            panic!("List type expected: {:#?}", obj);
+        }
+    } else {
+        panic!("Index type expected: {:#?}", expr);
+    }
+}
+
+
 #[test]
 fn string_literal() {
     let expr = parse_expr(r#" "val" "#);
@@ -144,3 +204,19 @@ fn string_literal_escape() {
 }
 
 
+
+
+#[test]
+fn string_literal_errors() {
+    let err = compiler::parse_expr(r#"1 + "a\q""#, "<test>").unwrap_err();
+    assert_eq!(r"'\q': unknown escape sequence", err.description);
+    assert_eq!((5, 4, 9), (err.loc.col, err.loc.start, err.loc.end));
+
+    for text in &[r#"""#, r#""abc"#, r#""abc\"#, r#""abc\""#] {
+        let err = compiler::parse_expr(text, "<test>").unwrap_err();
+        assert_eq!("unterminated string literal", err.description, "{}", text);
+    }
+
+    let err = compiler::parse(r#"let m = {"\r": 1};"#, "<test>").unwrap_err();
+    assert_eq!(r"'\r': unknown escape sequence", err.description);
+}
@@ -0,0 +1,171 @@
+use std::task::Poll;
+use compiler;
+use compiler::*;
+use super::test_execute::*;
+
+
+/// Displayed value of the expression, or description of the error.
+fn eval_str(ctx: &mut ExecContext, text: &str) -> Result<String, String> {
+    eval(ctx, text).map(|val| val.to_string())
+}
+
+
+#[test]
+fn list_functions() {
+    let mut ctx = stdlib_ctx();
+
+    exec_with(&mut ctx, r#"
+        let list = [1, 2];
+        push(list, 3);
+        insert(list, 0, 0);
+        let last = pop(list);
+        let first = remove(list, 0);
+    "#);
+
+    assert_eq!("[1, 2]", ctx.scope.get_val("list").to_string());
+    assert_eq!(Val::Num(3), ctx.scope.get_val("last"));
+    assert_eq!(Val::Num(0), ctx.scope.get_val("first"));
+
+    assert_eq!(Ok(String::from("2")), eval_str(&mut ctx, "len(list)"));
+    assert_eq!(Ok(String::from("3")), eval_str(&mut ctx, r#"len("ёжи")"#));
+    assert_eq!(Err(String::from("'pop': list is empty")), eval_str(&mut ctx, "pop([])"));
+    assert_eq!(Err(String::from("'insert': index 5 is out of range (length 2)")), eval_str(&mut ctx, "insert(list, 5, 0)"));
+    assert_eq!(
+        Err(String::from("'push': argument 1: type mismatch, list expected, found '1'")),
+        eval_str(&mut ctx, "push(1, 2)"));
+}
+
+
+#[test]
+fn map_functions() {
+    let mut ctx = stdlib_ctx();
+
+    exec_with(&mut ctx, r#"
+        let ages = {"b": 2};
+        insert(ages, "a", 1);
+        insert(ages, "c", 3);
+        let c = remove(ages, "c");
+    "#);
+
+    assert_eq!(Ok(String::from(r#"["a", "b"]"#)), eval_str(&mut ctx, "keys(ages)"));
+    assert_eq!(Ok(String::from("[1, 2]")), eval_str(&mut ctx, "values(ages)"));
+    assert_eq!(Val::Num(3), ctx.scope.get_val("c"));
+    assert_eq!(Err(String::from("'remove': 'x': no such key")), eval_str(&mut ctx, r#"remove(ages, "x")"#));
+}
+
+
+#[test]
+fn range_function() {
+    let mut ctx = stdlib_ctx();
+
+    assert_eq!(Ok(String::from("[0, 1, 2]")), eval_str(&mut ctx, "range(3)"));
+    assert_eq!(Ok(String::from("[2, 3]")), eval_str(&mut ctx, "range(2, 4)"));
+    assert_eq!(Ok(String::from("[5, 3, 1]")), eval_str(&mut ctx, "range(5, 0, 0 - 2)"));
+    assert_eq!(Ok(String::from("[]")), eval_str(&mut ctx, "range(0 - 3)"));
+    assert_eq!(Err(String::from("'range': step must not be zero")), eval_str(&mut ctx, "range(1, 2, 0)"));
+
+    ctx.set_memory_limit(Some(64 * 1024));
+    let err = eval_str(&mut ctx, "range(1000000)").unwrap_err();
+    assert!(err.starts_with("memory limit exceeded"), "{}", err);
+}
+
+
+#[test]
+fn higher_order() {
+    let mut ctx = stdlib_ctx();
+
+    exec_with(&mut ctx, r#"
+        fn square(x) {
+            return x * x;
+        }
+
+        fn odd(x) {
+            return x % 2;
+        }
+
+        fn add(a, b) {
+            return a + b;
+        }
+
+        let nums = range(1, 6);
+    "#);
+
+    assert_eq!(Ok(String::from("[1, 4, 9, 16, 25]")), eval_str(&mut ctx, "map(nums, square)"));
+    assert_eq!(Ok(String::from("[1, 3, 5]")), eval_str(&mut ctx, "filter(nums, odd)"));
+    assert_eq!(Ok(String::from("15")), eval_str(&mut ctx, "reduce(nums, add)"));
+    assert_eq!(Ok(String::from("25")), eval_str(&mut ctx, "reduce(nums, add, 10)"));
+    assert_eq!(Ok(String::from(r#"["1", "2"]"#)), eval_str(&mut ctx, "map([1, 2], str)"));
+    assert_eq!(
+        Err(String::from("'reduce': list is empty and there is no initial value")),
+        eval_str(&mut ctx, "reduce([], add)"));
+}
+
+
+#[test]
+fn sorting() {
+    let mut ctx = stdlib_ctx();
+
+    exec_with(&mut ctx, r#"
+        fn desc(a, b) {
+            return b - a;
+        }
+
+        fn by_len(a, b) {
+            return len(a) - len(b);
+        }
+
+        let nums = [3, 1.5, 2];
+    "#);
+
+    assert_eq!(Ok(String::from("None")), eval_str(&mut ctx, "sort(nums)"));
+    assert_eq!(Ok(String::from("[1.5, 2, 3]")), eval_str(&mut ctx, "nums"));
+
+    exec_with(&mut ctx, r#"
+        sort(nums, desc);
+        let words = ["cc", "aa", "b", "ddd"];
+        sort(words, by_len);
+        let mixed = [1, "a"];
+    "#);
+    assert_eq!(Ok(String::from("[3, 2, 1.5]")), eval_str(&mut ctx, "nums"));
+    assert_eq!(Ok(String::from(r#"["b", "cc", "aa", "ddd"]"#)), eval_str(&mut ctx, "words"));
+
+    assert_eq!(Err(String::from(r#"'sort': can't compare '"a"' and '1'"#)), eval_str(&mut ctx, "sort(mixed)"));
+    assert_eq!(Ok(String::from(r#"[1, "a"]"#)), eval_str(&mut ctx, "mixed"));
+}
+
+
+#[test]
+fn callback_errors() {
+    let mut ctx = stdlib_ctx();
+
+    exec_with(&mut ctx, r#"
+        fn fail(x) {
+            throw "bad item";
+        }
+
+        fn one() {
+            return 1;
+        }
+    "#);
+
+    let prog = compiler::parse(r#"
+
+        map([1], fail);
+    "#, "<test>").unwrap();
+    let err = compiler::execute(&mut ctx, &prog).unwrap_err().to_error();
+
+    // error keeps the location of the script function
+    assert_eq!("bad item", err.description);
+    assert_eq!(3, err.loc.line);
+
+    assert_eq!(Err(String::from("wrong arguments count, expected 0 args")), eval_str(&mut ctx, "map([1], one)"));
+    assert_eq!(
+        Err(String::from("'map': type mismatch, function expected, found '5'")),
+        eval_str(&mut ctx, "map([1], 5)"));
+
+    ctx.decl_func("wait", |_args| Poll::Pending);
+    assert_eq!(
+        Err(String::from("'map': native function result is pending inside a callback")),
+        eval_str(&mut ctx, "map([1], wait)"));
+    assert!(!ctx.is_suspended());
+}
@@ -0,0 +1,89 @@
+use compiler::*;
+use super::test_execute::*;
+
+
+#[test]
+fn constants() {
+    let mut ctx = stdlib_ctx();
+
+    exec_with(&mut ctx, r#"
+        let area = PI * 2 * 2;
+    "#);
+
+    assert_eq!(Val::Float(std::f64::consts::PI * 4.0), ctx.scope.get_val("area"));
+    assert_eq!(Ok(Val::Float(std::f64::consts::E)), eval(&mut ctx, "E"));
+
+    let err = exec_error(&mut ctx, "PI = 3;");
+    assert_eq!("'PI': library names are read-only", err.description);
+
+    // but may be shadowed
+    exec_with(&mut ctx, "let E = 2; E = 3;");
+    assert_eq!(Val::Num(3), ctx.scope.get_val("E"));
+    assert_eq!(Ok(Val::Float(std::f64::consts::PI)), eval(&mut ctx, "PI"));
+}
+
+
+#[test]
+fn abs_min_max_pow() {
+    let mut ctx = stdlib_ctx();
+
+    assert_eq!(Ok(Val::Num(5)), eval(&mut ctx, "abs(0 - 5)"));
+    assert_eq!(Ok(Val::Float(2.5)), eval(&mut ctx, "abs(0 - 2.5)"));
+    assert_eq!(Ok(Val::Num(1)), eval(&mut ctx, "min(3, 1, 2)"));
+    assert_eq!(Ok(Val::Float(3.5)), eval(&mut ctx, "max(3, 1, 3.5)"));
+    assert_eq!(Ok(Val::Num(1024)), eval(&mut ctx, "pow(2, 10)"));
+    assert_eq!(Ok(Val::Float(0.5)), eval(&mut ctx, "pow(2, 0 - 1)"));
+    assert_eq!(Ok(Val::Float(3.0)), eval(&mut ctx, "sqrt(9)"));
+}
+
+
+#[test]
+fn rounding() {
+    let mut ctx = stdlib_ctx();
+
+    assert_eq!(Ok(Val::Num(2)), eval(&mut ctx, "floor(2.7)"));
+    assert_eq!(Ok(Val::Num(3)), eval(&mut ctx, "ceil(2.1)"));
+    assert_eq!(Ok(Val::Num(3)), eval(&mut ctx, "round(2.5)"));
+    assert_eq!(Ok(Val::Num(7)), eval(&mut ctx, "round(7)"));
+    assert_eq!(Err(String::from("'floor': '1e20' is out of integer range")), eval(&mut ctx, "floor(pow(10.0, 20))"));
+}
+
+
+#[test]
+fn trigonometry() {
+    let mut ctx = stdlib_ctx();
+
+    assert_eq!(Ok(Val::Float(0.0)), eval(&mut ctx, "sin(0)"));
+    assert_eq!(Ok(Val::Float(1.0)), eval(&mut ctx, "cos(0)"));
+    assert_eq!(Ok(Val::Num(90)), eval(&mut ctx, "round(atan2(1, 0) * 180 / PI)"));
+    assert_eq!(Ok(Val::Num(45)), eval(&mut ctx, "round(atan(1) * 180 / PI)"));
+    assert_eq!(Err(String::from("'asin': argument is out of the function domain")), eval(&mut ctx, "asin(2)"));
+}
+
+
+#[test]
+fn gcd_lcm() {
+    let mut ctx = stdlib_ctx();
+
+    assert_eq!(Ok(Val::Num(6)), eval(&mut ctx, "gcd(12, 0 - 18)"));
+    assert_eq!(Ok(Val::Num(36)), eval(&mut ctx, "lcm(12, 18)"));
+    assert_eq!(Ok(Val::Num(0)), eval(&mut ctx, "lcm(0, 5)"));
+    assert_eq!(Err(String::from("'lcm': integer overflow")), eval(&mut ctx, "lcm(65536, 65537)"));
+}
+
+
+#[test]
+fn argument_errors() {
+    let mut ctx = stdlib_ctx();
+
+    assert_eq!(Err(String::from("wrong arguments count, expected 1 args")), eval(&mut ctx, "sqrt()"));
+    assert_eq!(Err(String::from("wrong arguments count, expected at least 1 args")), eval(&mut ctx, "max()"));
+    assert_eq!(
+        Err(String::from(r#"'sqrt': argument 1: type mismatch, number expected, found '"x"'"#)),
+        eval(&mut ctx, r#"sqrt("x")"#));
+    assert_eq!(
+        Err(String::from(r#"'min': argument 2: type mismatch, number expected, found '"x"'"#)),
+        eval(&mut ctx, r#"min(1, "x")"#));
+    assert_eq!(Err(String::from("'sqrt': argument is out of the function domain")), eval(&mut ctx, "sqrt(0 - 1)"));
+    assert_eq!(Err(String::from("'gcd': argument 1: type mismatch, number expected, found '1.5'")), eval(&mut ctx, "gcd(1.5, 2)"));
+}
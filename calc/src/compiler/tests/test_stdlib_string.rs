@@ -0,0 +1,85 @@
+use compiler::*;
+use super::test_execute::*;
+
+
+fn str_val(s: &str) -> Result<Val, String> {
+    Ok(Val::Str(Shared::new(String::from(s))))
+}
+
+
+#[test]
+fn case_and_length() {
+    let mut ctx = stdlib_ctx();
+
+    assert_eq!(Ok(Val::Num(6)), eval(&mut ctx, r#"len("привет")"#));
+    assert_eq!(str_val("STRASSE"), eval(&mut ctx, r#"upper("straße")"#));
+    assert_eq!(str_val("ёлка"), eval(&mut ctx, r#"lower("ЁЛКА")"#));
+    assert_eq!(str_val("a b"), eval(&mut ctx, r#"trim("  a b\n")"#));
+}
+
+
+#[test]
+fn split_join() {
+    let mut ctx = stdlib_ctx();
+
+    assert_eq!("[\"a\", \"b\", \"\"]", eval(&mut ctx, r#"split("a,b,", ",")"#).unwrap().to_string());
+    assert_eq!("[\"д\", \"а\"]", eval(&mut ctx, r#"split("да", "")"#).unwrap().to_string());
+    assert_eq!(str_val("a-b-c"), eval(&mut ctx, r#"join(split("a b c", " "), "-")"#));
+}
+
+
+#[test]
+fn search() {
+    let mut ctx = stdlib_ctx();
+
+    assert_eq!(Ok(Val::Num(1)), eval(&mut ctx, r#"contains("hello", "ell")"#));
+    assert_eq!(Ok(Val::Num(0)), eval(&mut ctx, r#"starts_with("hello", "ell")"#));
+    assert_eq!(Ok(Val::Num(1)), eval(&mut ctx, r#"ends_with("hello", "llo")"#));
+    assert_eq!(Ok(Val::Num(2)), eval(&mut ctx, r#"find("ёжик", "и")"#));
+    assert_eq!(Ok(Val::None), eval(&mut ctx, r#"find("ёжик", "z")"#));
+}
+
+
+#[test]
+fn replace_substr() {
+    let mut ctx = stdlib_ctx();
+
+    assert_eq!(str_val("a+b+c"), eval(&mut ctx, r#"replace("a b c", " ", "+")"#));
+    assert_eq!(Err(String::from("'replace': pattern is empty")), eval(&mut ctx, r#"replace("abc", "", "x")"#));
+    assert_eq!(str_val("жи"), eval(&mut ctx, r#"substr("ёжик", 1, 2)"#));
+    assert_eq!(str_val("ик"), eval(&mut ctx, r#"substr("ёжик", 2)"#));
+    assert_eq!(str_val(""), eval(&mut ctx, r#"substr("ёжик", 10, 2)"#));
+    assert_eq!(
+        Err(String::from("'substr': argument 2: non-negative number expected, found '-1'")),
+        eval(&mut ctx, r#"substr("abc", 0 - 1)"#));
+}
+
+
+#[test]
+fn conversions() {
+    let mut ctx = stdlib_ctx();
+
+    assert_eq!(str_val("42"), eval(&mut ctx, "str(42)"));
+    assert_eq!(str_val("1.5"), eval(&mut ctx, "str(1.5)"));
+    assert_eq!(str_val("x"), eval(&mut ctx, r#"str("x")"#));
+    assert_eq!(Ok(Val::Num(-12)), eval(&mut ctx, r#"parse_int(" -12 ")"#));
+    assert_eq!(Err(String::from("'parse_int': '1x' is not an integer")), eval(&mut ctx, r#"parse_int("1x")"#));
+    assert_eq!(
+        Err(String::from("'upper': argument 1: type mismatch, string expected, found '1'")),
+        eval(&mut ctx, "upper(1)"));
+}
+
+
+#[test]
+fn shared_results() {
+    let mut ctx = stdlib_ctx();
+    let s = Shared::new(String::from("text"));
+    ctx.set_var("s", Val::Str(Shared::clone(&s)));
+
+    for expr in &["trim(s)", "str(s)", "substr(s, 0)", r#"replace(s, "z", "y")"#] {
+        match eval(&mut ctx, expr) {
+            Ok(Val::Str(ref r)) => assert!(Shared::ptr_eq(&s, r), "{} copies the string", expr),
+            r => panic!("string expected: {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use compiler;
+use compiler::*;
+use super::test_execute::*;
+
+
+fn assert_send<T: Send>() {}
+fn assert_send_sync<T: Send + Sync>() {}
+
+
+#[test]
+fn types_are_threadsafe() {
+    assert_send::<ExecContext>();
+    assert_send_sync::<AST>();
+    assert_send_sync::<Val>();
+    assert_send_sync::<Error>();
+}
+
+
+#[test]
+fn move_context_to_thread() {
+    let mut ctx = ExecContext::new();
+    exec_with(&mut ctx, r#"
+        fn add(a, b) {
+            return a + b;
+        }
+    "#);
+
+    let ctx = thread::spawn(move || {
+        let mut ctx = ctx;
+        exec_with(&mut ctx, "let r = add(2, 3);");
+        ctx
+    }).join().unwrap();
+
+    assert_eq!(Val::Num(5), ctx.scope.get_val("r"));
+}
+
+
+#[test]
+fn share_program_between_threads() {
+    let prog = Arc::new(compiler::parse(r#"
+        fn twice(x) {
+            return x * 2;
+        }
+
+        let r = twice(arg);
+    "#, "<test>").expect("parse should not fail"));
+
+    let workers: Vec<_> = (0..4).map(|idx| {
+        let prog = Arc::clone(&prog);
+
+        thread::spawn(move || {
+            let mut ctx = ExecContext::new();
+            ctx.set_var("arg", Val::Num(idx));
+
+            compiler::execute(&mut ctx, &prog)
+                .map_err(|err| err.to_error())
+                .expect("execution should not fail");
+
+            ctx.scope.get_val("r")
+        })
+    }).collect();
+
+    for (idx, worker) in workers.into_iter().enumerate() {
+        assert_eq!(Val::Num(idx as i32 * 2), worker.join().unwrap());
+    }
+}
+
+
+#[test]
+fn threadsafe_natives() {
+    let mut ctx = ExecContext::new();
+    let out = Arc::new(Mutex::new(Vec::new()));
+
+    let out_cl = Arc::clone(&out);
+    ctx.decl_func("emit", move |args| {
+        out_cl.lock().unwrap().extend(args);
+        Val::None
+    });
+
+    thread::spawn(move || {
+        exec_with(&mut ctx, "emit(1, 2);");
+    }).join().unwrap();
+
+    assert_eq!(vec![Val::Num(1), Val::Num(2)], *out.lock().unwrap());
+}
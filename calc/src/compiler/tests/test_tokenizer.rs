@@ -11,7 +11,7 @@ fn loc(line: i32) -> Loc {
 }
 
 
-fn tokenize(text: &str) -> Vec<Token> {
+fn tokenize_spans(text: &str) -> Vec<Token<'_>> {
     tokenizer::tokenize(text, FILENAME)
         .map_err(|err| {
             panic!("text should compile: {}", err);
@@ -19,6 +19,30 @@ fn tokenize(text: &str) -> Vec<Token> {
 }
 
 
+/// Tokens with locations reduced to lines, spans are checked separately.
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    strip_spans(tokenize_spans(text))
+}
+
+
+fn strip_spans(tokens: Vec<Token<'_>>) -> Vec<Token<'_>> {
+    tokens.into_iter()
+        .map(|token| {
+            let loc = loc(token.loc().line);
+
+            match token {
+                Symbol(_, ch) => Symbol(loc, ch),
+                Int(_, val) => Int(loc, val),
+                Float(_, val) => Float(loc, val),
+                Str(_, val) => Str(loc, val),
+                Ident(_, name) => Ident(loc, name),
+                Eof(_) => Eof(loc),
+            }
+        })
+        .collect()
+}
+
+
 #[test]
 fn empty_stream() {
     let tkn = tokenize("");
@@ -35,6 +59,37 @@ fn parse_int() {
 }
 
 
+#[test]
+fn parse_float() {
+    let tkn = tokenize("1.5 2.foo");
+
+    assert_eq!(vec![
+        Float(loc(1), 1.5),
+        Int(loc(1), 2), Symbol(loc(1), '.'), Ident(loc(1), "foo"),
+        Eof(loc(1)),
+    ], tkn);
+}
+
+
+#[test]
+fn int_overflow() {
+    let r = tokenizer::tokenize("2147483648", FILENAME);
+
+    assert_eq!("'2147483648': integer is too large", r.unwrap_err().description);
+}
+
+
+#[test]
+fn unicode_text() {
+    let tkn = tokenize(r#"имя "ёжик" x"#);
+
+    assert_eq!(vec![
+        Ident(loc(1), "имя"), Str(loc(1), r#""ёжик""#), Ident(loc(1), "x"),
+        Eof(loc(1)),
+    ], tkn);
+}
+
+
 #[test]
 fn calc_lines() {
     let tkn = tokenize(r#"
@@ -122,3 +177,67 @@ fn string_literals_escape() {
 // * unknown escape symbol (anything except \t\n\\ & \"
 //
 // TODO: convert to normal string on AST node creation
+
+
+#[test]
+fn token_spans() {
+    let text = "x = \"ёж\";\n  ёжик + 10.5";
+    let spans: Vec<_> = tokenize_spans(text).iter()
+        .map(|token| {
+            let loc = token.loc();
+            (loc.line, loc.col, &text[loc.start..loc.end])
+        })
+        .collect();
+
+    assert_eq!(vec![
+        (1, 1, "x"),
+        (1, 3, "="),
+        (1, 5, "\"ёж\""),
+        (1, 9, ";"),
+        (2, 3, "ёжик"),
+        (2, 8, "+"),
+        (2, 10, "10.5"),
+        (2, 14, ""),
+    ], spans);
+}
+
+
+#[test]
+fn error_spans() {
+    let err = tokenizer::tokenize("ё = 1 $ 2", FILENAME).unwrap_err();
+    assert_eq!((1, 7, 7, 8), (err.loc.line, err.loc.col, err.loc.start, err.loc.end));
+
+    let err = tokenizer::tokenize("\n 99999999999", FILENAME).unwrap_err();
+    assert_eq!((2, 2, 2, 13), (err.loc.line, err.loc.col, err.loc.start, err.loc.end));
+}
+
+
+#[test]
+fn keep_comments() {
+    let text = "x // first  \n// второй\ny";
+    let (tokens, comments) = tokenizer::tokenize_with_comments(text, FILENAME).unwrap();
+
+    assert_eq!(3, tokens.len());
+    assert_eq!(
+        vec![(1, 3, "// first"), (2, 1, "// второй")],
+        comments.iter().map(|c| (c.loc.line, c.loc.col, c.text)).collect::<Vec<_>>());
+    assert_eq!("// второй", &text[comments[1].loc.start..comments[1].loc.end]);
+}
+
+
+#[test]
+fn recover_from_errors() {
+    let (tokens, comments, errors) = tokenizer::tokenize_recover("a $ 99999999999 // c\n# b", FILENAME);
+
+    assert_eq!(vec![
+        Ident(loc(1), "a"),
+        Int(loc(1), 0),
+        Ident(loc(2), "b"),
+        Eof(loc(2)),
+    ], strip_spans(tokens));
+
+    assert_eq!(1, comments.len());
+    assert_eq!(
+        vec!["'$': unexpected symbol", "'99999999999': integer is too large", "'#': unexpected symbol"],
+        errors.iter().map(|err| err.description.as_str()).collect::<Vec<_>>());
+}
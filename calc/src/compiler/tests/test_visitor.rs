@@ -0,0 +1,148 @@
+use compiler;
+use compiler::*;
+
+
+const PROGRAM: &str = "\
+let a = 1;
+
+fn f(x) {
+    let b = [x, {\"k\": a}];
+    for i in b {
+        try { g(i.k); } catch (e) { throw e; } finally { b[0] = 2 * 3 + x; }
+    }
+    return b.get(0);
+}
+";
+
+
+/// Names read anywhere in the program.
+#[derive(Default)]
+struct Reads(Vec<String>);
+
+
+impl<'a> Visitor<'a> for Reads {
+    fn visit_var(&mut self, _loc: &'a Loc, name: &'a str) {
+        self.0.push(String::from(name));
+    }
+
+    fn visit_call(&mut self, _loc: &'a Loc, name: &'a str, args: &'a [AST]) {
+        self.0.push(String::from(name));
+        args.iter().for_each(|arg| self.visit(arg));
+    }
+}
+
+
+/// Number of nodes by the hook on every node.
+#[derive(Default)]
+struct Count(usize);
+
+
+impl<'a> Visitor<'a> for Count {
+    fn visit(&mut self, ast: &'a AST) {
+        self.0 += 1;
+        walk(self, ast);
+    }
+}
+
+
+/// Computes the operations on integer literals.
+struct ConstFold;
+
+
+impl Folder for ConstFold {
+    fn fold(&mut self, ast: AST) -> AST {
+        match fold_children(self, ast) {
+            AST::BinOp{ loc, op, left, right } => match (*left, *right) {
+                (AST::Num{ val: l, .. }, AST::Num{ val: r, .. }) if op != '/' && op != '%' => {
+                    let val = match op {
+                        '+' => l + r,
+                        '-' => l - r,
+                        _ => l * r,
+                    };
+                    AST::Num{ loc, val }
+                }
+                (left, right) => AST::BinOp{ loc, op, left: Box::new(left), right: Box::new(right) },
+            },
+            ast => ast,
+        }
+    }
+}
+
+
+/// Renames the variable everywhere.
+struct Rename;
+
+
+impl Folder for Rename {
+    fn fold(&mut self, ast: AST) -> AST {
+        match fold_children(self, ast) {
+            AST::Var{ loc, ref name } if name == "x" => AST::Var{ loc, name: String::from("y") },
+            ast => ast,
+        }
+    }
+
+    fn fold_func_decl(&mut self, decl: FuncDecl) -> FuncDecl {
+        let args = decl.args.iter()
+            .map(|arg| if arg == "x" { String::from("y") } else { arg.clone() })
+            .collect();
+
+        FuncDecl { args, body: self.fold(decl.body), ..decl }
+    }
+}
+
+
+#[test]
+fn visit_order() {
+    let ast = compiler::parse(PROGRAM, "<test>").unwrap();
+
+    let mut reads = Reads::default();
+    reads.visit(&ast);
+    assert_eq!(reads.0, ["x", "a", "b", "g", "i", "e", "b", "x", "b"]);
+
+    let mut count = Count::default();
+    count.visit(&ast);
+    assert_eq!(count.0, 34);
+}
+
+
+#[test]
+fn fold_identity() {
+    let ast = compiler::parse(PROGRAM, "<test>").unwrap();
+
+    struct Identity;
+    impl Folder for Identity {}
+
+    assert_eq!(Identity.fold(ast.clone()), ast);
+}
+
+
+#[test]
+fn fold_constants() {
+    let ast = compiler::parse(PROGRAM, "<test>").unwrap();
+    let folded = format!("{:?}", ConstFold.fold(ast));
+    assert!(folded.contains("val: 6"), "function bodies should be folded: {}", folded);
+    assert!(!folded.contains("op: '*'"), "{}", folded);
+
+    let expr = compiler::parse_expr("(1 + 2) * 4 - a / 2", "<test>").unwrap();
+    let folded = format!("{:?}", ConstFold.fold(expr));
+    assert!(folded.contains("val: 12"), "{}", folded);
+    assert!(folded.contains("op: '/'"), "{}", folded);
+}
+
+
+#[test]
+fn fold_function_bodies() {
+    let mut engine = Engine::new();
+
+    let prog = engine.parse("fn f(x) { return x * 2; }", "<test>").unwrap();
+    let renamed = Rename.fold(prog.clone());
+    assert_eq!(renamed, engine.parse("fn f(y) { return y * 2; }", "<test>").unwrap());
+
+    // the declaration is shared with the function value after running
+    engine.execute(&renamed).unwrap();
+    let again = Rename.fold(renamed.clone());
+    assert_eq!(again, renamed);
+
+    let expr = engine.parse("f(21)", "<test>").unwrap();
+    assert_eq!(Ok(Val::Num(42)), engine.execute(&expr));
+}
@@ -6,16 +6,33 @@ use super::location::*;
 pub enum Token<'a> {
     Symbol(Loc, char),
     Int(Loc, i32),
+    Float(Loc, f64),
     Str(Loc, &'a str),
     Ident(Loc, &'a str),
     Eof(Loc),
 }
 
 
+/// Comment `// ...` (the text includes the slashes), comments are not part
+/// of the token stream and are only kept for tools that rewrite the text.
+#[derive(Debug, PartialEq)]
+pub struct Comment<'a> {
+    pub loc: Loc,
+    pub text: &'a str,
+}
+
+
 impl<'a> Token<'a> {
     pub fn is_symbol(&self, expected: char) -> bool {
         match *self {
-            Token::Symbol(_, ch) => (ch == expected),
+            Token::Symbol(_, ch) => ch == expected,
+            _ => false,
+        }
+    }
+
+    pub fn is_keyword(&self, expected: &str) -> bool {
+        match *self {
+            Token::Ident(_, name) => name == expected,
             _ => false,
         }
     }
@@ -29,6 +46,7 @@ impl<'a> Location for Token<'a> {
 		match *self {
 			Symbol(ref loc, _) => loc,
 			Int(ref loc, _) => loc,
+			Float(ref loc, _) => loc,
 			Str(ref loc, _) => loc,
 			Ident(ref loc, _) => loc,
 			Eof(ref loc) => loc,
@@ -44,6 +62,7 @@ impl<'a> Display for Token<'a> {
 		match *self {
 			Symbol(_, ch) => write!(f, "{}", ch),
 			Int(_, val) => write!(f, "{}", val),
+			Float(_, val) => write!(f, "{:?}", val),
 			Str(_, val) => write!(f, "{}", val),
 			Ident(_, name) => write!(f, "{}", name),
 			Eof(_) => write!(f, "<EOF>"),
@@ -7,7 +7,8 @@ struct TokenizeContext<'a> {
     stream: Chars<'a>,
     cur_text: &'a str,  // rest of the text beginning from cur
     cur: Option<char>,
-    loc: Loc,
+    loc: Loc,           // location of cur, with an empty byte range
+    text_len: usize,
 }
 
 
@@ -18,21 +19,47 @@ impl<'a> TokenizeContext<'a> {
             cur_text: text,
             cur: None,
             loc: Loc::new(filename, 1),
+            text_len: text.len(),
         }
     }
 
     fn next(&mut self) -> Option<char> {
+        let prev = self.cur;
+
         // update cur text (since cur symbol already cropped from the stream)
         self.cur_text = self.stream.as_str();
         self.cur = self.stream.next();
 
-        if let Some(ch) = self.cur {
-            if ch == '\n' {
-                self.loc.line += 1;
-            }
+        if prev == Some('\n') {
+            self.loc.line += 1;
+            self.loc.col = 1;
+        } else {
+            self.loc.col += 1;
         }
 
-        return self.cur;
+        self.loc.start = self.text_len - self.cur_text.len();
+        self.loc.end = self.loc.start;
+
+        self.cur
+    }
+
+    /// Location of a token started at `loc` and ending before cur.
+    fn span(&self, mut loc: Loc) -> Loc {
+        loc.end = self.loc.start;
+        loc
+    }
+
+    /// Location of the cur char.
+    fn char_loc(&self) -> Loc {
+        let mut loc = self.loc.clone();
+        loc.end += self.cur.map_or(0, char::len_utf8);
+        loc
+    }
+
+    /// Part of `text` (the rest of the text at some earlier point) up to
+    /// the current char.
+    fn taken(&self, text: &'a str) -> &'a str {
+        &text[..text.len() - self.cur_text.len()]
     }
 
     fn is_next(&self, expected: char) -> bool {
@@ -42,7 +69,7 @@ impl<'a> TokenizeContext<'a> {
             return ch == expected;
         }
 
-        return false;
+        false
     }
 }
 
@@ -54,22 +81,34 @@ impl<'a> Location for TokenizeContext<'a> {
 }
 
 
-fn parse_int<'a>(ctx: &mut TokenizeContext<'a>) -> Token<'a> {
+fn parse_number<'a>(ctx: &mut TokenizeContext<'a>) -> Result<Token<'a>, Error> {
     let loc = ctx.loc.clone();
-    let mut r = 0;
-    
+    let text = ctx.cur_text;
+    let mut fraction = false;
+
     while let Some(ch) = ctx.cur {
-        if !ch.is_digit(10) {
+        // the dot belongs to the number only if a digit follows, so that
+        // `5.foo()` is still a method call
+        if ch == '.' && !fraction && ctx.stream.clone().next().is_some_and(|ch| ch.is_ascii_digit()) {
+            fraction = true;
+        } else if !ch.is_ascii_digit() {
             break;
         }
 
-        r *= 10;
-        r += ch.to_digit(10).unwrap() as i32;
-
         ctx.next();
     }
 
-    Int(loc, r)
+    let text = ctx.taken(text);
+    let loc = ctx.span(loc);
+
+    if fraction {
+        return Ok(Float(loc, text.parse().unwrap()));
+    }
+
+    match text.parse() {
+        Ok(val) => Ok(Int(loc, val)),
+        Err(_) => loc.error(format!("'{}': integer is too large", text)).into(),
+    }
 }
 
 
@@ -77,13 +116,11 @@ fn parse_literal<'a>(ctx: &mut TokenizeContext<'a>) -> Token<'a> {
     let loc = ctx.loc.clone();
     let r = ctx.cur_text;
 
-    let mut len = 1;
     ctx.next();
 
     while let Some(ch) = ctx.cur {
         if ch == '\\' {
             // process next char unconditionally
-            len += 2;
             ctx.next();
             ctx.next();
 
@@ -92,7 +129,6 @@ fn parse_literal<'a>(ctx: &mut TokenizeContext<'a>) -> Token<'a> {
         }
 
         // include last char to the output
-        len += 1;
         ctx.next();
 
         if ch == '"' {
@@ -101,7 +137,7 @@ fn parse_literal<'a>(ctx: &mut TokenizeContext<'a>) -> Token<'a> {
 
     }
 
-    Str(loc, &r[..len])
+    Str(ctx.span(loc), ctx.taken(r))
 }
 
 
@@ -115,23 +151,62 @@ fn parse_ident<'a>(ctx: &mut TokenizeContext<'a>) -> Token<'a> {
     let text = ctx.cur_text;
 
     debug_assert!(is_word_start(ctx.cur.unwrap()));
-    let mut len = 1;
     ctx.next();
     
     while let Some(ch) = ctx.cur {
-        if !is_word_start(ch) && !ch.is_digit(10) {
+        if !is_word_start(ch) && !ch.is_ascii_digit() {
             break;
         }
 
-        len += 1;
         ctx.next();
     }
 
-    Ident(loc, &text[..len])
+    Ident(ctx.span(loc), ctx.taken(text))
 }
 
 
 pub fn tokenize<'a>(text: &'a str, filename: &str) -> TokenizeResult<'a> {
+    tokenize_impl(text, filename, None, None)
+}
+
+
+/// Lossless variant of `tokenize`: comments are returned too.
+pub fn tokenize_with_comments<'a>(text: &'a str, filename: &str) -> Result<(Vec<Token<'a>>, Vec<Comment<'a>>), Error> {
+    let mut comments = Vec::new();
+    let tokens = tokenize_impl(text, filename, Some(&mut comments), None)?;
+
+    Ok((tokens, comments))
+}
+
+
+/// Variant of `tokenize_with_comments` that doesn't stop at errors: unknown
+/// symbols are skipped and too large integers are replaced by `0`, so the
+/// rest of the text still tokenizes.
+pub fn tokenize_recover<'a>(text: &'a str, filename: &str) -> (Vec<Token<'a>>, Vec<Comment<'a>>, Vec<Error>) {
+    let mut comments = Vec::new();
+    let mut errors = Vec::new();
+
+    let tokens = tokenize_impl(text, filename, Some(&mut comments), Some(&mut errors))
+        .expect("recovering tokenizer should not fail");
+
+    (tokens, comments, errors)
+}
+
+
+/// Record the error if the tokenizer recovers from errors, fail otherwise.
+fn report(errors: &mut Option<&mut Vec<Error>>, err: Error) -> Result<(), Error> {
+    match *errors {
+        Some(ref mut errors) => {
+            errors.push(err);
+            Ok(())
+        }
+        None => Err(err),
+    }
+}
+
+
+fn tokenize_impl<'a>(text: &'a str, filename: &str, mut comments: Option<&mut Vec<Comment<'a>>>,
+                     mut errors: Option<&mut Vec<Error>>) -> TokenizeResult<'a> {
     let mut ctx = TokenizeContext::new(text, filename);
     let mut r: Vec<Token> = Vec::new();
     
@@ -144,8 +219,14 @@ pub fn tokenize<'a>(text: &'a str, filename: &str) -> TokenizeResult<'a> {
             continue;
         }
 
-        if ch.is_digit(10) {
-            r.push(parse_int(&mut ctx));
+        if ch.is_ascii_digit() {
+            match parse_number(&mut ctx) {
+                Ok(token) => r.push(token),
+                Err(err) => {
+                    r.push(Int(err.loc.clone(), 0));
+                    report(&mut errors, err)?;
+                }
+            }
             continue;
         }
 
@@ -161,6 +242,9 @@ pub fn tokenize<'a>(text: &'a str, filename: &str) -> TokenizeResult<'a> {
 
         if ch == '/' && ctx.is_next('/') {
             // comment found, skip it until eol
+            let loc = ctx.loc.clone();
+            let comment = ctx.cur_text;
+
             while let Some(ch) = ctx.cur {
                 if ch == '\n' {
                     break;
@@ -169,6 +253,10 @@ pub fn tokenize<'a>(text: &'a str, filename: &str) -> TokenizeResult<'a> {
                 ctx.next();
             }
 
+            if let Some(ref mut comments) = comments {
+                comments.push(Comment { loc: ctx.span(loc), text: ctx.taken(comment).trim_end() });
+            }
+
             continue;
         }
 
@@ -177,17 +265,18 @@ pub fn tokenize<'a>(text: &'a str, filename: &str) -> TokenizeResult<'a> {
             ':' | '?' | ',' | '.' | ';' | '=' |
             '+' | '-' | '*' | '/' | '%' |
             '!' | '~' | '|' | '&' => {
-                r.push(Symbol(ctx.loc.clone(), ch));
+                r.push(Symbol(ctx.char_loc(), ch));
                 ctx.next();
             }
 
             _ => {
-                return ctx.error(format!("'{}': unexpected symbol", ch)).into();
+                report(&mut errors, ctx.char_loc().error(format!("'{}': unexpected symbol", ch)))?;
+                ctx.next();
             }
         }
     }
 
     r.push(Eof(ctx.loc)); // finish this stream by Eof token
-    return Ok(r);
+    Ok(r)
 }
 
@@ -1,41 +1,159 @@
-use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::mem;
+use std::ptr;
+use std::task::Poll;
 use compiler::*;
 
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Clone)]
 pub enum Val {
     None,
     Num(i32),
-    Str(Rc<String>),
+    Float(f64),
+    Str(Shared<String>),
     Func{
-        decl: Rc<FuncDecl>,
-        scope: Rc<Scope>,
+        decl: Shared<FuncDecl>,
+        scope: Shared<Scope>,
     },
-    NativeFunc(Rc<NativeFuncDecl>),
+    NativeFunc(Shared<NativeFuncDecl>),
+    Generator(Shared<Generator>),
+    Error(Shared<Error>),
+    List(Shared<Lock<Vec<Val>>>),
+    Map(Shared<Lock<BTreeMap<String, Val>>>),
+    Object(Shared<HostRef>),
+    Module(Shared<Module>),
 }
 
 
-pub type CallbackType = Fn(Vec<Val>) -> Val;
+/// Result of a native function, error message is reported at the call site.
+pub type NativeResult = Poll<Result<Val, String>>;
+
+pub trait Callback: Fn(Vec<Val>) -> NativeResult + Threadsafe {}
+
+impl<T> Callback for T where T: Fn(Vec<Val>) -> NativeResult + Threadsafe {}
+
+
+/// Callback of natives that call back into the script (see `CallContext`).
+pub trait ContextCallback: Fn(&mut CallContext, Vec<Val>) -> ExecResult + Threadsafe {}
+
+impl<T> ContextCallback for T where T: Fn(&mut CallContext, Vec<Val>) -> ExecResult + Threadsafe {}
+
+
+/// Values that native functions may return: a `Val`, `Result<Val, E>` or
+/// `Poll<Val>` for functions that suspend the script until the host
+/// provides the result.
+pub trait IntoNativeResult {
+    fn into_native_result(self) -> NativeResult;
+}
+
+
+impl IntoNativeResult for Val {
+    fn into_native_result(self) -> NativeResult {
+        Poll::Ready(Ok(self))
+    }
+}
+
+
+impl<E: fmt::Display> IntoNativeResult for Result<Val, E> {
+    fn into_native_result(self) -> NativeResult {
+        Poll::Ready(self.map_err(|err| err.to_string()))
+    }
+}
+
+
+impl IntoNativeResult for Poll<Val> {
+    fn into_native_result(self) -> NativeResult {
+        self.map(Ok)
+    }
+}
+
+
+/// Number of arguments accepted by a native function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    Fixed(usize),
+    Range(usize, usize),    // inclusive
+    Variadic(usize),        // minimal count
+}
+
+pub type CallbackType = dyn Callback;
+pub type ContextCallbackType = dyn ContextCallback;
+
+pub enum NativeCallback {
+    Plain(Box<CallbackType>),
+    Context(Box<ContextCallbackType>),
+}
 
 pub struct NativeFuncDecl {
     pub name: String,
-    pub callback: Box<CallbackType>,
+    pub arity: Arity,
+    pub callback: NativeCallback,
 }
 
 
 impl Val {
     pub fn is_num(&self) -> bool {
+        matches!(*self, Val::Num(..))
+    }
+
+    pub fn is_func(&self) -> bool {
+        matches!(*self, Val::Func{..})
+    }
+
+    pub fn is_generator(&self) -> bool {
+        matches!(*self, Val::Generator(..))
+    }
+
+    pub fn as_error(&self) -> Option<&Error> {
         match *self {
-            Val::Num(..) => true,
-            _ => false,
+            Val::Error(ref err) => Some(err),
+            _ => None,
         }
     }
 
-    pub fn is_func(&self) -> bool {
+    pub fn as_list(&self) -> Option<&Lock<Vec<Val>>> {
         match *self {
-            Val::Func{..} => true,
-            _ => false,
+            Val::List(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn new_list(items: Vec<Val>) -> Val {
+        Val::List(Shared::new(Lock::new(items)))
+    }
+
+    pub fn new_map(items: BTreeMap<String, Val>) -> Val {
+        Val::Map(Shared::new(Lock::new(items)))
+    }
+
+    /// Whether the value counts as true in conditions: everything except
+    /// `None` and zero numbers.
+    pub fn is_truthy(&self) -> bool {
+        match *self {
+            Val::None => false,
+            Val::Num(val) => val != 0,
+            Val::Float(val) => val != 0.0,
+            _ => true,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&HostRef> {
+        match *self {
+            Val::Object(ref obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    pub fn new_object<T: HostObject>(obj: T) -> Val {
+        Val::Object(Shared::new(HostRef::new(obj)))
+    }
+
+    pub fn as_module(&self) -> Option<&Module> {
+        match *self {
+            Val::Module(ref module) => Some(module),
+            _ => None,
         }
     }
 
@@ -46,6 +164,15 @@ impl Val {
         }
     }
 
+    /// Numeric value of both integers and floats.
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            Val::Num(val) => Some(f64::from(val)),
+            Val::Float(val) => Some(val),
+            _ => None,
+        }
+    }
+
     pub fn as_str(&self) -> Option<&String> {
         match *self {
             Val::Str(ref val) => Some(val),
@@ -53,13 +180,52 @@ impl Val {
         }
     }
 
-    pub fn new_func<T>(name: &str, callback: T) -> Val 
-        where T: Fn(Vec<Val>) -> Val + 'static
+    /// Approximate number of heap bytes owned by this value (closure scopes
+    /// are accounted separately).
+    pub fn heap_size(&self) -> usize {
+        match *self {
+            Val::Str(ref val) => mem::size_of::<String>() + val.capacity(),
+            Val::NativeFunc(ref decl) => mem::size_of::<NativeFuncDecl>() + decl.name.capacity(),
+            Val::Error(ref err) => mem::size_of::<Error>() + err.description.capacity(),
+            Val::List(ref items) => mem::size_of::<Vec<Val>>() + items.borrow().capacity() * mem::size_of::<Val>(),
+            Val::Map(ref items) => {
+                let items = items.borrow();
+                mem::size_of::<BTreeMap<String, Val>>()
+                    + items.keys().map(|key| key.capacity() + mem::size_of::<(String, Val)>()).sum::<usize>()
+            }
+            _ => 0,
+        }
+    }
+
+    pub fn new_func<T, R>(name: &str, callback: T) -> Val
+        where T: Fn(Vec<Val>) -> R + Threadsafe + 'static,
+              R: IntoNativeResult
+    {
+        Val::new_func_arity(name, Arity::Variadic(0), callback)
+    }
+
+    pub fn new_func_arity<T, R>(name: &str, arity: Arity, callback: T) -> Val
+        where T: Fn(Vec<Val>) -> R + Threadsafe + 'static,
+              R: IntoNativeResult
     {
         Val::NativeFunc(
-            Rc::new(NativeFuncDecl {
+            Shared::new(NativeFuncDecl {
                 name: String::from(name),
-                callback: Box::new(callback),
+                arity,
+                callback: NativeCallback::Plain(
+                    Box::new(move |args| callback(args).into_native_result())),
+            }))
+    }
+
+    /// Native function that may call script functions passed to it.
+    pub fn new_context_func<T>(name: &str, arity: Arity, callback: T) -> Val
+        where T: Fn(&mut CallContext, Vec<Val>) -> ExecResult + Threadsafe + 'static
+    {
+        Val::NativeFunc(
+            Shared::new(NativeFuncDecl {
+                name: String::from(name),
+                arity,
+                callback: NativeCallback::Context(Box::new(callback)),
             }))
     }
 }
@@ -70,9 +236,172 @@ impl fmt::Display for Val {
         match *self {
             Val::None => write!(f, "None"),
             Val::Num(val) => write!(f, "{}", val),
+            Val::Float(val) => write!(f, "{:?}", val),
             Val::Str(ref val) => write!(f, r#""{}""#, val),
             Val::Func{ref decl, ..} => write!(f, "fn {}", decl.name),
             Val::NativeFunc(ref decl) => write!(f, "native fn {}", decl.name),
+            Val::Generator(ref gen) => write!(f, "generator fn {}", gen.decl.name),
+            Val::Error(ref err) => write!(f, r#"error "{}""#, err.description),
+            Val::Object(ref obj) => write!(f, "object {}", obj.type_name()),
+            Val::Module(ref module) => write!(f, r#"module "{}""#, module.name),
+            Val::List(ref items) => fmt_guarded(Shared::as_ptr(items) as usize, f, "[...]", |f| {
+                write!(f, "[")?;
+                for (idx, item) in items.borrow().iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }),
+            Val::Map(ref items) => fmt_guarded(Shared::as_ptr(items) as usize, f, "{...}", |f| {
+                write!(f, "{{")?;
+                for (idx, (key, item)) in items.borrow().iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, r#""{}": {}"#, key, item)?;
+                }
+                write!(f, "}}")
+            }),
+        }
+    }
+}
+
+
+impl fmt::Debug for Val {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Val::None => write!(f, "None"),
+            Val::Num(val) => f.debug_tuple("Num").field(&val).finish(),
+            Val::Float(val) => f.debug_tuple("Float").field(&val).finish(),
+            Val::Str(ref val) => f.debug_tuple("Str").field(val).finish(),
+            Val::Func{ ref decl, ref scope } => fmt_guarded(Shared::as_ptr(scope) as usize, f, "Func { .. }", |f| {
+                f.debug_struct("Func").field("decl", decl).field("scope", scope).finish()
+            }),
+            Val::NativeFunc(ref decl) => f.debug_tuple("NativeFunc").field(decl).finish(),
+            Val::Generator(ref gen) => f.debug_tuple("Generator").field(gen).finish(),
+            Val::Error(ref err) => f.debug_tuple("Error").field(err).finish(),
+            Val::Object(ref obj) => f.debug_tuple("Object").field(obj).finish(),
+            Val::Module(ref module) => f.debug_tuple("Module").field(module).finish(),
+            Val::List(ref items) => fmt_guarded(Shared::as_ptr(items) as usize, f, "List([...])", |f| {
+                f.debug_tuple("List").field(&*items.borrow()).finish()
+            }),
+            Val::Map(ref items) => fmt_guarded(Shared::as_ptr(items) as usize, f, "Map({...})", |f| {
+                f.debug_tuple("Map").field(&*items.borrow()).finish()
+            }),
+        }
+    }
+}
+
+
+impl PartialEq for Val {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Val::None, Val::None) => true,
+            (Val::Num(a), Val::Num(b)) => a == b,
+            (Val::Float(a), Val::Float(b)) => a == b,
+            (Val::Str(a), Val::Str(b)) => a == b,
+            (Val::Func{ decl: a_decl, scope: a }, Val::Func{ decl: b_decl, scope: b }) => {
+                a_decl == b_decl && eq_guarded(Shared::as_ptr(a) as usize, Shared::as_ptr(b) as usize, || a == b)
+            }
+            (Val::NativeFunc(a), Val::NativeFunc(b)) => a == b,
+            (Val::Generator(a), Val::Generator(b)) => a == b,
+            (Val::Error(a), Val::Error(b)) => a == b,
+            (Val::Object(a), Val::Object(b)) => a == b,
+            (Val::Module(a), Val::Module(b)) => a == b,
+            (Val::List(a), Val::List(b)) => {
+                eq_guarded(Shared::as_ptr(a) as usize, Shared::as_ptr(b) as usize, || *a.borrow() == *b.borrow())
+            }
+            (Val::Map(a), Val::Map(b)) => {
+                eq_guarded(Shared::as_ptr(a) as usize, Shared::as_ptr(b) as usize, || *a.borrow() == *b.borrow())
+            }
+            _ => false,
+        }
+    }
+}
+
+
+thread_local! {
+    // lists, maps and scopes being formatted or compared up the stack, they
+    // may contain themselves
+    static FORMATTING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+    static COMPARING: RefCell<Vec<(usize, usize)>> = const { RefCell::new(Vec::new()) };
+}
+
+
+/// Format the container, or write `placeholder` if it is met inside itself.
+fn fmt_guarded<F>(ptr: usize, f: &mut fmt::Formatter, placeholder: &str, fmt: F) -> fmt::Result
+    where F: FnOnce(&mut fmt::Formatter) -> fmt::Result
+{
+    let nested = FORMATTING.with(|open| {
+        let mut open = open.borrow_mut();
+        let nested = open.contains(&ptr);
+        open.push(ptr);
+        nested
+    });
+
+    let r = if nested {
+        f.write_str(placeholder)
+    } else {
+        fmt(f)
+    };
+
+    FORMATTING.with(|open| open.borrow_mut().pop());
+    r
+}
+
+
+/// Compare the containers, a pair met again inside itself is assumed equal
+/// (the rest of the items decide).
+fn eq_guarded<F>(a: usize, b: usize, eq: F) -> bool
+    where F: FnOnce() -> bool
+{
+    if a == b {
+        return true;
+    }
+
+    let nested = COMPARING.with(|open| {
+        let mut open = open.borrow_mut();
+        let nested = open.contains(&(a, b));
+        open.push((a, b));
+        nested
+    });
+
+    let r = nested || eq();
+
+    COMPARING.with(|open| open.borrow_mut().pop());
+    r
+}
+
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Fixed(n) => count == n,
+            Arity::Range(min, max) => min <= count && count <= max,
+            Arity::Variadic(min) => min <= count,
+        }
+    }
+}
+
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Arity::Fixed(n) => write!(f, "{}", n),
+            Arity::Range(min, max) => write!(f, "{} to {}", min, max),
+            Arity::Variadic(min) => write!(f, "at least {}", min),
+        }
+    }
+}
+
+
+impl NativeFuncDecl {
+    fn callback_ptr(&self) -> *const () {
+        match self.callback {
+            NativeCallback::Plain(ref callback) => &**callback as *const CallbackType as *const (),
+            NativeCallback::Context(ref callback) => &**callback as *const ContextCallbackType as *const (),
         }
     }
 }
@@ -87,8 +416,7 @@ impl fmt::Debug for NativeFuncDecl {
 
 impl PartialEq for NativeFuncDecl {
     fn eq(&self, other: &Self) -> bool {
-        return self.name == other.name
-            && &*self.callback as *const CallbackType == &*other.callback as *const CallbackType;
+        self.name == other.name && ptr::eq(self.callback_ptr(), other.callback_ptr())
     }
 }
 
@@ -0,0 +1,224 @@
+use compiler::*;
+use self::AST::*;
+
+
+/// Read-only walk over the `AST`.
+///
+/// `visit` is called for every node and dispatches to the method of its
+/// variant, the default methods visit the children in the order of the
+/// text, bodies of functions included (see `visit_func_decl`). A pass
+/// overrides the methods of the nodes it cares about and calls `visit` for
+/// the children it still wants walked. Overriding `visit` itself gives a
+/// hook for every node, `walk` then continues with the usual dispatch.
+pub trait Visitor<'a> {
+    fn visit(&mut self, ast: &'a AST) {
+        walk(self, ast);
+    }
+
+    fn visit_block(&mut self, _loc: &'a Loc, body: &'a [AST], _end: &'a Loc) {
+        body.iter().for_each(|st| self.visit(st));
+    }
+
+    fn visit_decl_var(&mut self, _loc: &'a Loc, _name: &'a str, init: &'a AST) {
+        self.visit(init);
+    }
+
+    fn visit_assign(&mut self, _loc: &'a Loc, _name: &'a str, init: &'a AST) {
+        self.visit(init);
+    }
+
+    fn visit_return(&mut self, _loc: &'a Loc, ret: &'a AST) {
+        self.visit(ret);
+    }
+
+    fn visit_func(&mut self, _loc: &'a Loc, decl: &'a FuncDecl) {
+        self.visit_func_decl(decl);
+    }
+
+    fn visit_func_decl(&mut self, decl: &'a FuncDecl) {
+        self.visit(&decl.body);
+    }
+
+    fn visit_yield(&mut self, _loc: &'a Loc, val: &'a AST) {
+        self.visit(val);
+    }
+
+    fn visit_for(&mut self, _loc: &'a Loc, _var: &'a str, iter: &'a AST, body: &'a AST) {
+        self.visit(iter);
+        self.visit(body);
+    }
+
+    fn visit_throw(&mut self, _loc: &'a Loc, val: &'a AST) {
+        self.visit(val);
+    }
+
+    fn visit_import(&mut self, _loc: &'a Loc, _path: &'a str, _alias: &'a str) {}
+
+    fn visit_from_import(&mut self, _loc: &'a Loc, _path: &'a str, _names: &'a [String]) {}
+
+    fn visit_try(&mut self, _loc: &'a Loc, body: &'a AST, catch: Option<(&'a str, &'a AST)>, finally: Option<&'a AST>) {
+        self.visit(body);
+
+        if let Some((_, handler)) = catch {
+            self.visit(handler);
+        }
+
+        if let Some(finally) = finally {
+            self.visit(finally);
+        }
+    }
+
+    fn visit_num(&mut self, _loc: &'a Loc, _val: i32) {}
+
+    fn visit_float(&mut self, _loc: &'a Loc, _val: f64) {}
+
+    fn visit_str(&mut self, _loc: &'a Loc, _val: &'a str) {}
+
+    fn visit_var(&mut self, _loc: &'a Loc, _name: &'a str) {}
+
+    fn visit_call(&mut self, _loc: &'a Loc, _name: &'a str, args: &'a [AST]) {
+        args.iter().for_each(|arg| self.visit(arg));
+    }
+
+    fn visit_bin_op(&mut self, _loc: &'a Loc, _op: char, left: &'a AST, right: &'a AST) {
+        self.visit(left);
+        self.visit(right);
+    }
+
+    fn visit_list(&mut self, _loc: &'a Loc, items: &'a [AST]) {
+        items.iter().for_each(|item| self.visit(item));
+    }
+
+    fn visit_map(&mut self, _loc: &'a Loc, items: &'a [(String, AST)]) {
+        items.iter().for_each(|item| self.visit(&item.1));
+    }
+
+    fn visit_index(&mut self, _loc: &'a Loc, obj: &'a AST, index: &'a AST) {
+        self.visit(obj);
+        self.visit(index);
+    }
+
+    fn visit_set_index(&mut self, _loc: &'a Loc, obj: &'a AST, index: &'a AST, val: &'a AST) {
+        self.visit(obj);
+        self.visit(index);
+        self.visit(val);
+    }
+
+    fn visit_get_prop(&mut self, _loc: &'a Loc, obj: &'a AST, _name: &'a str) {
+        self.visit(obj);
+    }
+
+    fn visit_set_prop(&mut self, _loc: &'a Loc, obj: &'a AST, _name: &'a str, val: &'a AST) {
+        self.visit(obj);
+        self.visit(val);
+    }
+
+    fn visit_method_call(&mut self, _loc: &'a Loc, obj: &'a AST, _name: &'a str, args: &'a [AST]) {
+        self.visit(obj);
+        args.iter().for_each(|arg| self.visit(arg));
+    }
+}
+
+
+/// Call the method of the node's variant, the default `Visitor::visit`.
+pub fn walk<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, ast: &'a AST) {
+    match *ast {
+        Block{ ref loc, ref body, ref end } => visitor.visit_block(loc, body, end),
+        DeclVar{ ref loc, ref name, ref init } => visitor.visit_decl_var(loc, name, init),
+        Assign{ ref loc, ref name, ref init } => visitor.visit_assign(loc, name, init),
+        Return{ ref loc, ref ret } => visitor.visit_return(loc, ret),
+        Func{ ref loc, ref decl } => visitor.visit_func(loc, decl),
+        Yield{ ref loc, ref val } => visitor.visit_yield(loc, val),
+        For{ ref loc, ref var, ref iter, ref body } => visitor.visit_for(loc, var, iter, body),
+        Throw{ ref loc, ref val } => visitor.visit_throw(loc, val),
+        Import{ ref loc, ref path, ref alias } => visitor.visit_import(loc, path, alias),
+        FromImport{ ref loc, ref path, ref names } => visitor.visit_from_import(loc, path, names),
+        Try{ ref loc, ref body, ref catch, ref finally } => {
+            let catch = catch.as_ref().map(|(var, handler)| (var.as_str(), &**handler));
+            visitor.visit_try(loc, body, catch, finally.as_deref())
+        }
+        Num{ ref loc, val } => visitor.visit_num(loc, val),
+        Float{ ref loc, val } => visitor.visit_float(loc, val),
+        Str{ ref loc, ref val } => visitor.visit_str(loc, val),
+        Var{ ref loc, ref name } => visitor.visit_var(loc, name),
+        Call{ ref loc, ref name, ref args } => visitor.visit_call(loc, name, args),
+        BinOp{ ref loc, op, ref left, ref right } => visitor.visit_bin_op(loc, op, left, right),
+        List{ ref loc, ref items } => visitor.visit_list(loc, items),
+        Map{ ref loc, ref items } => visitor.visit_map(loc, items),
+        Index{ ref loc, ref obj, ref index } => visitor.visit_index(loc, obj, index),
+        SetIndex{ ref loc, ref obj, ref index, ref val } => visitor.visit_set_index(loc, obj, index, val),
+        GetProp{ ref loc, ref obj, ref name } => visitor.visit_get_prop(loc, obj, name),
+        SetProp{ ref loc, ref obj, ref name, ref val } => visitor.visit_set_prop(loc, obj, name, val),
+        MethodCall{ ref loc, ref obj, ref name, ref args } => visitor.visit_method_call(loc, obj, name, args),
+    }
+}
+
+
+/// Rewriting walk over the `AST`, nodes are taken and returned by value.
+///
+/// The default `fold` rebuilds the node from its folded children, so the
+/// tree comes back unchanged. A pass overrides `fold`, matches the nodes it
+/// rewrites and hands the rest to `fold_children`; calling `fold_children`
+/// first makes the rewrite bottom-up. Function bodies go through
+/// `fold_func_decl`.
+pub trait Folder {
+    fn fold(&mut self, ast: AST) -> AST {
+        fold_children(self, ast)
+    }
+
+    fn fold_func_decl(&mut self, decl: FuncDecl) -> FuncDecl {
+        FuncDecl {
+            body: self.fold(decl.body),
+            ..decl
+        }
+    }
+}
+
+
+/// The node with every child replaced by its folded version.
+pub fn fold_children<F: Folder + ?Sized>(folder: &mut F, ast: AST) -> AST {
+    match ast {
+        Block{ loc, body, end } => Block{ loc, body: fold_all(folder, body), end },
+        DeclVar{ loc, name, init } => DeclVar{ loc, name, init: fold_box(folder, *init) },
+        Assign{ loc, name, init } => Assign{ loc, name, init: fold_box(folder, *init) },
+        Return{ loc, ret } => Return{ loc, ret: fold_box(folder, *ret) },
+        Func{ loc, decl } => {
+            // the declaration may be shared with a function value
+            let decl = Shared::try_unwrap(decl).unwrap_or_else(|decl| FuncDecl::clone(&decl));
+            Func{ loc, decl: Shared::new(folder.fold_func_decl(decl)) }
+        }
+        Yield{ loc, val } => Yield{ loc, val: fold_box(folder, *val) },
+        For{ loc, var, iter, body } => For{ loc, var, iter: fold_box(folder, *iter), body: fold_box(folder, *body) },
+        Throw{ loc, val } => Throw{ loc, val: fold_box(folder, *val) },
+        Try{ loc, body, catch, finally } => Try{
+            loc,
+            body: fold_box(folder, *body),
+            catch: catch.map(|(var, handler)| (var, fold_box(folder, *handler))),
+            finally: finally.map(|body| fold_box(folder, *body)),
+        },
+        Call{ loc, name, args } => Call{ loc, name, args: fold_all(folder, args) },
+        BinOp{ loc, op, left, right } => BinOp{ loc, op, left: fold_box(folder, *left), right: fold_box(folder, *right) },
+        List{ loc, items } => List{ loc, items: fold_all(folder, items) },
+        Map{ loc, items } => Map{
+            loc,
+            items: items.into_iter().map(|(key, val)| (key, folder.fold(val))).collect(),
+        },
+        Index{ loc, obj, index } => Index{ loc, obj: fold_box(folder, *obj), index: fold_box(folder, *index) },
+        SetIndex{ loc, obj, index, val } => SetIndex{ loc, obj: fold_box(folder, *obj), index: fold_box(folder, *index), val: fold_box(folder, *val) },
+        GetProp{ loc, obj, name } => GetProp{ loc, obj: fold_box(folder, *obj), name },
+        SetProp{ loc, obj, name, val } => SetProp{ loc, obj: fold_box(folder, *obj), name, val: fold_box(folder, *val) },
+        MethodCall{ loc, obj, name, args } => MethodCall{ loc, obj: fold_box(folder, *obj), name, args: fold_all(folder, args) },
+
+        leaf @ (Import{..} | FromImport{..} | Num{..} | Float{..} | Str{..} | Var{..}) => leaf,
+    }
+}
+
+
+fn fold_all<F: Folder + ?Sized>(folder: &mut F, nodes: Vec<AST>) -> Vec<AST> {
+    nodes.into_iter().map(|node| folder.fold(node)).collect()
+}
+
+
+fn fold_box<F: Folder + ?Sized>(folder: &mut F, node: AST) -> Box<AST> {
+    Box::new(folder.fold(node))
+}
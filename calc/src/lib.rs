@@ -0,0 +1,4 @@
+pub mod compiler;
+pub mod lsp;
+
+pub use compiler::Engine;
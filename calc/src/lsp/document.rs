@@ -0,0 +1,405 @@
+use compiler;
+use compiler::*;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DefKind {
+    Variable,
+    Argument,
+    Function,
+}
+
+
+/// Name introduced by the program. Offsets are bytes of the text.
+#[derive(Debug, Clone)]
+pub struct Def {
+    pub name: String,
+    pub kind: DefKind,
+    pub span: (usize, usize),       // the name itself
+    pub range: (usize, usize),      // whole declaration
+    pub scope: usize,               // scope the name is declared in
+    pub body: Option<usize>,        // scope of the function body
+    pub detail: String,             // declaration as written, `fn f(a, b)`
+    pub doc: Option<String>,
+}
+
+
+/// Program, function body, loop body or a clause of `try`.
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub parent: Option<usize>,
+    pub range: (usize, usize),
+}
+
+
+/// Name read or assigned by the program.
+#[derive(Debug, Clone)]
+pub struct Ref {
+    pub name: String,
+    pub span: (usize, usize),
+    pub scope: usize,
+}
+
+
+/// Open text document with the results of parsing and linting it.
+pub struct Document {
+    pub uri: String,
+    pub text: String,
+    pub errors: Vec<Error>,
+    pub warnings: Vec<Warning>,
+    pub defs: Vec<Def>,
+    pub refs: Vec<Ref>,
+    pub scopes: Vec<Scope>,
+    line_starts: Vec<usize>,
+}
+
+
+struct Indexer<'t> {
+    text: &'t str,
+    tokens: &'t [Token<'t>],
+    comments: &'t [Comment<'t>],
+    defs: Vec<Def>,
+    refs: Vec<Ref>,
+    scopes: Vec<Scope>,
+    current: usize,
+}
+
+
+impl Document {
+    pub fn new(uri: &str, text: String) -> Self {
+        let (ast, errors) = compiler::parse_recover(&text, uri);
+
+        let mut linter = Linter::new().allow_comments(&text).unwrap_or_default();
+        if !errors.is_empty() {
+            linter = linter.allow_unused();
+        }
+        let warnings = linter.check(&ast);
+
+        // the same tokens the recovering parser built the tree from
+        let (tokens, comments, _) = compiler::tokenize_recover(&text, uri);
+
+        let (defs, refs, scopes) = {
+            let mut indexer = Indexer {
+                text: &text,
+                tokens: &tokens,
+                comments: &comments,
+                defs: Vec::new(),
+                refs: Vec::new(),
+                scopes: vec![Scope { parent: None, range: (0, text.len()) }],
+                current: 0,
+            };
+
+            indexer.visit(&ast);
+            (indexer.defs, indexer.refs, indexer.scopes)
+        };
+
+        let line_starts = ::std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(pos, _)| pos + 1))
+            .collect();
+
+        Document {
+            uri: String::from(uri),
+            text, errors, warnings, defs, refs, scopes, line_starts,
+        }
+    }
+
+    /// LSP position: zero-based line and UTF-16 offset in the line.
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let character = self.text[self.line_starts[line]..offset].encode_utf16().count();
+
+        (line, character)
+    }
+
+    /// Byte offset of the LSP position, clamped to the line and the text.
+    pub fn offset(&self, line: usize, character: usize) -> usize {
+        let start = match self.line_starts.get(line) {
+            Some(&start) => start,
+            None => return self.text.len(),
+        };
+
+        let mut units = 0;
+
+        for (pos, ch) in self.text[start..].char_indices() {
+            if units >= character || ch == '\n' {
+                return start + pos;
+            }
+            units += ch.len_utf16();
+        }
+
+        self.text.len()
+    }
+
+    /// Byte range of the location, the whole line when it has no span.
+    pub fn loc_range(&self, loc: &Loc) -> (usize, usize) {
+        if loc.has_span() {
+            return (loc.start, loc.end);
+        }
+
+        let line = (loc.line.max(1) as usize - 1).min(self.line_starts.len() - 1);
+        let start = self.line_starts[line];
+        let end = self.text[start..].find('\n').map_or(self.text.len(), |pos| start + pos);
+
+        (start, end)
+    }
+
+    /// Innermost scope around the offset.
+    pub fn scope_at(&self, offset: usize) -> usize {
+        self.scopes.iter().enumerate()
+            .filter(|(_, scope)| scope.range.0 <= offset && offset <= scope.range.1)
+            .max_by_key(|(_, scope)| scope.range.0)
+            .map_or(0, |(idx, _)| idx)
+    }
+
+    /// Scope and its parents up to the program.
+    fn scope_chain(&self, scope: usize) -> Vec<usize> {
+        let mut chain = vec![scope];
+
+        while let Some(parent) = self.scopes[*chain.last().unwrap()].parent {
+            chain.push(parent);
+        }
+
+        chain
+    }
+
+    /// Declaration the name refers to at the offset: the latest one before
+    /// the offset in the innermost scope, otherwise a later one, since
+    /// function bodies may refer to names declared after them.
+    pub fn resolve(&self, name: &str, offset: usize, scope: usize) -> Option<&Def> {
+        let chain = self.scope_chain(scope);
+
+        let before = chain.iter().filter_map(|&scope| {
+            self.defs.iter().rev()
+                .find(|def| def.scope == scope && def.name == name && def.span.0 <= offset)
+        }).next();
+
+        before.or_else(|| {
+            chain.iter().filter_map(|&scope| {
+                self.defs.iter().find(|def| def.scope == scope && def.name == name)
+            }).next()
+        })
+    }
+
+    /// Declaration at the offset, or the one the name at the offset refers to.
+    pub fn def_at(&self, offset: usize) -> Option<&Def> {
+        let contains = |span: (usize, usize)| span.0 <= offset && offset <= span.1;
+
+        if let Some(def) = self.defs.iter().find(|def| contains(def.span)) {
+            return Some(def);
+        }
+
+        self.refs.iter()
+            .find(|r| contains(r.span))
+            .and_then(|r| self.resolve(&r.name, r.span.0, r.scope))
+    }
+
+    /// Name read or assigned at the offset.
+    pub fn ref_at(&self, offset: usize) -> Option<&Ref> {
+        self.refs.iter().find(|r| r.span.0 <= offset && offset <= r.span.1)
+    }
+
+    /// Names visible at the offset, inner scopes first, each name once.
+    pub fn visible(&self, offset: usize) -> Vec<&Def> {
+        let mut r: Vec<&Def> = Vec::new();
+
+        for scope in self.scope_chain(self.scope_at(offset)) {
+            let defs = self.defs.iter().rev()
+                .filter(|def| def.scope == scope)
+                .filter(|def| def.span.0 <= offset || def.kind == DefKind::Function);
+
+            for def in defs {
+                if !r.iter().any(|seen| seen.name == def.name) {
+                    r.push(def);
+                }
+            }
+        }
+
+        r
+    }
+}
+
+
+impl<'t> Indexer<'t> {
+    fn token_idx(&self, offset: usize) -> usize {
+        self.tokens.partition_point(|token| token.loc().start < offset)
+    }
+
+    /// First identifier with the name from the token index on.
+    fn find_ident(&self, from: usize, name: &str) -> Option<usize> {
+        (from..self.tokens.len()).find(|&idx| self.tokens[idx].is_keyword(name))
+    }
+
+    fn declare(&mut self, name: &str, kind: DefKind, span: (usize, usize), detail: String) -> usize {
+        self.defs.push(Def {
+            name: String::from(name),
+            kind,
+            span,
+            range: span,
+            scope: self.current,
+            body: None,
+            detail,
+            doc: None,
+        });
+
+        self.defs.len() - 1
+    }
+
+    /// Declare the name found in the tokens after the index.
+    fn declare_after(&mut self, from: usize, name: &str, detail: String) {
+        if let Some(idx) = self.find_ident(from, name) {
+            let loc = self.tokens[idx].loc();
+            let span = (loc.start, loc.end);
+            self.declare(name, DefKind::Variable, span, detail);
+        }
+    }
+
+    /// Index names declared by the callback in a nested scope.
+    fn nested<F: FnOnce(&mut Self)>(&mut self, range: (usize, usize), index: F) {
+        self.scopes.push(Scope { parent: Some(self.current), range });
+        let outer = self.current;
+        self.current = self.scopes.len() - 1;
+
+        index(self);
+        self.current = outer;
+    }
+
+    fn reference(&mut self, loc: &Loc, name: &str) {
+        self.refs.push(Ref {
+            name: String::from(name),
+            span: (loc.start, loc.end),
+            scope: self.current,
+        });
+    }
+
+    /// Comment lines right above the line, without the slashes.
+    fn doc_comment(&self, line: i32) -> Option<String> {
+        let mut lines = Vec::new();
+        let mut expected = line - 1;
+
+        for comment in self.comments.iter().rev().skip_while(|comment| comment.loc.line >= line) {
+            let own_line = self.text[..comment.loc.start].rsplit('\n').next()
+                .is_some_and(|before| before.trim().is_empty());
+
+            if comment.loc.line != expected || !own_line {
+                break;
+            }
+
+            let text = comment.text.trim_start_matches('/');
+            lines.push(text.strip_prefix(' ').unwrap_or(text));
+            expected -= 1;
+        }
+
+        if lines.is_empty() {
+            return None;
+        }
+
+        lines.reverse();
+        Some(lines.join("\n"))
+    }
+}
+
+
+impl<'a, 't> Visitor<'a> for Indexer<'t> {
+    fn visit_decl_var(&mut self, loc: &'a Loc, name: &'a str, init: &'a AST) {
+        self.visit(init);
+        self.declare(name, DefKind::Variable, (loc.start, loc.end), format!("let {}", name));
+    }
+
+    fn visit_assign(&mut self, loc: &'a Loc, name: &'a str, init: &'a AST) {
+        self.reference(loc, name);
+        self.visit(init);
+    }
+
+    fn visit_func(&mut self, loc: &'a Loc, decl: &'a FuncDecl) {
+        let name_idx = self.token_idx(loc.start);
+
+        // the range starts at `fn` and ends at the closing brace
+        let start = name_idx.checked_sub(1)
+            .map_or(loc.start, |idx| self.tokens[idx].loc().start);
+        let end = block_end(&decl.body);
+
+        let signature = format!("fn {}({})", decl.name, decl.args.join(", "));
+        let def = self.declare(&decl.name, DefKind::Function, (loc.start, loc.end), signature);
+
+        self.scopes.push(Scope { parent: Some(self.current), range: (start, end) });
+        let outer = self.current;
+        self.current = self.scopes.len() - 1;
+
+        self.defs[def].range = (start, end);
+        self.defs[def].body = Some(self.current);
+        self.defs[def].doc = self.doc_comment(loc.line);
+
+        for (arg, arg_loc) in decl.args.iter().zip(&decl.arg_locs) {
+            let detail = format!("argument of {}", decl.name);
+            self.declare(arg, DefKind::Argument, (arg_loc.start, arg_loc.end), detail);
+        }
+
+        self.visit_func_decl(decl);
+        self.current = outer;
+    }
+
+    fn visit_for(&mut self, loc: &'a Loc, var: &'a str, iter: &'a AST, body: &'a AST) {
+        self.visit(iter);
+
+        // the variable is declared anew for each iteration of the body
+        self.nested((loc.start, block_end(body)), |indexer| {
+            let from = indexer.token_idx(loc.start) + 1;
+            indexer.declare_after(from, var, format!("for {} in ...", var));
+
+            indexer.visit(body);
+        });
+    }
+
+    fn visit_try(&mut self, _loc: &'a Loc, body: &'a AST, catch: Option<(&'a str, &'a AST)>, finally: Option<&'a AST>) {
+        self.nested((body.loc().start, block_end(body)), |indexer| indexer.visit(body));
+
+        if let Some((var, handler)) = catch {
+            // `catch (<var>)` follows the closing brace of the body
+            let body_end = block_end(body);
+
+            self.nested((body_end, block_end(handler)), |indexer| {
+                let from = indexer.token_idx(body_end);
+                indexer.declare_after(from, var, format!("catch ({})", var));
+                indexer.visit(handler);
+            });
+        }
+
+        if let Some(finally) = finally {
+            self.nested((finally.loc().start, block_end(finally)), |indexer| indexer.visit(finally));
+        }
+    }
+
+    fn visit_import(&mut self, loc: &'a Loc, path: &'a str, alias: &'a str) {
+        let from = self.token_idx(loc.start) + 1;
+        self.declare_after(from, alias, format!("import \"{}\" as {}", path, alias));
+    }
+
+    fn visit_from_import(&mut self, loc: &'a Loc, path: &'a str, names: &'a [String]) {
+        let keyword = self.find_ident(self.token_idx(loc.start) + 1, "import");
+
+        if let Some(keyword) = keyword {
+            for name in names {
+                self.declare_after(keyword + 1, name, format!("from \"{}\" import {}", path, name));
+            }
+        }
+    }
+
+    fn visit_var(&mut self, loc: &'a Loc, name: &'a str) {
+        self.reference(loc, name);
+    }
+
+    fn visit_call(&mut self, loc: &'a Loc, name: &'a str, args: &'a [AST]) {
+        self.reference(loc, name);
+        args.iter().for_each(|arg| self.visit(arg));
+    }
+}
+
+
+/// Offset after the closing brace of the block.
+fn block_end(body: &AST) -> usize {
+    match *body {
+        AST::Block{ ref end, .. } => end.end,
+        ref body => body.loc().end,
+    }
+}
@@ -0,0 +1,16 @@
+//! Language server for calc scripts, see the `calc-lsp` binary.
+//!
+//! The server speaks JSON-RPC with `Content-Length` framing over any pair of
+//! streams and offers diagnostics (syntax errors and lint warnings), go to
+//! definition, hover, document symbols and completion.
+
+mod rpc;
+mod document;
+mod server;
+
+#[cfg(test)]
+mod tests;
+
+
+pub use self::rpc::{read_message, write_message};
+pub use self::server::Server;
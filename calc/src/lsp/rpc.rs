@@ -0,0 +1,66 @@
+use std::io::{self, BufRead, Write};
+use compiler::Json;
+
+
+/// Largest message body accepted, so a bad header can't exhaust the memory.
+const MAX_LENGTH: usize = 64 * 1024 * 1024;
+
+
+/// Body of the next message, `None` at the end of the input. Messages are
+/// framed by a `Content-Length` header as in the LSP base protocol.
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut length = None;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+
+        if input.read_line(&mut line)? == 0 {
+            if length.is_none() {
+                return Ok(None);
+            }
+            return Err(invalid("unexpected end of headers"));
+        }
+
+        let header = line.trim_end();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;   // stray line break between messages
+        }
+
+        let (name, val) = header.split_once(':')
+            .ok_or_else(|| invalid(&format!("'{}': invalid header", header)))?;
+
+        if name.eq_ignore_ascii_case("Content-Length") {
+            let val = val.trim();
+            let parsed = val.parse()
+                .map_err(|_| invalid(&format!("'{}': invalid content length", val)))?;
+            if parsed > MAX_LENGTH {
+                return Err(invalid(&format!("'{}': content length is too large", val)));
+            }
+            length = Some(parsed);
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|_| invalid("message is not valid UTF-8"))
+}
+
+
+pub fn write_message<W: Write>(output: &mut W, msg: &Json) -> io::Result<()> {
+    let body = msg.to_string();
+
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use compiler::{stdlib, Json, ExecContext, Val, Error, Warning, Label, Note};
+use super::document::{Document, Def, DefKind};
+use super::rpc::{read_message, write_message};
+
+
+const KEYWORDS: [&str; 13] = [
+    "let", "fn", "return", "yield", "for", "in", "throw",
+    "try", "catch", "finally", "import", "from", "as",
+];
+
+
+// JSON-RPC and LSP error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+// LSP enumerations
+const SEVERITY_ERROR: i64 = 1;
+const SEVERITY_WARNING: i64 = 2;
+const SYMBOL_FUNCTION: i64 = 12;
+const SYMBOL_VARIABLE: i64 = 13;
+const COMPLETION_FUNCTION: i64 = 3;
+const COMPLETION_VARIABLE: i64 = 6;
+const COMPLETION_KEYWORD: i64 = 14;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Created,
+    Running,
+    ShutDown,
+}
+
+
+type RequestResult = Result<Json, (i64, String)>;
+
+
+/// Language server for calc scripts, the documents are kept in memory and
+/// analyzed on every change without running them.
+pub struct Server {
+    state: State,
+    documents: HashMap<String, Document>,
+    library: Vec<String>,
+}
+
+
+impl Default for Server {
+    fn default() -> Self {
+        Server::new()
+    }
+}
+
+
+impl Server {
+    pub fn new() -> Self {
+        let mut ctx = ExecContext::new();
+        stdlib::register(&mut ctx);
+
+        let mut library: Vec<String> = ctx.prelude.vals.borrow().iter()
+            .filter(|(_, val)| matches!(val, Val::NativeFunc(..)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        library.sort();
+
+        Server {
+            state: State::Created,
+            documents: HashMap::new(),
+            library,
+        }
+    }
+
+    /// Serve the messages of the input until `exit` or the end of the input.
+    /// Returns the exit code: 0 after a `shutdown` request, 1 otherwise.
+    pub fn run<R: BufRead, W: Write>(&mut self, mut input: R, mut output: W) -> io::Result<i32> {
+        while let Some(body) = read_message(&mut input)? {
+            let replies = match Json::parse(&body) {
+                Ok(msg) => {
+                    if msg.get("method").and_then(Json::as_str) == Some("exit") {
+                        break;
+                    }
+                    self.handle(&msg)
+                }
+                Err(err) => vec![error_response(Json::Null, PARSE_ERROR, &err)],
+            };
+
+            for reply in &replies {
+                write_message(&mut output, reply)?;
+            }
+        }
+
+        Ok(if self.state == State::ShutDown { 0 } else { 1 })
+    }
+
+    /// Responses and notifications to send for the client message.
+    pub fn handle(&mut self, msg: &Json) -> Vec<Json> {
+        let method = match msg.get("method").and_then(Json::as_str) {
+            Some(method) => method,
+            None => return Vec::new(),  // responses to our requests are not expected
+        };
+
+        let params = msg.get("params").unwrap_or(&Json::Null);
+
+        let id = match msg.get("id") {
+            Some(id) => id.clone(),
+            None => return self.notification(method, params),
+        };
+
+        let r = match (self.state, method) {
+            (State::Created, "initialize") => Ok(self.initialize()),
+            (State::Created, _) => Err((SERVER_NOT_INITIALIZED, String::from("server is not initialized"))),
+            (State::ShutDown, _) => Err((INVALID_REQUEST, String::from("server is shut down"))),
+            (State::Running, _) => self.request(method, params),
+        };
+
+        let response = match r {
+            Ok(result) => Json::object(vec![
+                ("jsonrpc", Json::from("2.0")),
+                ("id", id),
+                ("result", result),
+            ]),
+            Err((code, msg)) => error_response(id, code, &msg),
+        };
+
+        vec![response]
+    }
+
+    fn request(&mut self, method: &str, params: &Json) -> RequestResult {
+        match method {
+            "initialize" => Err((INVALID_REQUEST, String::from("server is already initialized"))),
+            "shutdown" => {
+                self.state = State::ShutDown;
+                Ok(Json::Null)
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/completion" => self.completion(params),
+            _ => Err((METHOD_NOT_FOUND, format!("'{}': unknown method", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        if self.state != State::Running {
+            return Vec::new();
+        }
+
+        let doc = params.get("textDocument");
+        let uri = doc.and_then(|doc| doc.get("uri")).and_then(Json::as_str);
+
+        let text = match method {
+            "textDocument/didOpen" => doc.and_then(|doc| doc.get("text")).and_then(Json::as_str),
+            // full synchronization: the last change has the whole text
+            "textDocument/didChange" => params.get("contentChanges")
+                .and_then(Json::as_array)
+                .and_then(|changes| changes.last())
+                .and_then(|change| change.get("text"))
+                .and_then(Json::as_str),
+            "textDocument/didClose" => {
+                return match uri {
+                    Some(uri) => {
+                        self.documents.remove(uri);
+                        vec![publish_diagnostics(uri, Vec::new())]
+                    }
+                    None => Vec::new(),
+                };
+            }
+            _ => None,
+        };
+
+        match (uri, text) {
+            (Some(uri), Some(text)) => {
+                let doc = Document::new(uri, String::from(text));
+                let msg = publish_diagnostics(uri, diagnostics(&doc));
+
+                self.documents.insert(String::from(uri), doc);
+                vec![msg]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn initialize(&mut self) -> Json {
+        self.state = State::Running;
+
+        Json::object(vec![
+            ("capabilities", Json::object(vec![
+                ("textDocumentSync", Json::Int(1)),
+                ("definitionProvider", Json::from(true)),
+                ("hoverProvider", Json::from(true)),
+                ("documentSymbolProvider", Json::from(true)),
+                ("completionProvider", Json::object(Vec::<(&str, Json)>::new())),
+            ])),
+            ("serverInfo", Json::object(vec![
+                ("name", Json::from("calc-lsp")),
+                ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+            ])),
+        ])
+    }
+
+    /// Document and byte offset of the `TextDocumentPositionParams`.
+    fn position_params(&self, params: &Json) -> Result<(&Document, usize), (i64, String)> {
+        let doc = self.document_params(params)?;
+
+        let pos = params.get("position");
+        let number = |name| pos.and_then(|pos| pos.get(name)).and_then(Json::as_i64);
+
+        match (number("line"), number("character")) {
+            (Some(line), Some(character)) if line >= 0 && character >= 0 => {
+                Ok((doc, doc.offset(line as usize, character as usize)))
+            }
+            _ => Err((INVALID_PARAMS, String::from("'position': line and character expected"))),
+        }
+    }
+
+    fn document_params(&self, params: &Json) -> Result<&Document, (i64, String)> {
+        let uri = params.get("textDocument")
+            .and_then(|doc| doc.get("uri"))
+            .and_then(Json::as_str)
+            .ok_or_else(|| (INVALID_PARAMS, String::from("'textDocument': uri expected")))?;
+
+        self.documents.get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("'{}': document is not open", uri)))
+    }
+
+    fn definition(&self, params: &Json) -> RequestResult {
+        let (doc, offset) = self.position_params(params)?;
+
+        Ok(match doc.def_at(offset) {
+            Some(def) => location(doc, def.span),
+            None => Json::Null,
+        })
+    }
+
+    fn hover(&self, params: &Json) -> RequestResult {
+        let (doc, offset) = self.position_params(params)?;
+
+        let (text, span) = if let Some(def) = doc.def_at(offset) {
+            let span = doc.ref_at(offset).map_or(def.span, |r| r.span);
+            (describe(def), span)
+        } else {
+            match doc.ref_at(offset) {
+                Some(r) if self.library.contains(&r.name) => {
+                    (format!("```calc\nfn {}(...)\n```\n\nlibrary function", r.name), r.span)
+                }
+                _ => return Ok(Json::Null),
+            }
+        };
+
+        Ok(Json::object(vec![
+            ("contents", Json::object(vec![
+                ("kind", Json::from("markdown")),
+                ("value", Json::from(text)),
+            ])),
+            ("range", range(doc, span)),
+        ]))
+    }
+
+    fn document_symbols(&self, params: &Json) -> RequestResult {
+        let doc = self.document_params(params)?;
+        Ok(Json::from(symbols(doc, 0)))
+    }
+
+    fn completion(&self, params: &Json) -> RequestResult {
+        let (doc, offset) = self.position_params(params)?;
+
+        let mut items: Vec<Json> = Vec::new();
+        let mut seen: Vec<&str> = Vec::new();
+
+        for def in doc.visible(offset) {
+            let kind = if def.kind == DefKind::Function { COMPLETION_FUNCTION } else { COMPLETION_VARIABLE };
+            items.push(completion_item(&def.name, kind, &def.detail));
+            seen.push(&def.name);
+        }
+
+        for name in &self.library {
+            if !seen.contains(&name.as_str()) {
+                items.push(completion_item(name, COMPLETION_FUNCTION, "library function"));
+            }
+        }
+
+        for keyword in &KEYWORDS {
+            items.push(completion_item(keyword, COMPLETION_KEYWORD, "keyword"));
+        }
+
+        Ok(Json::from(items))
+    }
+}
+
+
+fn error_response(id: Json, code: i64, msg: &str) -> Json {
+    Json::object(vec![
+        ("jsonrpc", Json::from("2.0")),
+        ("id", id),
+        ("error", Json::object(vec![
+            ("code", Json::from(code)),
+            ("message", Json::from(msg)),
+        ])),
+    ])
+}
+
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object(vec![
+        ("jsonrpc", Json::from("2.0")),
+        ("method", Json::from("textDocument/publishDiagnostics")),
+        ("params", Json::object(vec![
+            ("uri", Json::from(uri)),
+            ("diagnostics", Json::from(diagnostics)),
+        ])),
+    ])
+}
+
+
+fn position(doc: &Document, offset: usize) -> Json {
+    let (line, character) = doc.position(offset);
+
+    Json::object(vec![
+        ("line", Json::from(line)),
+        ("character", Json::from(character)),
+    ])
+}
+
+
+fn range(doc: &Document, span: (usize, usize)) -> Json {
+    Json::object(vec![
+        ("start", position(doc, span.0)),
+        ("end", position(doc, span.1)),
+    ])
+}
+
+
+fn location(doc: &Document, span: (usize, usize)) -> Json {
+    Json::object(vec![
+        ("uri", Json::from(doc.uri.as_str())),
+        ("range", range(doc, span)),
+    ])
+}
+
+
+/// Syntax errors and lint warnings of the document.
+fn diagnostics(doc: &Document) -> Vec<Json> {
+    let errors = doc.errors.iter().map(|err: &Error| {
+        diagnostic(doc, SEVERITY_ERROR, None, &err.description, &err.loc, &err.labels, &err.notes)
+    });
+
+    let warnings = doc.warnings.iter().map(|warn: &Warning| {
+        diagnostic(doc, SEVERITY_WARNING, Some(warn.lint.code()), &warn.description, &warn.loc, &warn.labels, &warn.notes)
+    });
+
+    errors.chain(warnings).collect()
+}
+
+
+fn diagnostic(
+    doc: &Document,
+    severity: i64,
+    code: Option<&str>,
+    description: &str,
+    loc: &::compiler::Loc,
+    labels: &[Label],
+    notes: &[Note]) -> Json
+{
+    let mut message = String::from(description);
+
+    for note in notes {
+        match *note {
+            Note::Info(ref text) => message.push_str(&format!("\nnote: {}", text)),
+            Note::Help(ref text) => message.push_str(&format!("\nhelp: {}", text)),
+        }
+    }
+
+    let mut members = vec![
+        ("range", range(doc, doc.loc_range(loc))),
+        ("severity", Json::from(severity)),
+        ("source", Json::from("calc")),
+        ("message", Json::from(message)),
+    ];
+
+    if let Some(code) = code {
+        members.push(("code", Json::from(code)));
+    }
+
+    if !labels.is_empty() {
+        let related = labels.iter()
+            .map(|label| Json::object(vec![
+                ("location", location(doc, doc.loc_range(&label.loc))),
+                ("message", Json::from(label.text.as_str())),
+            ]))
+            .collect();
+
+        members.push(("relatedInformation", Json::Array(related)));
+    }
+
+    Json::object(members)
+}
+
+
+/// Hover text: the declaration and the comment above a function.
+fn describe(def: &Def) -> String {
+    let mut text = format!("```calc\n{}\n```", def.detail);
+
+    if def.kind == DefKind::Argument {
+        text = format!("```calc\n{}\n```\n\n{}", def.name, def.detail);
+    }
+
+    if let Some(ref doc) = def.doc {
+        text.push_str("\n\n");
+        text.push_str(doc);
+    }
+
+    text
+}
+
+
+/// Declarations of the scope, functions with their own as children.
+fn symbols(doc: &Document, scope: usize) -> Vec<Json> {
+    doc.defs.iter()
+        .filter(|def| def.scope == scope)
+        .map(|def| {
+            let kind = if def.kind == DefKind::Function { SYMBOL_FUNCTION } else { SYMBOL_VARIABLE };
+
+            let mut members = vec![
+                ("name", Json::from(def.name.as_str())),
+                ("detail", Json::from(def.detail.as_str())),
+                ("kind", Json::from(kind)),
+                ("range", range(doc, def.range)),
+                ("selectionRange", range(doc, def.span)),
+            ];
+
+            if let Some(body) = def.body {
+                members.push(("children", Json::from(symbols(doc, body))));
+            }
+
+            Json::object(members)
+        })
+        .collect()
+}
+
+
+fn completion_item(label: &str, kind: i64, detail: &str) -> Json {
+    Json::object(vec![
+        ("label", Json::from(label)),
+        ("kind", Json::from(kind)),
+        ("detail", Json::from(detail)),
+    ])
+}
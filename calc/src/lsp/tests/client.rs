@@ -0,0 +1,103 @@
+use std::io::Cursor;
+use compiler::Json;
+use lsp::{Server, read_message};
+
+
+pub const URI: &str = "file:///test.calc";
+
+
+pub fn frame(body: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+
+pub fn request(id: i64, method: &str, params: Json) -> String {
+    frame(&Json::object(vec![
+        ("jsonrpc", Json::from("2.0")),
+        ("id", Json::from(id)),
+        ("method", Json::from(method)),
+        ("params", params),
+    ]).to_string())
+}
+
+
+pub fn notification(method: &str, params: Json) -> String {
+    frame(&Json::object(vec![
+        ("jsonrpc", Json::from("2.0")),
+        ("method", Json::from(method)),
+        ("params", params),
+    ]).to_string())
+}
+
+
+pub fn initialize() -> String {
+    request(0, "initialize", Json::object(vec![("capabilities", Json::object(Vec::<(&str, Json)>::new()))]))
+        + &notification("initialized", Json::object(Vec::<(&str, Json)>::new()))
+}
+
+
+pub fn did_open(text: &str) -> String {
+    notification("textDocument/didOpen", Json::object(vec![
+        ("textDocument", Json::object(vec![
+            ("uri", Json::from(URI)),
+            ("languageId", Json::from("calc")),
+            ("version", Json::from(1i64)),
+            ("text", Json::from(text)),
+        ])),
+    ]))
+}
+
+
+/// Request with `TextDocumentPositionParams`.
+pub fn at(id: i64, method: &str, line: i64, character: i64) -> String {
+    request(id, method, Json::object(vec![
+        ("textDocument", Json::object(vec![("uri", Json::from(URI))])),
+        ("position", Json::object(vec![
+            ("line", Json::from(line)),
+            ("character", Json::from(character)),
+        ])),
+    ]))
+}
+
+
+/// Feed the framed messages to a server, returns the messages it wrote
+/// and the exit code.
+pub fn session(input: &str) -> (Vec<Json>, i32) {
+    let mut output = Vec::new();
+    let code = Server::new().run(Cursor::new(input.as_bytes()), &mut output).unwrap();
+
+    let mut replies = Vec::new();
+    let mut output = Cursor::new(output);
+
+    while let Some(body) = read_message(&mut output).unwrap() {
+        replies.push(Json::parse(&body).unwrap());
+    }
+
+    (replies, code)
+}
+
+
+/// Result of the response to the request.
+pub fn result(replies: &[Json], id: i64) -> &Json {
+    let reply = replies.iter()
+        .find(|reply| reply.get("id") == Some(&Json::from(id)))
+        .unwrap_or_else(|| panic!("no response to request {}: {:?}", id, replies));
+
+    reply.get("result").unwrap_or_else(|| panic!("request {} failed: {}", id, reply))
+}
+
+
+/// Parameters of the published diagnostics, in the order of publishing.
+pub fn diagnostics(replies: &[Json]) -> Vec<&Json> {
+    replies.iter()
+        .filter(|reply| reply.get("method").and_then(Json::as_str) == Some("textDocument/publishDiagnostics"))
+        .map(|reply| reply.get("params").unwrap())
+        .collect()
+}
+
+
+/// `[line, character, line, character]` of the LSP range.
+pub fn span(range: &Json) -> [i64; 4] {
+    let pos = |name, member| range.get(name).and_then(|pos: &Json| pos.get(member)).and_then(Json::as_i64).unwrap();
+    [pos("start", "line"), pos("start", "character"), pos("end", "line"), pos("end", "character")]
+}
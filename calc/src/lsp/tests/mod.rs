@@ -0,0 +1,5 @@
+
+mod client;
+
+mod test_protocol;
+mod test_features;
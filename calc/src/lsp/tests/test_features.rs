@@ -0,0 +1,181 @@
+use compiler::Json;
+use super::client::*;
+
+
+const PROGRAM: &str = "\
+// Adds two numbers.
+// Returns the sum.
+fn add(a, b) {
+    let sum = a + b;
+    return sum;
+}
+
+let total = add(1, 2);
+let ё = total;
+abs(ё) + total;
+";
+
+
+/// Values of the member in the array of objects.
+fn members<'a>(items: &'a Json, name: &str) -> Vec<&'a str> {
+    items.as_array().unwrap().iter()
+        .map(|item| item.get(name).and_then(Json::as_str).unwrap())
+        .collect()
+}
+
+
+#[test]
+fn publish_diagnostics() {
+    let input = initialize()
+        + &did_open("let x = ;\nlet unused = 1;\nfn f() {\n    let unused = 2;\n    return unused;\n}\nf();\n")
+        + &notification("textDocument/didChange", Json::object(vec![
+            ("textDocument", Json::object(vec![("uri", Json::from(URI)), ("version", Json::from(2i64))])),
+            ("contentChanges", Json::Array(vec![Json::object(vec![("text", Json::from("let x = 1;\nlet unused = x;\n"))])])),
+        ]))
+        + &notification("textDocument/didClose", Json::object(vec![
+            ("textDocument", Json::object(vec![("uri", Json::from(URI))])),
+        ]));
+    let (replies, _) = session(&input);
+
+    let published = diagnostics(&replies);
+    assert_eq!(published.len(), 3);
+    assert_eq!(published[0].get("uri").and_then(Json::as_str), Some(URI));
+
+    let list = published[0].get("diagnostics").and_then(Json::as_array).unwrap();
+    let field = |idx: usize, name| list[idx].get(name).cloned().unwrap_or(Json::Null);
+
+    // unused names are not reported while there are syntax errors
+    assert_eq!(list.len(), 2);
+
+    assert_eq!(field(0, "severity"), Json::Int(1));
+    assert_eq!(field(0, "message"), Json::from("';': unexpected token"));
+    assert_eq!(span(&field(0, "range")), [0, 8, 0, 9]);
+
+    assert_eq!(field(1, "severity"), Json::Int(2));
+    assert_eq!(field(1, "code"), Json::from("W005"));
+    assert_eq!(span(&field(1, "range")), [3, 8, 3, 14]);
+    let related = field(1, "relatedInformation");
+    let related = &related.as_array().unwrap()[0];
+    assert_eq!(span(related.get("location").and_then(|loc| loc.get("range")).unwrap()), [1, 4, 1, 10]);
+    assert!(field(1, "message").as_str().unwrap().contains("\nnote: "));
+
+    // fixed
+    let list = published[1].get("diagnostics").and_then(Json::as_array).unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].get("code"), Some(&Json::from("W001")));
+    assert_eq!(span(list[0].get("range").unwrap()), [1, 4, 1, 10]);
+
+    // and closed
+    assert_eq!(published[2].get("diagnostics"), Some(&Json::Array(Vec::new())));
+}
+
+
+#[test]
+fn incomplete_strings() {
+    let input = initialize()
+        + &did_open("let s = \"")
+        + &did_open("let t = 1;\nlet s = \"a\\q\";\nt;\n")
+        + &at(1, "textDocument/definition", 2, 0);
+    let (replies, _) = session(&input);
+
+    let messages: Vec<Vec<&str>> = diagnostics(&replies).iter()
+        .map(|published| members(published.get("diagnostics").unwrap(), "message"))
+        .collect();
+    assert_eq!(messages, [vec!["unterminated string literal"], vec!["'\\q': unknown escape sequence"]]);
+
+    // the rest of the document is still indexed
+    assert_eq!(span(result(&replies, 1).get("range").unwrap()), [0, 4, 0, 5]);
+}
+
+
+#[test]
+fn definition() {
+    let input = initialize()
+        + &did_open(PROGRAM)
+        + &at(1, "textDocument/definition", 4, 12)     // sum
+        + &at(2, "textDocument/definition", 7, 13)     // add
+        + &at(3, "textDocument/definition", 3, 14)     // a
+        + &at(4, "textDocument/definition", 9, 10)     // total after `ё`
+        + &at(5, "textDocument/definition", 9, 4)      // ё
+        + &at(6, "textDocument/definition", 6, 0)
+        + &at(7, "textDocument/definition", 9, 1);     // library function
+    let (replies, _) = session(&input);
+
+    let target = |id| {
+        let loc = result(&replies, id);
+        assert_eq!(loc.get("uri").and_then(Json::as_str), Some(URI));
+        span(loc.get("range").unwrap())
+    };
+
+    assert_eq!(target(1), [3, 8, 3, 11]);
+    assert_eq!(target(2), [2, 3, 2, 6]);
+    assert_eq!(target(3), [2, 7, 2, 8]);
+    assert_eq!(target(4), [7, 4, 7, 9]);
+    assert_eq!(target(5), [8, 4, 8, 5]);
+    assert_eq!(result(&replies, 6), &Json::Null);
+    assert_eq!(result(&replies, 7), &Json::Null);
+}
+
+
+#[test]
+fn hover() {
+    let input = initialize()
+        + &did_open(PROGRAM)
+        + &at(1, "textDocument/hover", 7, 13)
+        + &at(2, "textDocument/hover", 3, 18)
+        + &at(3, "textDocument/hover", 9, 1)
+        + &at(4, "textDocument/hover", 5, 0);
+    let (replies, _) = session(&input);
+
+    let text = |id| {
+        let hover = result(&replies, id);
+        hover.get("contents").and_then(|contents| contents.get("value")).and_then(Json::as_str).unwrap().to_string()
+    };
+
+    assert_eq!(text(1), "```calc\nfn add(a, b)\n```\n\nAdds two numbers.\nReturns the sum.");
+    assert_eq!(span(result(&replies, 1).get("range").unwrap()), [7, 12, 7, 15]);
+
+    assert_eq!(text(2), "```calc\nb\n```\n\nargument of add");
+    assert_eq!(text(3), "```calc\nfn abs(...)\n```\n\nlibrary function");
+    assert_eq!(result(&replies, 4), &Json::Null);
+}
+
+
+#[test]
+fn document_symbols() {
+    let input = initialize()
+        + &did_open(PROGRAM)
+        + &request(1, "textDocument/documentSymbol", Json::object(vec![
+            ("textDocument", Json::object(vec![("uri", Json::from(URI))])),
+        ]));
+    let (replies, _) = session(&input);
+
+    let symbols = result(&replies, 1);
+    assert_eq!(members(symbols, "name"), ["add", "total", "ё"]);
+
+    let add = &symbols.as_array().unwrap()[0];
+    assert_eq!(add.get("kind"), Some(&Json::Int(12)));
+    assert_eq!(add.get("detail").and_then(Json::as_str), Some("fn add(a, b)"));
+    assert_eq!(span(add.get("range").unwrap()), [2, 0, 5, 1]);
+    assert_eq!(span(add.get("selectionRange").unwrap()), [2, 3, 2, 6]);
+    assert_eq!(members(add.get("children").unwrap(), "name"), ["a", "b", "sum"]);
+}
+
+
+#[test]
+fn completion() {
+    let input = initialize()
+        + &did_open(PROGRAM)
+        + &at(1, "textDocument/completion", 4, 11)
+        + &at(2, "textDocument/completion", 9, 0);
+    let (replies, _) = session(&input);
+
+    let inside = members(result(&replies, 1), "label");
+    assert_eq!(&inside[..4], ["sum", "b", "a", "add"]);
+    assert!(!inside.contains(&"total"), "declared later: {:?}", inside);
+    assert!(inside.contains(&"abs") && inside.contains(&"let"), "{:?}", inside);
+
+    let outside = members(result(&replies, 2), "label");
+    assert_eq!(&outside[..3], ["ё", "total", "add"]);
+    assert!(!outside.contains(&"sum"), "local of the function: {:?}", outside);
+}
@@ -0,0 +1,74 @@
+use std::io::ErrorKind;
+use compiler::Json;
+use lsp::read_message;
+use super::client::*;
+
+
+#[test]
+fn framing() {
+    let input = "Content-Length: 2\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{}\r\ncontent-length: 6\r\n\r\n\"ёж\"";
+    let mut input = input.as_bytes();
+
+    assert_eq!(read_message(&mut input).unwrap(), Some(String::from("{}")));
+    assert_eq!(read_message(&mut input).unwrap(), Some(String::from("\"ёж\"")));
+    assert_eq!(read_message(&mut input).unwrap(), None);
+
+    assert!(read_message(&mut "Content-Length: x\r\n\r\n".as_bytes()).is_err());
+    assert!(read_message(&mut "Content-Length: 10\r\n\r\n{}".as_bytes()).is_err());
+
+    let err = read_message(&mut "Content-Length: 99999999999\r\n\r\n{}".as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+
+#[test]
+fn lifecycle() {
+    let input = initialize()
+        + &request(1, "shutdown", Json::Null)
+        + &notification("exit", Json::Null);
+    let (replies, code) = session(&input);
+
+    let caps = result(&replies, 0).get("capabilities").unwrap();
+    assert_eq!(caps.get("textDocumentSync"), Some(&Json::Int(1)));
+    assert_eq!(caps.get("hoverProvider"), Some(&Json::Bool(true)));
+
+    assert_eq!(result(&replies, 1), &Json::Null);
+    assert_eq!(code, 0);
+
+    // without `shutdown` the exit is abnormal, so is the end of the input
+    assert_eq!(session(&(initialize() + &notification("exit", Json::Null))).1, 1);
+    assert_eq!(session(&initialize()).1, 1);
+}
+
+
+#[test]
+fn errors() {
+    let error_code = |replies: &[Json], id: Json| {
+        replies.iter()
+            .find(|reply| reply.get("id") == Some(&id))
+            .and_then(|reply| reply.get("error"))
+            .and_then(|err| err.get("code"))
+            .and_then(Json::as_i64)
+    };
+
+    let input = request(1, "textDocument/hover", Json::Null)
+        + &initialize()
+        + &frame("{not json")
+        + &request(2, "workspace/unknown", Json::Null)
+        + &notification("$/unknown", Json::Null)
+        + &request(3, "textDocument/definition", Json::Null)
+        + &at(4, "textDocument/hover", 0, 0)
+        + &request(5, "shutdown", Json::Null)
+        + &request(6, "textDocument/hover", Json::Null);
+    let (replies, _) = session(&input);
+
+    assert_eq!(error_code(&replies, Json::Int(1)), Some(-32002));
+    assert_eq!(error_code(&replies, Json::Null), Some(-32700));
+    assert_eq!(error_code(&replies, Json::Int(2)), Some(-32601));
+    assert_eq!(error_code(&replies, Json::Int(3)), Some(-32602));
+    assert_eq!(error_code(&replies, Json::Int(4)), Some(-32602));    // document is not open
+    assert_eq!(error_code(&replies, Json::Int(6)), Some(-32600));
+
+    // notifications are never answered
+    assert_eq!(replies.len(), 8);
+}
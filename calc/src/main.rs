@@ -1,7 +1,403 @@
+extern crate calc;
 
-mod compiler;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::process;
+use calc::Engine;
+use calc::compiler::{self, stdlib, Val, Error, FileLoader, Renderer, Linter};
+use repl::Repl;
+
+mod repl;
+
+
+const EXIT_RUNTIME_ERROR: i32 = 1;
+const EXIT_PARSE_ERROR: i32 = 2;
+const EXIT_USAGE: i32 = 3;
+
+const USAGE: &str = "\
+usage: calc [script [args...]]     run the script, `-` or nothing reads stdin
+       calc -e <expr> [args...]    evaluate the expression
+       calc -i [args...]           start an interactive session (default on a terminal)
+       calc --lint [script | -e <expr>]   report syntax errors and warnings, don't run
+       calc --dump-ast [script | -e <expr>]   print the syntax tree as JSON, don't run
+       calc fmt [--check] [files...]      format the files in place (or stdin to stdout)
+
+options: --pretty    show errors with the source lines they point to, must come first (not with fmt)";
+
+
+/// Where the program text comes from.
+#[derive(Debug, PartialEq)]
+enum Source {
+    File(String),
+    Expr(String),
+    Stdin,
+    Repl,
+}
+
+
+/// What to do with the program text.
+#[derive(Debug, PartialEq)]
+enum Mode {
+    Run,
+    Lint,
+    DumpAst,
+}
+
+
+struct Options {
+    source: Source,
+    args: Vec<String>,
+    mode: Mode,
+    pretty: bool,   // render errors with source snippets
+}
+
+
+/// Parsed command line.
+enum Command {
+    Help,
+    Run(Options),
+}
+
+
+fn parse_args(mut args: Vec<String>) -> Result<Command, String> {
+    let pretty = args.first().is_some_and(|arg| arg == "--pretty");
+    if pretty {
+        args.remove(0);
+    }
+
+    let mode = match args.first().map(String::as_str) {
+        Some("--lint") => Mode::Lint,
+        Some("--dump-ast") => Mode::DumpAst,
+        _ => Mode::Run,
+    };
+
+    if mode != Mode::Run {
+        args.remove(0);
+    }
+
+    if args.is_empty() {
+        let source = if io::stdin().is_terminal() && mode == Mode::Run { Source::Repl } else { Source::Stdin };
+        return Ok(Command::Run(Options { source, args, mode, pretty }));
+    }
+
+    let first = args.remove(0);
+
+    let source = match first.as_str() {
+        "-h" | "--help" => return Ok(Command::Help),
+        "-e" => {
+            if args.is_empty() {
+                return Err(String::from("'-e': expression expected"));
+            }
+            Source::Expr(args.remove(0))
+        }
+        "-i" if mode == Mode::Run => Source::Repl,
+        "-" => Source::Stdin,
+        _ if first.starts_with('-') => return Err(format!("'{}': unknown option", first)),
+        _ => Source::File(first),
+    };
+
+    Ok(Command::Run(Options { source, args, mode, pretty }))
+}
+
+
+fn read_source(source: &Source) -> io::Result<(String, String)> {
+    match *source {
+        Source::File(ref path) => Ok((fs::read_to_string(path)?, path.clone())),
+        Source::Expr(ref text) => Ok((text.clone(), String::from("<expr>"))),
+        Source::Stdin => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            Ok((text, String::from("<stdin>")))
+        }
+        Source::Repl => unreachable!("interactive session has no program text"),
+    }
+}
+
+
+fn new_engine(args: Vec<String>) -> Engine {
+    let mut engine = Engine::new();
+    stdlib::register(engine.context_mut());
+
+    engine.context_mut().decl_func("print", |args: Vec<Val>| {
+        let parts: Vec<String> = args.iter()
+            .map(|arg| match arg.as_str() {
+                Some(text) => text.clone(),
+                None => arg.to_string(),
+            })
+            .collect();
+
+        println!("{}", parts.join(" "));
+        Val::None
+    });
+
+    engine.set_global("args", args);
+    engine.set_module_loader(FileLoader::new("."));
+    engine
+}
+
+
+/// Print the error as `file(line): message`, or with the source line it
+/// points to when `pretty` is set.
+fn report(err: &Error, filename: &str, text: &str, pretty: bool) {
+    if !pretty {
+        eprintln!("{}", err);
+        return;
+    }
+
+    let out = Renderer::new()
+        .source(filename, text)
+        .color(io::stderr().is_terminal())
+        .render(err);
+
+    eprint!("{}", out);
+}
+
+
+fn run(opts: Options) -> i32 {
+    if let Source::Repl = opts.source {
+        return match Repl::new(opts.args, opts.pretty).run() {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("calc: {}", err);
+                EXIT_USAGE
+            }
+        };
+    }
+
+    let (text, filename) = match read_source(&opts.source) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("calc: can't read program: {}", err);
+            return EXIT_USAGE;
+        }
+    };
+
+    if opts.mode == Mode::Lint {
+        return lint(&text, &filename, opts.pretty);
+    }
+
+    let mut engine = new_engine(opts.args);
+
+    let prog = match engine.parse(&text, &filename) {
+        Ok(prog) => prog,
+        Err(err) => {
+            // show all syntax errors of a script at once
+            let errors = match opts.source {
+                Source::Expr(_) => compiler::parse_expr(&text, &filename).err().into_iter().collect(),
+                _ => compiler::parse_recover(&text, &filename).1,
+            };
+            let errors = if errors.is_empty() { vec![err] } else { errors };
+
+            for err in &errors {
+                report(err, &filename, &text, opts.pretty);
+            }
+            return EXIT_PARSE_ERROR;
+        }
+    };
+
+    if opts.mode == Mode::DumpAst {
+        println!("{}", compiler::ast_to_json(&prog).pretty());
+        return 0;
+    }
+
+    match engine.execute(&prog) {
+        Ok(Val::None) => 0,
+        Ok(val) => {
+            println!("{}", val);
+            0
+        }
+        Err(err) => {
+            report(&err, &filename, &text, opts.pretty);
+            EXIT_RUNTIME_ERROR
+        }
+    }
+}
+
+
+/// Report syntax errors and warnings without running the program.
+fn lint(text: &str, filename: &str, pretty: bool) -> i32 {
+    let renderer = Renderer::new()
+        .source(filename, text)
+        .color(io::stderr().is_terminal());
+
+    let (ast, errors) = compiler::parse_recover(text, filename);
+
+    for err in &errors {
+        report(err, filename, text, pretty);
+    }
+
+    let mut linter = match Linter::new().allow_comments(text) {
+        Ok(linter) => linter,
+        Err(msg) => {
+            eprintln!("calc: {}: {}", filename, msg);
+            return EXIT_USAGE;
+        }
+    };
+
+    if !errors.is_empty() {
+        linter = linter.allow_unused();
+    }
+
+    for warn in linter.check(&ast) {
+        if pretty {
+            eprint!("{}", renderer.render_warning(&warn));
+        } else {
+            eprintln!("{}", warn);
+        }
+    }
+
+    if errors.is_empty() { 0 } else { EXIT_PARSE_ERROR }
+}
+
+
+/// Rewrite the files in the canonical layout, with `--check` only report
+/// the files that would change.
+fn fmt(mut files: Vec<String>) -> i32 {
+    let check = files.first().is_some_and(|arg| arg == "--check");
+    if check {
+        files.remove(0);
+    }
+
+    let sources = if files.is_empty() {
+        vec![Source::Stdin]
+    } else {
+        files.into_iter().map(Source::File).collect()
+    };
+
+    let mut code = 0;
+
+    for source in &sources {
+        let (text, filename) = match read_source(source) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("calc: can't read program: {}", err);
+                return EXIT_USAGE;
+            }
+        };
+
+        let formatted = match compiler::format(&text, &filename) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                report(&err, &filename, &text, false);
+                code = EXIT_PARSE_ERROR;
+                continue;
+            }
+        };
+
+        match *source {
+            _ if check => {
+                if formatted != text {
+                    println!("{}: not formatted", filename);
+                    code = code.max(1);
+                }
+            }
+            Source::File(ref path) => {
+                if formatted != text {
+                    if let Err(err) = fs::write(path, formatted) {
+                        eprintln!("calc: {}: can't write file: {}", path, err);
+                        return EXIT_USAGE;
+                    }
+                }
+            }
+            _ => print!("{}", formatted),
+        }
+    }
+
+    code
+}
 
 
 fn main() {
-    println!("Hello, world!");
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    if args.first().is_some_and(|arg| arg == "fmt") {
+        args.remove(0);
+        process::exit(fmt(args));
+    }
+
+    let opts = match parse_args(args) {
+        Ok(Command::Run(opts)) => opts,
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            process::exit(0);
+        }
+        Err(msg) => {
+            eprintln!("calc: {}\n{}", msg, USAGE);
+            process::exit(EXIT_USAGE);
+        }
+    };
+
+    process::exit(run(opts));
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        match parse_args(args.iter().map(|arg| String::from(*arg)).collect())? {
+            Command::Run(opts) => Ok(opts),
+            Command::Help => Err(String::from("help")),
+        }
+    }
+
+
+    #[test]
+    fn sources() {
+        let opts = parse(&["script.calc", "a", "-b"]).unwrap();
+        assert_eq!(Source::File(String::from("script.calc")), opts.source);
+        assert_eq!(vec!["a", "-b"], opts.args);
+        assert_eq!(Mode::Run, opts.mode);
+
+        let opts = parse(&["-e", "1 + 2", "a"]).unwrap();
+        assert_eq!(Source::Expr(String::from("1 + 2")), opts.source);
+        assert_eq!(vec!["a"], opts.args);
+
+        assert_eq!(Source::Stdin, parse(&["-"]).unwrap().source);
+        assert_eq!(Source::Repl, parse(&["-i", "a"]).unwrap().source);
+    }
+
+
+    #[test]
+    fn modes() {
+        let opts = parse(&["--lint", "script.calc"]).unwrap();
+        assert_eq!(Mode::Lint, opts.mode);
+        assert_eq!(Source::File(String::from("script.calc")), opts.source);
+
+        let opts = parse(&["--dump-ast", "-e", "1"]).unwrap();
+        assert_eq!(Mode::DumpAst, opts.mode);
+        assert_eq!(Source::Expr(String::from("1")), opts.source);
+
+        // nothing to run interactively
+        assert_eq!(Source::Stdin, parse(&["--lint"]).unwrap().source);
+        assert_eq!(Err(String::from("'-i': unknown option")), parse(&["--lint", "-i"]).map(|_| ()));
+    }
+
+
+    #[test]
+    fn pretty_errors() {
+        assert!(!parse(&["script.calc"]).unwrap().pretty);
+
+        let opts = parse(&["--pretty", "--lint", "script.calc"]).unwrap();
+        assert!(opts.pretty);
+        assert_eq!(Mode::Lint, opts.mode);
+        assert_eq!(Source::File(String::from("script.calc")), opts.source);
+
+        let opts = parse(&["--pretty", "-e", "1"]).unwrap();
+        assert!(opts.pretty);
+        assert_eq!(Source::Expr(String::from("1")), opts.source);
+    }
+
+
+    #[test]
+    fn help_and_errors() {
+        assert!(matches!(parse_args(vec![String::from("-h")]), Ok(Command::Help)));
+        assert!(matches!(parse_args(vec![String::from("--help"), String::from("x")]), Ok(Command::Help)));
+
+        assert_eq!(Err(String::from("'-e': expression expected")), parse(&["-e"]).map(|_| ()));
+        assert_eq!(Err(String::from("'-x': unknown option")), parse(&["-x"]).map(|_| ()));
+    }
 }
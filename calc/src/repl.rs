@@ -0,0 +1,234 @@
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use calc::Engine;
+use calc::compiler::{self, Val, Error, Renderer};
+use super::new_engine;
+
+
+const HELP: &str = "\
+:vars          list global variables
+:load <file>   run the script in this session
+:ast <expr>    show the parsed syntax tree
+:reset         start over with a fresh context
+:help          show this help
+:quit          leave (or Ctrl-D)";
+
+
+/// Interactive session, all inputs share one `ExecContext`.
+pub struct Repl {
+    engine: Engine,
+    args: Vec<String>,
+    inputs: Vec<(String, String)>,  // file name and text, for error messages
+    pretty: bool,   // render errors with source snippets
+}
+
+
+/// Where the session writes values and errors.
+struct Output<'a> {
+    out: &'a mut dyn Write,
+    err: &'a mut dyn Write,
+    color: bool,    // of the errors
+}
+
+
+impl Repl {
+    pub fn new(args: Vec<String>, pretty: bool) -> Self {
+        Repl {
+            engine: new_engine(args.clone()),
+            args,
+            inputs: Vec::new(),
+            pretty,
+        }
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        let color = io::stderr().is_terminal();
+        self.run_with(io::stdin().lock(), &mut io::stdout(), &mut io::stderr(), color)
+    }
+
+    /// Read the input line by line until `:quit` or the end of the input.
+    pub fn run_with<R: BufRead>(&mut self, input: R, out: &mut dyn Write, err: &mut dyn Write, color: bool) -> io::Result<()> {
+        let mut output = Output { out, err, color };
+        let mut lines = input.lines();
+        let mut input = String::new();
+
+        loop {
+            output.prompt(if input.is_empty() { "> " } else { "... " })?;
+
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => break,
+            };
+
+            input.push_str(&line);
+            input.push('\n');
+
+            if compiler::is_incomplete(&input) {
+                continue;
+            }
+
+            let text = input.trim().to_string();
+            input.clear();
+
+            if text.is_empty() {
+                continue;
+            }
+
+            if text.starts_with(':') {
+                if !self.command(&text, &mut output)? {
+                    break;
+                }
+            } else {
+                let filename = format!("<repl:{}>", self.inputs.len() + 1);
+                self.eval(text, filename, &mut output)?;
+            }
+        }
+
+        writeln!(output.out)
+    }
+
+    /// Returns false when the session should end.
+    fn command(&mut self, text: &str, output: &mut Output) -> io::Result<bool> {
+        let (name, arg) = match text.find(char::is_whitespace) {
+            Some(pos) => (&text[..pos], text[pos..].trim()),
+            None => (text, ""),
+        };
+
+        match name {
+            ":quit" | ":q" => return Ok(false),
+            ":help" => writeln!(output.out, "{}", HELP)?,
+            ":vars" => self.print_vars(output)?,
+            ":reset" => {
+                self.engine = new_engine(self.args.clone());
+                self.inputs.clear();
+            }
+            ":load" if !arg.is_empty() => match fs::read_to_string(arg) {
+                Ok(text) => self.eval(text, String::from(arg), output)?,
+                Err(err) => writeln!(output.err, "'{}': can't read file: {}", arg, err)?,
+            },
+            ":ast" if !arg.is_empty() => match self.engine.parse(arg, "<repl>") {
+                Ok(ast) => writeln!(output.out, "{:#?}", ast)?,
+                Err(err) if self.pretty => write!(output.err, "{}", Renderer::new().source("<repl>", arg).color(output.color).render(&err))?,
+                Err(err) => writeln!(output.err, "{}", err)?,
+            },
+            ":load" | ":ast" => writeln!(output.err, "'{}': argument expected", name)?,
+            _ => writeln!(output.err, "'{}': unknown command, try :help", name)?,
+        }
+
+        Ok(true)
+    }
+
+    /// Run the input and print its value. The value is kept as `ans` in the
+    /// prelude, so scripts may still declare their own `ans`.
+    fn eval(&mut self, text: String, filename: String, output: &mut Output) -> io::Result<()> {
+        let r = self.engine.parse(&text, &filename)
+            .and_then(|prog| self.engine.execute(&prog));
+
+        // declarations may be referred to by later errors
+        self.inputs.push((filename, text));
+
+        match r {
+            Ok(Val::None) => Ok(()),
+            Ok(val) => {
+                writeln!(output.out, "{}", val)?;
+                self.engine.context_mut().decl_const("ans", val);
+                Ok(())
+            }
+            Err(err) => self.report(&err, output),
+        }
+    }
+
+    fn report(&self, err: &Error, output: &mut Output) -> io::Result<()> {
+        if !self.pretty {
+            return writeln!(output.err, "{}", err);
+        }
+
+        let renderer = self.inputs.iter()
+            .fold(Renderer::new(), |renderer, input| renderer.source(&input.0, &input.1))
+            .color(output.color);
+
+        write!(output.err, "{}", renderer.render(err))
+    }
+
+    /// Global variables in name order, the library functions and `ans` are
+    /// kept in the prelude and aren't listed.
+    fn print_vars(&self, output: &mut Output) -> io::Result<()> {
+        let vals = self.engine.context().scope.vals.borrow();
+        let mut names: Vec<&String> = vals.keys().collect();
+        names.sort();
+
+        for name in names {
+            writeln!(output.out, "{} = {}", name, vals[name])?;
+        }
+
+        Ok(())
+    }
+}
+
+
+impl<'a> Output<'a> {
+    fn prompt(&mut self, text: &str) -> io::Result<()> {
+        self.out.write_all(text.as_bytes())?;
+        self.out.flush()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Run a session over the input, return what it wrote to stdout and
+    /// stderr.
+    fn session(input: &str, pretty: bool) -> (String, String) {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+
+        Repl::new(vec![String::from("a")], pretty)
+            .run_with(input.as_bytes(), &mut out, &mut err, false)
+            .unwrap();
+
+        (String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+
+    #[test]
+    fn values_and_ans() {
+        let (out, err) = session("let x = 20;\nx + 1\nans * 2\nlet ans = 1;\nans\nans = 5;\n", false);
+
+        assert_eq!("> > 21\n> 42\n> > 1\n> > \n", out);
+        assert_eq!("", err);
+    }
+
+
+    #[test]
+    fn incomplete_input() {
+        let (out, err) = session("fn add(a, b) {\n  return a + b;\n}\nadd(1,\n 2)\n", false);
+
+        assert_eq!("> ... ... > ... 3\n> \n", out);
+        assert_eq!("", err);
+    }
+
+
+    #[test]
+    fn commands() {
+        let (out, err) = session("let y = 2;\n3\n:vars\n:reset\n:vars\n:nope\n:load\n:quit\n4\n", false);
+
+        assert_eq!("> > 3\n> args = [\"a\"]\ny = 2\n> > args = [\"a\"]\n> > > \n", out);
+        assert_eq!("':nope': unknown command, try :help\n':load': argument expected\n", err);
+    }
+
+
+    #[test]
+    fn errors() {
+        let (out, err) = session("let x = 1;\nx / 0\nx\n", false);
+
+        assert_eq!("> > > 1\n> \n", out);
+        assert_eq!("<repl:2>(1): division by zero\n", err);
+
+        let (_, err) = session("let x = 1;\nlet x = 2;\n", true);
+        assert!(err.starts_with("error: 'x': redefinition\n --> <repl:2>:1:5\n"), "{}", err);
+        assert!(err.contains("<repl:1>:1:5"), "{}", err);
+    }
+}